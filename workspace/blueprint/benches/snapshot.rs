@@ -0,0 +1,35 @@
+use blueprint::{Blueprint, Index};
+use criterion::{criterion_group, criterion_main, Criterion};
+use valid::Validator;
+
+const SUPERGRAPH: &str = include_str!("../src/fixtures/router.graphql");
+
+// What a start pays for the Index with and without a snapshot of it.
+fn bench_load(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("router.index");
+    Index::load(&path, "router.graphql", SUPERGRAPH)
+        .to_result()
+        .unwrap();
+
+    let mut group = c.benchmark_group("load");
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            Blueprint::parse_source("router.graphql", SUPERGRAPH)
+                .to_result()
+                .unwrap()
+                .to_index()
+        })
+    });
+    group.bench_function("snapshot", |b| {
+        b.iter(|| {
+            Index::load(&path, "router.graphql", SUPERGRAPH)
+                .to_result()
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_load);
+criterion_main!(benches);
@@ -0,0 +1,399 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use valid::{Valid, Validator};
+
+use crate::build::at;
+use crate::{
+    Blueprint, Definition, Directive, DirectiveDefinition, EnumTypeDefinition, EnumValueDefinition,
+    FieldDefinition, InputFieldDefinition, InputObjectTypeDefinition, InterfaceTypeDefinition,
+    Links, ObjectTypeDefinition, Pos, ScalarTypeDefinition, SchemaDefinition, Type,
+    UnionTypeDefinition,
+};
+
+/// Derives the schema clients see from a supergraph, collecting every reason
+/// the result would not be a valid schema.
+pub fn api_schema(blueprint: &Blueprint) -> Valid<Blueprint, String> {
+    let api = Api::new(blueprint);
+
+    api.validate().map(|_| Blueprint {
+        definitions: blueprint
+            .definitions
+            .iter()
+            .filter(|definition| api.is_public(definition.name()))
+            .map(|definition| Arc::new(api.definition(definition)))
+            .collect(),
+        schema: SchemaDefinition {
+            directives: api.directives(&blueprint.schema.directives),
+            ..blueprint.schema.clone()
+        },
+        directives: blueprint
+            .directives
+            .iter()
+            .filter(|definition| !api.machinery.owns(&definition.name))
+            .map(|definition| DirectiveDefinition {
+                arguments: api.inputs(&definition.arguments),
+                ..definition.clone()
+            })
+            .collect(),
+        join_graphs: Vec::new(),
+        links: Links::default(),
+        source: blueprint.source.clone(),
+    })
+}
+
+// The names owned by the specs a supergraph `@link`s. A spec owns the
+// directive named after it, everything under its `namespace__` prefix and
+// what it imports: `@link(url: ".../join/v0.3")` owns `@join__type` and
+// `join__Graph`.
+struct Machinery<'a> {
+    links: &'a Links,
+    inaccessible: String,
+}
+
+impl<'a> Machinery<'a> {
+    fn new(links: &'a Links) -> Self {
+        Machinery {
+            links,
+            inaccessible: links.directive("inaccessible", "inaccessible"),
+        }
+    }
+
+    fn owns(&self, name: &str) -> bool {
+        self.links.owns(name)
+    }
+}
+
+struct Api<'a> {
+    blueprint: &'a Blueprint,
+    machinery: Machinery<'a>,
+    inaccessible_types: BTreeSet<&'a str>,
+}
+
+impl<'a> Api<'a> {
+    fn new(blueprint: &'a Blueprint) -> Self {
+        let machinery = Machinery::new(&blueprint.links);
+        let inaccessible_types = blueprint
+            .definitions
+            .iter()
+            .filter(|definition| {
+                definition
+                    .directives()
+                    .iter()
+                    .any(|directive| directive.name == machinery.inaccessible)
+            })
+            .map(|definition| definition.name())
+            .collect();
+
+        Api { blueprint, machinery, inaccessible_types }
+    }
+
+    fn is_public(&self, type_name: &str) -> bool {
+        !self.machinery.owns(type_name) && !self.inaccessible_types.contains(type_name)
+    }
+
+    fn is_accessible(&self, directives: &[Directive]) -> bool {
+        !directives
+            .iter()
+            .any(|directive| directive.name == self.machinery.inaccessible)
+    }
+
+    fn directives(&self, directives: &[Directive]) -> Vec<Directive> {
+        directives
+            .iter()
+            .filter(|directive| !self.machinery.owns(&directive.name))
+            .cloned()
+            .collect()
+    }
+
+    fn inputs(&self, inputs: &[InputFieldDefinition]) -> Vec<InputFieldDefinition> {
+        inputs
+            .iter()
+            .filter(|input| self.is_accessible(&input.directives))
+            .map(|input| InputFieldDefinition {
+                directives: self.directives(&input.directives),
+                join_fields: Vec::new(),
+                ..input.clone()
+            })
+            .collect()
+    }
+
+    fn fields(&self, fields: &[FieldDefinition]) -> Vec<FieldDefinition> {
+        fields
+            .iter()
+            .filter(|field| self.is_accessible(&field.directives))
+            .map(|field| FieldDefinition {
+                args: self.inputs(&field.args),
+                directives: self.directives(&field.directives),
+                join_fields: Vec::new(),
+                ..field.clone()
+            })
+            .collect()
+    }
+
+    fn definition(&self, definition: &Definition) -> Definition {
+        match definition {
+            Definition::Object(def) => Definition::Object(ObjectTypeDefinition {
+                fields: self.fields(&def.fields),
+                directives: self.directives(&def.directives),
+                implements: def
+                    .implements
+                    .iter()
+                    .filter(|interface| self.is_public(interface))
+                    .cloned()
+                    .collect(),
+                join_types: Vec::new(),
+                join_implements: Vec::new(),
+                ..def.clone()
+            }),
+            Definition::Interface(def) => Definition::Interface(InterfaceTypeDefinition {
+                fields: self.fields(&def.fields),
+                directives: self.directives(&def.directives),
+                implements: def
+                    .implements
+                    .iter()
+                    .filter(|interface| self.is_public(interface))
+                    .cloned()
+                    .collect(),
+                join_types: Vec::new(),
+                join_implements: Vec::new(),
+                ..def.clone()
+            }),
+            Definition::InputObject(def) => Definition::InputObject(InputObjectTypeDefinition {
+                fields: self.inputs(&def.fields),
+                directives: self.directives(&def.directives),
+                join_types: Vec::new(),
+                ..def.clone()
+            }),
+            Definition::Scalar(def) => Definition::Scalar(ScalarTypeDefinition {
+                directives: self.directives(&def.directives),
+                join_types: Vec::new(),
+                ..def.clone()
+            }),
+            Definition::Enum(def) => Definition::Enum(EnumTypeDefinition {
+                directives: self.directives(&def.directives),
+                enum_values: def
+                    .enum_values
+                    .iter()
+                    .filter(|value| self.is_accessible(&value.directives))
+                    .map(|value| EnumValueDefinition {
+                        directives: self.directives(&value.directives),
+                        join_enums: Vec::new(),
+                        ..value.clone()
+                    })
+                    .collect(),
+                join_types: Vec::new(),
+                ..def.clone()
+            }),
+            Definition::Union(def) => Definition::Union(UnionTypeDefinition {
+                directives: self.directives(&def.directives),
+                types: def
+                    .types
+                    .iter()
+                    .filter(|member| self.is_public(member))
+                    .cloned()
+                    .collect(),
+                join_types: Vec::new(),
+                join_unions: Vec::new(),
+                ..def.clone()
+            }),
+        }
+    }
+
+    fn validate(&self) -> Valid<(), String> {
+        let schema = &self.blueprint.schema;
+        let roots = [&schema.query, &schema.mutation, &schema.subscription]
+            .into_iter()
+            .flatten()
+            .filter(|root| self.inaccessible_types.contains(root.as_str()));
+
+        Valid::from_iter(roots, |root| {
+            self.fail::<()>(
+                "Root types can not be @inaccessible".to_string(),
+                self.type_pos(root),
+            )
+            .trace(root)
+        })
+        .and(Valid::from_iter(
+            self.blueprint
+                .definitions
+                .iter()
+                .filter(|definition| self.is_public(definition.name())),
+            |definition| {
+                self.validate_definition(definition)
+                    .trace(definition.name())
+            },
+        ))
+        .unit()
+    }
+
+    fn validate_definition(&self, definition: &Definition) -> Valid<(), String> {
+        match definition {
+            Definition::Object(ObjectTypeDefinition { fields, pos, .. })
+            | Definition::Interface(InterfaceTypeDefinition { fields, pos, .. }) => self
+                .none_left(
+                    fields.iter().map(|field| &field.directives),
+                    "Every field is @inaccessible",
+                    *pos,
+                )
+                .and(Valid::from_iter(fields, |field| {
+                    self.validate_field(field).trace(&field.name)
+                }))
+                .unit(),
+            Definition::InputObject(def) => self
+                .none_left(
+                    def.fields.iter().map(|field| &field.directives),
+                    "Every field is @inaccessible",
+                    def.pos,
+                )
+                .and(Valid::from_iter(def.fields.iter(), |field| {
+                    self.validate_input(field, "Required input fields")
+                        .trace(&field.name)
+                }))
+                .unit(),
+            Definition::Enum(def) => self.none_left(
+                def.enum_values.iter().map(|value| &value.directives),
+                "Every value is @inaccessible",
+                def.pos,
+            ),
+            Definition::Union(def) => {
+                if !def.types.is_empty() && def.types.iter().all(|member| !self.is_public(member)) {
+                    self.fail("Every member is @inaccessible".to_string(), def.pos)
+                } else {
+                    Valid::succeed(())
+                }
+            }
+            Definition::Scalar(_) => Valid::succeed(()),
+        }
+    }
+
+    fn validate_field(&self, field: &FieldDefinition) -> Valid<(), String> {
+        if !self.is_accessible(&field.directives) {
+            return Valid::succeed(());
+        }
+
+        self.validate_type(&field.of_type, field.pos)
+            .and(Valid::from_iter(field.args.iter(), |arg| {
+                self.validate_input(arg, "Required arguments")
+                    .trace(&arg.name)
+            }))
+            .unit()
+    }
+
+    fn validate_input(&self, input: &InputFieldDefinition, required: &str) -> Valid<(), String> {
+        if self.is_accessible(&input.directives) {
+            self.validate_type(&input.of_type, input.pos)
+        } else if is_required(&input.of_type) && input.default_value.is_none() {
+            self.fail(format!("{} can not be @inaccessible", required), input.pos)
+        } else {
+            Valid::succeed(())
+        }
+    }
+
+    // A public element can't refer to a type that is not public.
+    fn validate_type(&self, of_type: &Type, pos: Option<Pos>) -> Valid<(), String> {
+        let type_name = of_type.as_type_str();
+        if self.inaccessible_types.contains(type_name.as_str()) {
+            self.fail(
+                format!("Type `{}` is @inaccessible but still reachable", type_name),
+                pos,
+            )
+        } else {
+            Valid::succeed(())
+        }
+    }
+
+    fn none_left<'b>(
+        &self,
+        mut elements: impl Iterator<Item = &'b Vec<Directive>>,
+        message: &str,
+        pos: Option<Pos>,
+    ) -> Valid<(), String> {
+        let mut any = false;
+        let none_left = elements.all(|directives| {
+            any = true;
+            !self.is_accessible(directives)
+        });
+
+        if any && none_left {
+            self.fail(message.to_string(), pos)
+        } else {
+            Valid::succeed(())
+        }
+    }
+
+    fn type_pos(&self, type_name: &str) -> Option<Pos> {
+        self.blueprint
+            .definitions
+            .iter()
+            .find(|definition| definition.name() == type_name)
+            .and_then(|definition| definition.pos())
+    }
+
+    fn fail<A>(&self, message: String, pos: Option<Pos>) -> Valid<A, String> {
+        at(Valid::fail(message), pos, self.blueprint.source.as_deref())
+    }
+}
+
+fn is_required(of_type: &Type) -> bool {
+    match of_type {
+        Type::Named { required, .. } => *required,
+        Type::List { non_null, .. } => *non_null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_schema() {
+        let blueprint = Blueprint::parse(include_str!("fixtures/router.graphql"))
+            .to_result()
+            .unwrap();
+        let api = api_schema(&blueprint).to_result().unwrap();
+
+        insta::assert_snapshot!(api.to_string());
+    }
+
+    #[test]
+    fn test_api_schema_errors() {
+        let graphql = r#"
+schema @link(url: "https://specs.apollo.dev/link/v1.0")
+  @link(url: "https://specs.apollo.dev/join/v0.3", for: EXECUTION)
+  @link(url: "https://specs.apollo.dev/inaccessible/v0.2", as: "hidden", for: SECURITY) {
+  query: Query
+}
+
+directive @hidden on FIELD_DEFINITION | OBJECT | ARGUMENT_DEFINITION | ENUM_VALUE
+
+enum join__Graph {
+  A @join__graph(name: "a", url: "http://localhost:4001")
+}
+
+type Query {
+  user(id: ID! @hidden, kind: Kind): User
+  secret: Secret @hidden
+}
+
+type User {
+  id: ID! @hidden
+  secret: Secret
+}
+
+type Secret @hidden {
+  value: String
+}
+
+enum Kind {
+  ADMIN @hidden
+}
+"#;
+        let blueprint = Blueprint::parse_source("router.graphql", graphql)
+            .to_result()
+            .unwrap();
+        let errors = api_schema(&blueprint).to_result().unwrap_err();
+
+        insta::assert_snapshot!(errors);
+    }
+}
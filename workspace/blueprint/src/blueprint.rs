@@ -1,25 +1,36 @@
 use std::collections::BTreeSet;
+use std::sync::Arc;
 
 use async_graphql_parser::types::ServiceDocument;
+use async_graphql_parser::Positioned;
+use async_graphql_value::ConstValue;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use valid::{Valid, ValidateFrom, Validator};
 
-use crate::error::Error;
 use crate::index::Index;
+use crate::{Contract, Diff, Links, Name, Pos, Subgraph};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Blueprint {
-    pub definitions: Vec<Definition>,
+    pub definitions: Vec<Arc<Definition>>,
     pub schema: SchemaDefinition,
     pub directives: Vec<DirectiveDefinition>,
     pub join_graphs: Vec<JoinGraph>,
+    /// The specs the schema `@link`s, naming the directives and types it
+    /// reads them through.
+    pub links: Links,
+    /// Name of the document the Blueprint was read from, used when reporting
+    /// positions.
+    pub source: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq)]
-pub struct Graph(String);
+pub struct Graph(Name);
 impl Graph {
     pub fn new<A: AsRef<str>>(name: A) -> Self {
-        Graph(name.as_ref().to_string())
+        Graph(Name::new(name.as_ref()))
     }
     pub fn as_str(&self) -> &str {
         &self.0
@@ -33,13 +44,109 @@ pub struct JoinGraph {
 }
 
 impl Blueprint {
-    pub fn parse_doc(doc: ServiceDocument) -> Blueprint {
-        super::build::parse(doc)
+    /// Reads a supergraph from a document already parsed from `schema`. The
+    /// text is needed as the parser reports every directive as `repeatable`.
+    pub fn parse_doc(doc: ServiceDocument, schema: &str) -> Valid<Blueprint, String> {
+        super::build::parse_document(doc, schema, None)
     }
 
-    pub fn parse(schema: &str) -> Result<Blueprint, Error> {
-        let document = async_graphql_parser::parse_schema(schema)?;
-        Ok(Blueprint::parse_doc(document))
+    pub fn parse(schema: &str) -> Valid<Blueprint, String> {
+        super::build::parse_text(schema, None)
+    }
+
+    /// Parses a supergraph read from `source`, so that errors and positions
+    /// can be reported as `source:line:col`.
+    pub fn parse_source(source: &str, schema: &str) -> Valid<Blueprint, String> {
+        super::build::parse_text(schema, Some(source))
+    }
+
+    /// Composes a supergraph from the schemas of its subgraphs.
+    pub fn compose(subgraphs: &[Subgraph]) -> Valid<Blueprint, String> {
+        super::compose::compose(subgraphs)
+    }
+
+    /// Derives the API schema: what clients see of the supergraph, without
+    /// the `join__*`/`link__*` machinery and the `@inaccessible` elements.
+    pub fn to_api_schema(&self) -> Valid<Blueprint, String> {
+        super::api::api_schema(self)
+    }
+
+    /// Lists the changes from this Blueprint to `new`, classified by how they
+    /// affect clients.
+    pub fn diff(&self, new: &Blueprint) -> Diff {
+        super::diff::diff(self, new)
+    }
+
+    /// Derives the supergraph of a contract variant, see [Contract].
+    pub fn contract(&self, contract: &Contract) -> Valid<Blueprint, String> {
+        super::contract::contract(self, contract)
+    }
+
+    /// Renders the position of an element of this Blueprint.
+    pub fn location(&self, pos: &Pos) -> String {
+        pos.in_source(self.source.as_deref())
+    }
+
+    /// Drops every position, for Blueprints that are not read from a document
+    /// or to compare Blueprints read from different documents.
+    pub(crate) fn forget_positions(&mut self) {
+        fn directives(directives: &mut [Directive]) {
+            directives
+                .iter_mut()
+                .for_each(|directive| directive.pos = None);
+        }
+        fn inputs(inputs: &mut [InputFieldDefinition]) {
+            for input in inputs {
+                input.pos = None;
+                directives(&mut input.directives);
+            }
+        }
+        fn fields(fields: &mut [FieldDefinition]) {
+            for field in fields {
+                field.pos = None;
+                directives(&mut field.directives);
+                inputs(&mut field.args);
+            }
+        }
+
+        directives(&mut self.schema.directives);
+        for definition in self.directives.iter_mut() {
+            inputs(&mut definition.arguments);
+        }
+        for definition in self.definitions.iter_mut() {
+            match Arc::make_mut(definition) {
+                Definition::Interface(def) => {
+                    def.pos = None;
+                    directives(&mut def.directives);
+                    fields(&mut def.fields);
+                }
+                Definition::Object(def) => {
+                    def.pos = None;
+                    directives(&mut def.directives);
+                    fields(&mut def.fields);
+                }
+                Definition::InputObject(def) => {
+                    def.pos = None;
+                    directives(&mut def.directives);
+                    inputs(&mut def.fields);
+                }
+                Definition::Scalar(def) => {
+                    def.pos = None;
+                    directives(&mut def.directives);
+                }
+                Definition::Enum(def) => {
+                    def.pos = None;
+                    directives(&mut def.directives);
+                    for value in def.enum_values.iter_mut() {
+                        directives(&mut value.directives);
+                    }
+                }
+                Definition::Union(def) => {
+                    def.pos = None;
+                    directives(&mut def.directives);
+                }
+            }
+        }
     }
 
     pub fn to_index(&self) -> Index {
@@ -57,40 +164,105 @@ pub enum Definition {
     Union(UnionTypeDefinition),
 }
 
+impl Definition {
+    pub fn name(&self) -> &str {
+        match self {
+            Definition::Interface(def) => &def.name,
+            Definition::Object(def) => &def.name,
+            Definition::InputObject(def) => &def.name,
+            Definition::Scalar(def) => &def.name,
+            Definition::Enum(def) => &def.name,
+            Definition::Union(def) => &def.name,
+        }
+    }
+
+    pub fn directives(&self) -> &[Directive] {
+        match self {
+            Definition::Interface(def) => &def.directives,
+            Definition::Object(def) => &def.directives,
+            Definition::InputObject(def) => &def.directives,
+            Definition::Scalar(def) => &def.directives,
+            Definition::Enum(def) => &def.directives,
+            Definition::Union(def) => &def.directives,
+        }
+    }
+
+    pub fn join_types(&self) -> &[JoinTypeParsed] {
+        match self {
+            Definition::Interface(def) => &def.join_types,
+            Definition::Object(def) => &def.join_types,
+            Definition::InputObject(def) => &def.join_types,
+            Definition::Scalar(def) => &def.join_types,
+            Definition::Enum(def) => &def.join_types,
+            Definition::Union(def) => &def.join_types,
+        }
+    }
+
+    pub(crate) fn directives_mut(&mut self) -> &mut Vec<Directive> {
+        match self {
+            Definition::Interface(def) => &mut def.directives,
+            Definition::Object(def) => &mut def.directives,
+            Definition::InputObject(def) => &mut def.directives,
+            Definition::Scalar(def) => &mut def.directives,
+            Definition::Enum(def) => &mut def.directives,
+            Definition::Union(def) => &mut def.directives,
+        }
+    }
+
+    pub fn pos(&self) -> Option<Pos> {
+        match self {
+            Definition::Interface(def) => def.pos,
+            Definition::Object(def) => def.pos,
+            Definition::InputObject(def) => def.pos,
+            Definition::Scalar(def) => def.pos,
+            Definition::Enum(def) => def.pos,
+            Definition::Union(def) => def.pos,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InterfaceTypeDefinition {
-    pub name: String,
+    pub name: Name,
     pub fields: Vec<FieldDefinition>,
+    pub directives: Vec<Directive>,
     pub description: Option<String>,
+    pub implements: BTreeSet<String>,
     pub join_types: Vec<JoinTypeParsed>,
     pub join_implements: Vec<JoinImplements>,
+    pub pos: Option<Pos>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ObjectTypeDefinition {
-    pub name: String,
+    pub name: Name,
     pub fields: Vec<FieldDefinition>,
+    pub directives: Vec<Directive>,
     pub description: Option<String>,
     pub implements: BTreeSet<String>,
     pub join_types: Vec<JoinTypeParsed>,
     pub join_implements: Vec<JoinImplements>,
+    pub pos: Option<Pos>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InputObjectTypeDefinition {
-    pub name: String,
+    pub name: Name,
     pub fields: Vec<InputFieldDefinition>,
+    pub directives: Vec<Directive>,
     pub description: Option<String>,
     pub join_types: Vec<JoinTypeParsed>,
+    pub pos: Option<Pos>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct EnumTypeDefinition {
-    pub name: String,
+    pub name: Name,
     pub directives: Vec<Directive>,
     pub description: Option<String>,
     pub enum_values: Vec<EnumValueDefinition>,
     pub join_types: Vec<JoinTypeParsed>,
+    pub pos: Option<Pos>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -111,27 +283,37 @@ pub struct SchemaDefinition {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InputFieldDefinition {
-    pub name: String,
+    pub name: Name,
     pub of_type: Type,
     pub default_value: Option<Value>,
+    pub directives: Vec<Directive>,
     pub description: Option<String>,
     pub join_fields: Vec<JoinFieldParsed>,
+    pub pos: Option<Pos>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FieldDefinition {
-    pub name: String,
+    pub name: Name,
     pub args: Vec<InputFieldDefinition>,
     pub of_type: Type,
     pub directives: Vec<Directive>,
     pub description: Option<String>,
     pub join_fields: Vec<JoinFieldParsed>,
+    pub pos: Option<Pos>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Directive {
     pub name: String,
     pub arguments: Value,
+    pub pos: Option<Pos>,
+}
+
+impl Directive {
+    pub fn new<A: AsRef<str>>(name: A, arguments: Value) -> Self {
+        Directive { name: name.as_ref().to_string(), arguments, pos: None }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -140,39 +322,124 @@ pub struct DirectiveDefinition {
     pub description: Option<String>,
     pub arguments: Vec<InputFieldDefinition>,
     pub repeatable: bool,
-    pub locations: Vec<String>,
+    pub locations: Vec<DirectiveLocation>,
+}
+
+/// Where a directive can be used, in a schema or in an operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum DirectiveLocation {
+    Query,
+    Mutation,
+    Subscription,
+    Field,
+    FragmentDefinition,
+    FragmentSpread,
+    InlineFragment,
+    VariableDefinition,
+    Schema,
+    Scalar,
+    Object,
+    FieldDefinition,
+    ArgumentDefinition,
+    Interface,
+    Union,
+    Enum,
+    EnumValue,
+    InputObject,
+    InputFieldDefinition,
+}
+
+impl DirectiveLocation {
+    /// Whether the location is in an operation rather than in a schema.
+    pub fn is_executable(&self) -> bool {
+        matches!(
+            self,
+            DirectiveLocation::Query
+                | DirectiveLocation::Mutation
+                | DirectiveLocation::Subscription
+                | DirectiveLocation::Field
+                | DirectiveLocation::FragmentDefinition
+                | DirectiveLocation::FragmentSpread
+                | DirectiveLocation::InlineFragment
+                | DirectiveLocation::VariableDefinition
+        )
+    }
+}
+
+impl From<async_graphql_parser::types::DirectiveLocation> for DirectiveLocation {
+    fn from(location: async_graphql_parser::types::DirectiveLocation) -> Self {
+        use async_graphql_parser::types::DirectiveLocation as L;
+
+        match location {
+            L::Query => DirectiveLocation::Query,
+            L::Mutation => DirectiveLocation::Mutation,
+            L::Subscription => DirectiveLocation::Subscription,
+            L::Field => DirectiveLocation::Field,
+            L::FragmentDefinition => DirectiveLocation::FragmentDefinition,
+            L::FragmentSpread => DirectiveLocation::FragmentSpread,
+            L::InlineFragment => DirectiveLocation::InlineFragment,
+            L::VariableDefinition => DirectiveLocation::VariableDefinition,
+            L::Schema => DirectiveLocation::Schema,
+            L::Scalar => DirectiveLocation::Scalar,
+            L::Object => DirectiveLocation::Object,
+            L::FieldDefinition => DirectiveLocation::FieldDefinition,
+            L::ArgumentDefinition => DirectiveLocation::ArgumentDefinition,
+            L::Interface => DirectiveLocation::Interface,
+            L::Union => DirectiveLocation::Union,
+            L::Enum => DirectiveLocation::Enum,
+            L::EnumValue => DirectiveLocation::EnumValue,
+            L::InputObject => DirectiveLocation::InputObject,
+            L::InputFieldDefinition => DirectiveLocation::InputFieldDefinition,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ScalarTypeDefinition {
-    pub name: String,
+    pub name: Name,
     pub directives: Vec<Directive>,
     pub description: Option<String>,
     pub join_types: Vec<JoinTypeParsed>,
+    pub pos: Option<Pos>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UnionTypeDefinition {
-    pub name: String,
+    pub name: Name,
     pub directives: Vec<Directive>,
     pub description: Option<String>,
     pub types: BTreeSet<String>,
     pub join_types: Vec<JoinTypeParsed>,
     pub join_unions: Vec<JoinUnion>,
+    pub pos: Option<Pos>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct JoinType {
     pub graph: Graph,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub key: Option<String>,
-    #[serde(default = "default_false")]
+    #[serde(default = "default_false", skip_serializing_if = "is_false")]
     pub extension: bool,
-    #[serde(default = "default_true")]
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
     pub resolvable: bool,
-    #[serde(default = "default_false")]
+    #[serde(default = "default_false", skip_serializing_if = "is_false")]
     pub is_interface_object: bool,
 }
 
+impl JoinType {
+    pub fn new(graph: Graph) -> Self {
+        Self {
+            graph,
+            key: None,
+            extension: false,
+            resolvable: true,
+            is_interface_object: false,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct JoinTypeParsed {
     pub graph: Graph,
@@ -185,24 +452,66 @@ pub struct JoinTypeParsed {
     pub is_interface_object: bool,
 }
 
-impl From<JoinType> for JoinTypeParsed {
-    fn from(value: JoinType) -> Self {
-        Self {
+impl ValidateFrom<JoinType> for JoinTypeParsed {
+    type Error = String;
+
+    fn validate_from(value: JoinType) -> Valid<Self, Self::Error> {
+        parse_query_string(value.key).trace("key").map(|key| Self {
             graph: value.graph,
-            key: parse_query_string(value.key),
+            key,
             extension: value.extension,
             resolvable: value.resolvable,
             is_interface_object: value.is_interface_object,
-        }
+        })
+    }
+}
+
+/// A field set, as `@key`, `@requires` and `@provides` take it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SelectionSet(pub Vec<Selection>);
+
+impl SelectionSet {
+    /// The fields selected at this level, through the inline fragments.
+    pub fn fields(&self) -> impl Iterator<Item = &Field> {
+        self.0.iter().flat_map(|selection| match selection {
+            Selection::Field(field) => vec![field],
+            Selection::InlineFragment(fragment) => fragment.selections.fields().collect(),
+        })
     }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct SelectionSet(pub Vec<Field>);
+#[serde(untagged)]
+pub enum Selection {
+    Field(Field),
+    InlineFragment(InlineFragment),
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Field {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
     pub name: String,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub arguments: IndexMap<String, ConstValue>,
+    pub selections: SelectionSet,
+}
+
+impl Field {
+    pub fn new(name: impl Into<String>, selections: SelectionSet) -> Self {
+        Field {
+            alias: None,
+            name: name.into(),
+            arguments: IndexMap::new(),
+            selections,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineFragment {
+    pub type_condition: Option<String>,
     pub selections: SelectionSet,
 }
 
@@ -210,7 +519,20 @@ fn default_true() -> bool {
     true
 }
 fn default_false() -> bool {
-    true
+    false
+}
+fn is_true(value: &bool) -> bool {
+    *value
+}
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// The graph owning an entity in federation 1 supergraphs, the others only
+/// extend it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JoinOwner {
+    pub graph: Graph,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -219,16 +541,41 @@ pub struct JoinEnum {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct JoinField {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub graph: Option<Graph>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub requires: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub provides: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub r#type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub external: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub r#override: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub override_label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub used_overridden: Option<bool>,
 }
 
+impl JoinField {
+    pub fn new(graph: Graph) -> Self {
+        Self {
+            graph: Some(graph),
+            requires: None,
+            provides: None,
+            r#type: None,
+            external: None,
+            r#override: None,
+            override_label: None,
+            used_overridden: None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct JoinFieldParsed {
     pub graph: Option<Graph>,
@@ -237,57 +584,188 @@ pub struct JoinFieldParsed {
     pub r#type: Option<String>,
     pub external: Option<bool>,
     pub r#override: Option<String>,
+    /// Makes the `@override` progressive: with the label the field is
+    /// resolved by the overriding graph, without it by the overridden one.
+    /// Both graphs' `@join__field`s carry it.
+    pub override_label: Option<OverrideLabel>,
     pub used_overridden: Option<bool>,
 }
 
-impl From<JoinField> for JoinFieldParsed {
-    fn from(value: JoinField) -> Self {
-        Self {
-            graph: value.graph,
-            requires: parse_query_string(value.requires),
-            provides: parse_query_string(value.provides),
-            r#type: value.r#type,
-            external: value.external,
-            r#override: value.r#override,
-            used_overridden: value.used_overridden,
+/// The label of a progressive `@override`, deciding for each request whether
+/// the field has moved to the overriding graph yet.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum OverrideLabel {
+    /// Set on the given percentage of requests.
+    Percent(f64),
+    /// Set by the context of a request.
+    Custom(String),
+}
+
+impl OverrideLabel {
+    pub fn parse(label: &str) -> Valid<OverrideLabel, String> {
+        if let Some(percent) = label
+            .strip_prefix("percent(")
+            .and_then(|label| label.strip_suffix(')'))
+        {
+            let (whole, decimals) = percent.split_once('.').unwrap_or((percent, "0"));
+            let digits = |digits: &str| digits.chars().all(|char| char.is_ascii_digit());
+            return match percent.parse::<f64>() {
+                Ok(value)
+                    if !whole.is_empty()
+                        && digits(whole)
+                        && (1..=8).contains(&decimals.len())
+                        && digits(decimals)
+                        && (0.0..=100.0).contains(&value) =>
+                {
+                    Valid::succeed(OverrideLabel::Percent(value))
+                }
+                _ => Valid::fail(format!(
+                    "Invalid override label `{}`: the percentage must be a number from 0 to \
+                     100, with at most 8 decimals",
+                    label
+                )),
+            };
+        }
+
+        let mut chars = label.chars();
+        let valid = chars.next().is_some_and(|char| char.is_ascii_alphabetic())
+            && chars.all(|char| char.is_ascii_alphanumeric() || "_-:./".contains(char));
+        if valid {
+            Valid::succeed(OverrideLabel::Custom(label.to_string()))
+        } else {
+            Valid::fail(format!(
+                "Invalid override label `{}`: a custom label starts with a letter, followed by \
+                 letters, digits and `_-:./`",
+                label
+            ))
         }
     }
 }
 
-fn parse_query_string(query: Option<String>) -> Option<SelectionSet> {
-    fn recursive_extract_data(
-        selection_set: async_graphql_parser::Positioned<async_graphql_parser::types::SelectionSet>,
-    ) -> SelectionSet {
-        let fields = selection_set
-            .node
-            .items
-            .into_iter()
-            .map(|field| match field.node {
-                async_graphql_parser::types::Selection::Field(field) => {
-                    let selections = recursive_extract_data(field.node.selection_set);
-                    Field { name: field.node.name.node.to_string(), selections }
+impl std::fmt::Display for OverrideLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverrideLabel::Percent(percent) => write!(f, "percent({})", percent),
+            OverrideLabel::Custom(label) => f.write_str(label),
+        }
+    }
+}
+
+impl ValidateFrom<JoinField> for JoinFieldParsed {
+    type Error = String;
+
+    fn validate_from(value: JoinField) -> Valid<Self, Self::Error> {
+        parse_query_string(value.requires)
+            .trace("requires")
+            .zip(parse_query_string(value.provides).trace("provides"))
+            .zip(
+                match value.override_label.as_deref() {
+                    Some(label) => OverrideLabel::parse(label).some(),
+                    None => Valid::succeed(None),
                 }
-                _ => panic!("cannot do that"),
+                .trace("overrideLabel"),
+            )
+            .map(|((requires, provides), override_label)| Self {
+                graph: value.graph,
+                requires,
+                provides,
+                r#type: value.r#type,
+                external: value.external,
+                r#override: value.r#override,
+                override_label,
+                used_overridden: value.used_overridden,
             })
-            .collect::<Vec<_>>();
-        SelectionSet(fields)
-    }
-
-    query.map(|value| {
-        let document = async_graphql_parser::parse_query(format!("{{ {} }}", value)).unwrap();
-        let selections = recursive_extract_data(
-            document
-                .operations
-                .iter()
-                .next()
-                .unwrap()
-                .1
-                .node
-                .selection_set
-                .clone(),
-        );
-        selections
-    })
+    }
+}
+
+pub(crate) fn parse_query_string(query: Option<String>) -> Valid<Option<SelectionSet>, String> {
+    use async_graphql_parser::types as Q;
+
+    fn no_directives(directives: &[Positioned<Q::Directive>]) -> Valid<(), String> {
+        match directives.first() {
+            Some(directive) => Valid::fail(format!(
+                "Directive `@{}` is not allowed in a field set",
+                directive.node.name.node
+            )),
+            None => Valid::succeed(()),
+        }
+    }
+
+    fn recursive_extract_data(selection_set: &Q::SelectionSet) -> Valid<SelectionSet, String> {
+        Valid::from_iter(selection_set.items.iter(), |selection| {
+            match &selection.node {
+                Q::Selection::Field(field) => {
+                    let field = &field.node;
+                    let name = field.name.node.to_string();
+                    let arguments = Valid::from_iter(field.arguments.iter(), |(name, value)| {
+                        Valid::from_option(
+                            value.node.clone().into_const(),
+                            format!(
+                                "Variables are not allowed in a field set, in `{}`",
+                                name.node
+                            ),
+                        )
+                        .map(|value| (name.node.to_string(), value))
+                    });
+
+                    no_directives(&field.directives)
+                        .and(arguments)
+                        .fuse(recursive_extract_data(&field.selection_set.node))
+                        .map(|(arguments, selections)| {
+                            Selection::Field(Field {
+                                alias: field.alias.as_ref().map(|alias| alias.node.to_string()),
+                                name: name.clone(),
+                                arguments: arguments.into_iter().collect(),
+                                selections,
+                            })
+                        })
+                        .trace(&name)
+                }
+                Q::Selection::InlineFragment(fragment) => {
+                    let fragment = &fragment.node;
+                    let type_condition = fragment
+                        .type_condition
+                        .as_ref()
+                        .map(|condition| condition.node.on.node.to_string());
+
+                    no_directives(&fragment.directives)
+                        .and(recursive_extract_data(&fragment.selection_set.node))
+                        .map(|selections| {
+                            Selection::InlineFragment(InlineFragment {
+                                type_condition: type_condition.clone(),
+                                selections,
+                            })
+                        })
+                        .trace(&format!(
+                            "... on {}",
+                            type_condition.as_deref().unwrap_or("*")
+                        ))
+                }
+                Q::Selection::FragmentSpread(spread) => Valid::fail(format!(
+                    "Fragment spread `...{}` is not allowed in a field set",
+                    spread.node.fragment_name.node
+                )),
+            }
+        })
+        .map(SelectionSet)
+    }
+
+    let Some(query) = query else {
+        return Valid::none();
+    };
+
+    match async_graphql_parser::parse_query(format!("{{ {} }}", query)) {
+        Ok(document) => match document.operations.iter().next() {
+            Some((_, operation)) => {
+                recursive_extract_data(&operation.node.selection_set.node).some()
+            }
+            None => Valid::fail(format!("Field set `{}` is empty", query)),
+        },
+        Err(error) => Valid::fail_with(
+            format!("Field set `{}` is not valid", query),
+            error.to_string(),
+        ),
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -304,7 +782,7 @@ pub struct JoinUnion {
 
 /// Type to represent GraphQL type usage with modifiers
 /// [spec](https://spec.graphql.org/October2021/#sec-Wrapping-Types)
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Type {
     Named {
         /// Name of the type
@@ -321,6 +799,22 @@ pub enum Type {
 }
 
 impl Type {
+    /// Whether the type is non-null.
+    pub fn is_required(&self) -> bool {
+        match self {
+            Type::Named { required, .. } => *required,
+            Type::List { non_null, .. } => *non_null,
+        }
+    }
+
+    /// Name of the named type, without list and non-null wrappers.
+    pub fn name(&self) -> &str {
+        match self {
+            Type::Named { name, .. } => name,
+            Type::List { of_type, .. } => of_type.name(),
+        }
+    }
+
     pub fn as_type_str(&self) -> String {
         match self {
             Type::Named { name, .. } => name.to_string(),
@@ -1,17 +1,97 @@
 use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
 
 use async_graphql_parser::Positioned;
 use async_graphql_value::{ConstValue, Name};
 use serde::de::DeserializeOwned;
+use valid::{Cause, Valid, ValidateFrom, Validator};
 
 use crate::{
-    Blueprint, Definition, Directive, DirectiveDefinition, EnumValueDefinition, FieldDefinition,
-    InputFieldDefinition, JoinEnum, JoinField, JoinFieldParsed, JoinGraph, JoinImplements,
-    JoinType, JoinTypeParsed, JoinUnion, SchemaDefinition, Type,
+    Blueprint, Definition, Directive, DirectiveDefinition, DirectiveLocation, EnumValueDefinition,
+    FieldDefinition, Graph, InputFieldDefinition, JoinEnum, JoinFieldParsed, JoinGraph,
+    JoinImplements, JoinOwner, JoinTypeParsed, JoinUnion, Links, Pos, SchemaDefinition, Type,
+    Version,
 };
 
-// Reading a super-graph configuration is infallible
-pub fn parse(doc: async_graphql_parser::types::ServiceDocument) -> Blueprint {
+/// Reads a supergraph from its text, which unlike the parsed document tells
+/// which directives are `repeatable`.
+pub fn parse_text(text: &str, source: Option<&str>) -> Valid<Blueprint, String> {
+    let document = match async_graphql_parser::parse_schema(text) {
+        Ok(document) => document,
+        Err(error) => {
+            return Valid::fail(match source {
+                Some(source) => format!("{}: {}", source, error),
+                None => error.to_string(),
+            })
+        }
+    };
+    parse_document(document, text, source)
+}
+
+/// Reads a supergraph from a document already parsed from `text`, which is
+/// still needed to tell which directives are `repeatable`.
+pub fn parse_document(
+    document: async_graphql_parser::types::ServiceDocument,
+    text: &str,
+    source: Option<&str>,
+) -> Valid<Blueprint, String> {
+    let repeatable = repeatable(&document, text);
+
+    parse(document, source).map(|mut blueprint| {
+        for directive in blueprint.directives.iter_mut() {
+            if let Some(repeatable) = repeatable.get(&directive.name) {
+                directive.repeatable = *repeatable;
+            }
+        }
+        blueprint
+    })
+}
+
+// async-graphql-parser 7 reports every directive as repeatable, its grammar
+// lets the `repeatable` keyword match nothing. The keyword is the last word
+// before the `on` that starts the locations, so it is looked for there.
+pub(crate) fn repeatable(
+    document: &async_graphql_parser::types::ServiceDocument,
+    text: &str,
+) -> BTreeMap<String, bool> {
+    let lines = text.split('\n').collect::<Vec<_>>();
+    let offset = |pos: async_graphql_parser::Pos| {
+        let start = lines[..pos.line - 1]
+            .iter()
+            .map(|line| line.len() + 1)
+            .sum::<usize>();
+        let column = lines[pos.line - 1]
+            .char_indices()
+            .nth(pos.column - 1)
+            .map_or(lines[pos.line - 1].len(), |(offset, _)| offset);
+        start + column
+    };
+
+    document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            async_graphql_parser::types::TypeSystemDefinition::Directive(directive) => {
+                let directive = &directive.node;
+                let location = directive.locations.first()?;
+                let head = text.get(offset(directive.name.pos)..offset(location.pos))?;
+                let head = head.trim_end().strip_suffix("on")?.trim_end();
+                let repeatable = head
+                    .strip_suffix("repeatable")
+                    .is_some_and(|rest| rest.ends_with(|c: char| c.is_whitespace() || c == ')'));
+                Some((directive.name.node.to_string(), repeatable))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// Reading a super-graph configuration collects every problem instead of
+// failing on the first one.
+pub fn parse(
+    doc: async_graphql_parser::types::ServiceDocument,
+    source: Option<&str>,
+) -> Valid<Blueprint, String> {
     let mut root_schema = SchemaDefinition {
         query: None,
         mutation: None,
@@ -20,196 +100,239 @@ pub fn parse(doc: async_graphql_parser::types::ServiceDocument) -> Blueprint {
     };
     let mut definitions = BTreeMap::<String, Definition>::new();
     let mut directives = BTreeMap::<String, DirectiveDefinition>::new();
+    let mut errors = valid::Error::empty();
 
+    // The schema's `@link`s name the elements of the specs, so it is read
+    // before the types and directives that use them.
+    let mut schemas = Vec::new();
+    let mut types = Vec::new();
+    let mut directive_nodes = Vec::new();
     for definition in doc.definitions.into_iter() {
         match definition {
-            async_graphql_parser::types::TypeSystemDefinition::Schema(Positioned {
-                pos: _,
-                node: schema_node,
-            }) => {
-                let schema = parse_schema(schema_node);
-
-                if let (None, Some(query)) = (&root_schema.query, schema.query) {
-                    root_schema.query = Some(query)
-                }
+            async_graphql_parser::types::TypeSystemDefinition::Schema(schema) => {
+                schemas.push(schema)
+            }
+            async_graphql_parser::types::TypeSystemDefinition::Type(type_node) => {
+                types.push(type_node)
+            }
+            async_graphql_parser::types::TypeSystemDefinition::Directive(directive_node) => {
+                directive_nodes.push(directive_node)
+            }
+        }
+    }
 
-                if let (None, Some(mutation)) = (&root_schema.mutation, schema.mutation) {
-                    root_schema.mutation = Some(mutation)
-                }
+    for Positioned { pos: _, node: schema_node } in schemas {
+        let schema = match parse_schema(schema_node, source)
+            .trace("schema")
+            .to_result()
+        {
+            Ok(schema) => schema,
+            Err(error) => {
+                errors = errors.combine(error);
+                continue;
+            }
+        };
 
-                if let (None, Some(subscription)) = (&root_schema.subscription, schema.subscription)
-                {
-                    root_schema.subscription = Some(subscription)
-                }
+        if let (None, Some(query)) = (&root_schema.query, schema.query) {
+            root_schema.query = Some(query)
+        }
 
-                // TODO: validate that non-repetitive directive is not defined twice
-                let directives = root_schema
-                    .directives
-                    .clone()
-                    .into_iter()
-                    .chain(schema.directives.into_iter())
-                    .collect::<Vec<_>>();
+        if let (None, Some(mutation)) = (&root_schema.mutation, schema.mutation) {
+            root_schema.mutation = Some(mutation)
+        }
 
-                root_schema.directives = directives;
-            }
-            async_graphql_parser::types::TypeSystemDefinition::Type(Positioned {
-                pos: _,
-                node: type_node,
-            }) => {
-                let name = type_node.name.clone().into_inner().to_string();
+        if let (None, Some(subscription)) = (&root_schema.subscription, schema.subscription) {
+            root_schema.subscription = Some(subscription)
+        }
+
+        // TODO: validate that non-repetitive directive is not defined twice
+        let directives = root_schema
+            .directives
+            .clone()
+            .into_iter()
+            .chain(schema.directives)
+            .collect::<Vec<_>>();
+
+        root_schema.directives = directives;
+    }
+
+    let links = match Links::parse(&root_schema.directives, source)
+        .trace("schema")
+        .to_result()
+    {
+        Ok(links) => links,
+        Err(error) => {
+            errors = errors.combine(error);
+            Links::default()
+        }
+    };
 
-                let definition = parse_type(type_node);
+    for Positioned { pos, node: type_node } in types {
+        let name = type_node.name.clone().into_inner().to_string();
+
+        match parse_type(type_node, pos.into(), &links, source)
+            .trace(&name)
+            .to_result()
+        {
+            Ok(definition) => {
                 definitions.insert(name, definition);
             }
-            async_graphql_parser::types::TypeSystemDefinition::Directive(Positioned {
-                pos: _,
-                node: directive_node,
-            }) => {
-                let name = directive_node.name.clone().into_inner().to_string();
-                let directive = parse_directive_definition(directive_node);
+            Err(error) => errors = errors.combine(error),
+        }
+    }
+
+    for Positioned { pos: _, node: directive_node } in directive_nodes {
+        let name = directive_node.name.clone().into_inner().to_string();
+        match parse_directive_definition(directive_node, &links, source)
+            .trace(&format!("@{}", name))
+            .to_result()
+        {
+            Ok(directive) => {
                 directives.insert(name.clone(), directive);
             }
+            Err(error) => errors = errors.combine(error),
         }
     }
-    let definitions = definitions.clone().into_values().collect::<Vec<_>>();
+
+    let definitions = definitions.into_values().collect::<Vec<_>>();
     let directives = directives.into_values().collect::<Vec<_>>();
 
-    let join_graphs = parse_join_graphs(definitions.clone());
+    let parsed = if errors.is_empty() {
+        Valid::succeed(())
+    } else {
+        Valid::from_validation_err(errors)
+    };
 
-    Blueprint { definitions, schema: root_schema, directives, join_graphs }
+    let join_graphs = parse_join_graphs(&definitions, &links, source);
+    let definitions = if is_federation_1(&links) {
+        parse_federation_1(definitions, &links, source)
+    } else {
+        Valid::succeed(definitions)
+    };
+
+    parsed
+        .and(definitions)
+        .fuse(join_graphs)
+        .map(|(definitions, join_graphs)| Blueprint {
+            definitions: definitions.into_iter().map(Arc::new).collect(),
+            schema: root_schema,
+            directives,
+            join_graphs,
+            links,
+            source: source.map(|source| source.to_string()),
+        })
+        // Field sets can only be checked once every type is known.
+        .and_then(|blueprint| blueprint.to_index().validate_field_sets().map_to(blueprint))
 }
 
-fn parse_directive_definition(
+pub(crate) fn parse_directive_definition(
     directive_node: async_graphql_parser::types::DirectiveDefinition,
-) -> DirectiveDefinition {
+    links: &Links,
+    source: Option<&str>,
+) -> Valid<DirectiveDefinition, String> {
     let name = directive_node.name.into_inner().to_string();
+    // async-graphql-parser 7 reports every directive as repeatable, callers
+    // holding the text of the document correct it with `repeatable`.
     let repeatable = directive_node.is_repeatable;
     let description = directive_node.description.map(|d| d.to_string());
 
-    let arguments: Vec<_> = directive_node
-        .arguments
-        .into_iter()
-        .map(|Positioned { pos: _, node: input_field_node }| parse_input_field(input_field_node))
-        .collect();
-
-    let locations: Vec<String> = directive_node
+    let locations = directive_node
         .locations
         .into_iter()
-        .map(|loc| match loc.into_inner() {
-            async_graphql_parser::types::DirectiveLocation::Query => "Query".into(),
-            async_graphql_parser::types::DirectiveLocation::Mutation => "Mutation".into(),
-            async_graphql_parser::types::DirectiveLocation::Subscription => "Subscription".into(),
-            async_graphql_parser::types::DirectiveLocation::Field => "Field".into(),
-            async_graphql_parser::types::DirectiveLocation::FragmentDefinition => {
-                "FragmentDefinition".into()
-            }
-            async_graphql_parser::types::DirectiveLocation::FragmentSpread => {
-                "FragmentSpread".into()
-            }
-            async_graphql_parser::types::DirectiveLocation::InlineFragment => {
-                "InlineFragment".into()
-            }
-            async_graphql_parser::types::DirectiveLocation::Schema => "Schema".into(),
-            async_graphql_parser::types::DirectiveLocation::Scalar => "Scalar".into(),
-            async_graphql_parser::types::DirectiveLocation::Object => "Object".into(),
-            async_graphql_parser::types::DirectiveLocation::FieldDefinition => {
-                "FieldDefinition".into()
-            }
-            async_graphql_parser::types::DirectiveLocation::ArgumentDefinition => {
-                "ArgumentDefinition".into()
-            }
-            async_graphql_parser::types::DirectiveLocation::Interface => "Interface".into(),
-            async_graphql_parser::types::DirectiveLocation::Union => "Union".into(),
-            async_graphql_parser::types::DirectiveLocation::Enum => "Enum".into(),
-            async_graphql_parser::types::DirectiveLocation::EnumValue => "EnumValue".into(),
-            async_graphql_parser::types::DirectiveLocation::InputObject => "InputObject".into(),
-            async_graphql_parser::types::DirectiveLocation::InputFieldDefinition => {
-                "InputFieldDefinition".into()
-            }
-            async_graphql_parser::types::DirectiveLocation::VariableDefinition => {
-                "VariableDefinition".into()
-            }
-        })
+        .map(|location| DirectiveLocation::from(location.into_inner()))
         .collect();
 
-    DirectiveDefinition { name, description, arguments, repeatable, locations }
+    Valid::from_iter(
+        directive_node.arguments,
+        |Positioned { pos, node: input_field_node }| {
+            let name = input_field_node.name.to_string();
+            parse_input_field(input_field_node, pos.into(), links, source).trace(&name)
+        },
+    )
+    .map(|arguments| DirectiveDefinition {
+        name,
+        description,
+        arguments,
+        repeatable,
+        locations,
+    })
 }
 
-fn parse_type(type_node: async_graphql_parser::types::TypeDefinition) -> Definition {
+pub(crate) fn parse_type(
+    type_node: async_graphql_parser::types::TypeDefinition,
+    pos: Pos,
+    links: &Links,
+    source: Option<&str>,
+) -> Valid<Definition, String> {
     let name = type_node.name.to_string();
     let description = type_node.description.map(|d| d.to_string());
-    let directives: Vec<_> = type_node
-        .directives
-        .clone()
-        .into_iter()
-        .map(|Positioned { pos: _, node: directive_node }| parse_directive(directive_node))
-        .collect();
+    let pos = Some(pos);
 
-    match type_node.kind {
+    parse_directives(type_node.directives, source).and_then(|directives| match type_node.kind {
         async_graphql_parser::types::TypeKind::Scalar => {
-            let join_types: Vec<JoinTypeParsed> =
-                find_directive::<JoinType>(&directives, "join__type")
-                    .into_iter()
-                    .map(JoinTypeParsed::from)
-                    .collect();
-            Definition::Scalar(crate::ScalarTypeDefinition {
-                name,
-                directives,
-                description,
-                join_types,
+            parse_join_types(&directives, links, source).map(|join_types| {
+                Definition::Scalar(crate::ScalarTypeDefinition {
+                    name: name.into(),
+                    directives,
+                    description,
+                    join_types,
+                    pos,
+                })
             })
         }
         async_graphql_parser::types::TypeKind::Object(object_type) => {
-            let fields = object_type
-                .fields
-                .into_iter()
-                .map(|Positioned { pos: _, node: field_node }| parse_field(field_node))
-                .collect();
-
             let implements = object_type
                 .implements
                 .into_iter()
                 .map(|name| name.to_string())
                 .collect::<BTreeSet<String>>();
 
-            let join_types: Vec<JoinTypeParsed> =
-                find_directive::<JoinType>(&directives, "join__type")
-                    .into_iter()
-                    .map(JoinTypeParsed::from)
-                    .collect();
-            let join_implements: Vec<JoinImplements> =
-                find_directive(&directives, "join__implements");
-
-            Definition::Object(crate::ObjectTypeDefinition {
-                name,
-                fields,
-                description,
-                implements,
-                join_types,
-                join_implements,
-            })
+            parse_fields(object_type.fields, links, source)
+                .fuse(parse_join_types(&directives, links, source))
+                .fuse(find_directive::<JoinImplements>(
+                    &directives,
+                    &links.directive("join", "implements"),
+                    source,
+                ))
+                .map(|(fields, join_types, join_implements)| {
+                    Definition::Object(crate::ObjectTypeDefinition {
+                        name: name.into(),
+                        fields,
+                        directives,
+                        description,
+                        implements,
+                        join_types,
+                        join_implements,
+                        pos,
+                    })
+                })
         }
         async_graphql_parser::types::TypeKind::Interface(interface_type) => {
-            let fields = interface_type
-                .fields
+            let implements = interface_type
+                .implements
                 .into_iter()
-                .map(|Positioned { pos: _, node: field_node }| parse_field(field_node))
-                .collect();
+                .map(|name| name.to_string())
+                .collect::<BTreeSet<String>>();
 
-            let join_types: Vec<JoinTypeParsed> =
-                find_directive::<JoinType>(&directives, "join__type")
-                    .into_iter()
-                    .map(JoinTypeParsed::from)
-                    .collect();
-            let join_implements: Vec<JoinImplements> =
-                find_directive(&directives, "join__implements");
-            Definition::Interface(crate::InterfaceTypeDefinition {
-                name,
-                fields,
-                description,
-                join_implements,
-                join_types,
-            })
+            parse_fields(interface_type.fields, links, source)
+                .fuse(parse_join_types(&directives, links, source))
+                .fuse(find_directive::<JoinImplements>(
+                    &directives,
+                    &links.directive("join", "implements"),
+                    source,
+                ))
+                .map(|(fields, join_types, join_implements)| {
+                    Definition::Interface(crate::InterfaceTypeDefinition {
+                        name: name.into(),
+                        fields,
+                        directives,
+                        description,
+                        implements,
+                        join_implements,
+                        join_types,
+                        pos,
+                    })
+                })
         }
         async_graphql_parser::types::TypeKind::Union(union_type) => {
             let types = union_type
@@ -218,130 +341,178 @@ fn parse_type(type_node: async_graphql_parser::types::TypeDefinition) -> Definit
                 .map(|type_name| type_name.into_inner().to_string())
                 .collect();
 
-            let join_types: Vec<JoinTypeParsed> =
-                find_directive::<JoinType>(&directives, "join__type")
-                    .into_iter()
-                    .map(JoinTypeParsed::from)
-                    .collect();
-            let join_unions: Vec<JoinUnion> = find_directive(&directives, "join__unionMember");
-
-            Definition::Union(crate::UnionTypeDefinition {
-                name,
-                directives,
-                description,
-                types,
-                join_types,
-                join_unions,
-            })
+            parse_join_types(&directives, links, source)
+                .fuse(find_directive::<JoinUnion>(
+                    &directives,
+                    &links.directive("join", "unionMember"),
+                    source,
+                ))
+                .map(|(join_types, join_unions)| {
+                    Definition::Union(crate::UnionTypeDefinition {
+                        name: name.into(),
+                        directives,
+                        description,
+                        types,
+                        join_types,
+                        join_unions,
+                        pos,
+                    })
+                })
         }
-        async_graphql_parser::types::TypeKind::Enum(enum_type) => {
-            let enum_values = enum_type
-                .values
-                .into_iter()
-                .map(|Positioned { pos: _, node: enum_node }| parse_enum(enum_node))
-                .collect();
-
-            let join_types: Vec<JoinTypeParsed> =
-                find_directive::<JoinType>(&directives, "join__type")
-                    .into_iter()
-                    .map(JoinTypeParsed::from)
-                    .collect();
+        async_graphql_parser::types::TypeKind::Enum(enum_type) => Valid::from_iter(
+            enum_type.values,
+            |Positioned { pos: _, node: enum_node }| {
+                let name = enum_node.value.to_string();
+                parse_enum(enum_node, links, source).trace(&name)
+            },
+        )
+        .fuse(parse_join_types(&directives, links, source))
+        .map(|(enum_values, join_types)| {
             Definition::Enum(crate::EnumTypeDefinition {
-                name,
+                name: name.into(),
                 directives,
                 description,
                 enum_values,
                 join_types,
+                pos,
             })
-        }
-        async_graphql_parser::types::TypeKind::InputObject(input_object_type) => {
-            let fields = input_object_type
-                .fields
-                .into_iter()
-                .map(|Positioned { pos: _, node: input_field_node }| {
-                    parse_input_field(input_field_node)
-                })
-                .collect();
-
-            let join_types: Vec<JoinTypeParsed> =
-                find_directive::<JoinType>(&directives, "join__type")
-                    .into_iter()
-                    .map(JoinTypeParsed::from)
-                    .collect();
+        }),
+        async_graphql_parser::types::TypeKind::InputObject(input_object_type) => Valid::from_iter(
+            input_object_type.fields,
+            |Positioned { pos, node: input_field_node }| {
+                let name = input_field_node.name.to_string();
+                parse_input_field(input_field_node, pos.into(), links, source).trace(&name)
+            },
+        )
+        .fuse(parse_join_types(&directives, links, source))
+        .map(|(fields, join_types)| {
             Definition::InputObject(crate::InputObjectTypeDefinition {
-                name,
+                name: name.into(),
                 fields,
+                directives,
                 description,
                 join_types,
+                pos,
             })
-        }
-    }
+        }),
+    })
+}
+
+fn parse_fields(
+    fields: Vec<Positioned<async_graphql_parser::types::FieldDefinition>>,
+    links: &Links,
+    source: Option<&str>,
+) -> Valid<Vec<FieldDefinition>, String> {
+    Valid::from_iter(fields, |Positioned { pos, node: field_node }| {
+        let name = field_node.name.to_string();
+        parse_field(field_node, pos.into(), links, source).trace(&name)
+    })
 }
 
-fn parse_enum(enum_node: async_graphql_parser::types::EnumValueDefinition) -> EnumValueDefinition {
+fn parse_join_types(
+    directives: &[Directive],
+    links: &Links,
+    source: Option<&str>,
+) -> Valid<Vec<JoinTypeParsed>, String> {
+    find_directive_with(
+        directives,
+        &links.directive("join", "type"),
+        source,
+        JoinTypeParsed::validate_from,
+    )
+}
+
+fn parse_join_fields(
+    directives: &[Directive],
+    links: &Links,
+    source: Option<&str>,
+) -> Valid<Vec<JoinFieldParsed>, String> {
+    find_directive_with(
+        directives,
+        &links.directive("join", "field"),
+        source,
+        JoinFieldParsed::validate_from,
+    )
+}
+
+fn parse_enum(
+    enum_node: async_graphql_parser::types::EnumValueDefinition,
+    links: &Links,
+    source: Option<&str>,
+) -> Valid<EnumValueDefinition, String> {
     let name = enum_node.value.to_string();
     let description = enum_node.description.map(|d| d.to_string());
-    let directives: Vec<_> = enum_node
-        .directives
-        .into_iter()
-        .map(|Positioned { pos: _, node: directive_node }| parse_directive(directive_node))
-        .collect();
 
-    let join_enums: Vec<JoinEnum> = find_directive(&directives, "join__enumValue");
-    EnumValueDefinition { description, name, directives, join_enums }
+    parse_directives(enum_node.directives, source).and_then(|directives| {
+        find_directive::<JoinEnum>(&directives, &links.directive("join", "enumValue"), source)
+            .map(|join_enums| EnumValueDefinition { description, name, directives, join_enums })
+    })
 }
 
-fn parse_field(field_node: async_graphql_parser::types::FieldDefinition) -> FieldDefinition {
+fn parse_field(
+    field_node: async_graphql_parser::types::FieldDefinition,
+    pos: Pos,
+    links: &Links,
+    source: Option<&str>,
+) -> Valid<FieldDefinition, String> {
     let name = field_node.name.to_string();
     let description = field_node.description.map(|d| d.to_string());
     let of_type = map_type(&field_node.ty.into_inner());
 
-    let args = field_node
-        .arguments
-        .into_iter()
-        .map(|Positioned { pos: _, node: arg_node }| parse_input_field(arg_node))
-        .collect();
-
-    let directives: Vec<_> = field_node
-        .directives
-        .into_iter()
-        .map(|Positioned { pos: _, node: directive_node }| parse_directive(directive_node))
-        .collect();
-
-    let join_fields: Vec<JoinFieldParsed> = find_directive::<JoinField>(&directives, "join__field")
-        .into_iter()
-        .map(JoinFieldParsed::from)
-        .collect();
-
-    FieldDefinition { name, args, of_type, directives, description, join_fields }
+    Valid::from_iter(
+        field_node.arguments,
+        |Positioned { pos, node: arg_node }| {
+            let name = arg_node.name.to_string();
+            parse_input_field(arg_node, pos.into(), links, source).trace(&name)
+        },
+    )
+    .zip(parse_directives(field_node.directives, source))
+    .and_then(|(args, directives)| {
+        parse_join_fields(&directives, links, source).map(|join_fields| FieldDefinition {
+            name: name.into(),
+            args,
+            of_type,
+            directives,
+            description,
+            join_fields,
+            pos: Some(pos),
+        })
+    })
 }
 
-fn parse_input_field(
+pub(crate) fn parse_input_field(
     input_field_node: async_graphql_parser::types::InputValueDefinition,
-) -> InputFieldDefinition {
+    pos: Pos,
+    links: &Links,
+    source: Option<&str>,
+) -> Valid<InputFieldDefinition, String> {
     let name = input_field_node.name.to_string();
     let description = input_field_node.description.map(|d| d.to_string());
     let of_type = map_type(&input_field_node.ty.into_inner());
 
-    let default_value = input_field_node
-        .default_value
-        .map(|Positioned { pos: _, node: argument_value }| parse_argument(argument_value));
-
-    let directives: Vec<_> = input_field_node
-        .directives
-        .into_iter()
-        .map(|Positioned { pos: _, node: directive_node }| parse_directive(directive_node))
-        .collect();
-
-    let join_fields: Vec<JoinFieldParsed> = find_directive::<JoinField>(&directives, "join__field")
-        .into_iter()
-        .map(JoinFieldParsed::from)
-        .collect();
+    let default_value = match input_field_node.default_value {
+        Some(Positioned { pos, node: argument_value }) => {
+            at(parse_argument(argument_value), Some(pos.into()), source).some()
+        }
+        None => Valid::none(),
+    };
 
-    InputFieldDefinition { name, of_type, default_value, description, join_fields }
+    default_value
+        .zip(parse_directives(input_field_node.directives, source))
+        .and_then(|(default_value, directives)| {
+            parse_join_fields(&directives, links, source).map(|join_fields| InputFieldDefinition {
+                name: name.into(),
+                of_type,
+                default_value,
+                directives,
+                description,
+                join_fields,
+                pos: Some(pos),
+            })
+        })
 }
 
-fn map_type(type_: &async_graphql_parser::types::Type) -> Type {
+pub(crate) fn map_type(type_: &async_graphql_parser::types::Type) -> Type {
     match &type_.base {
         async_graphql_parser::types::BaseType::Named(name) => {
             Type::Named { name: name.to_string(), required: !type_.nullable }
@@ -353,7 +524,10 @@ fn map_type(type_: &async_graphql_parser::types::Type) -> Type {
     }
 }
 
-fn parse_schema(schema_node: async_graphql_parser::types::SchemaDefinition) -> SchemaDefinition {
+fn parse_schema(
+    schema_node: async_graphql_parser::types::SchemaDefinition,
+    source: Option<&str>,
+) -> Valid<SchemaDefinition, String> {
     let query = if let Some(Positioned { pos: _, node: query_node }) = schema_node.query {
         Some(query_node.to_string())
     } else {
@@ -373,87 +547,441 @@ fn parse_schema(schema_node: async_graphql_parser::types::SchemaDefinition) -> S
             None
         };
 
-    let directives = schema_node
-        .directives
-        .into_iter()
-        .map(|Positioned { pos: _, node: directive_node }| parse_directive(directive_node))
-        .collect();
+    parse_directives(schema_node.directives, source).map(|directives| SchemaDefinition {
+        query,
+        mutation,
+        subscription,
+        directives,
+    })
+}
 
-    SchemaDefinition { query, mutation, subscription, directives }
+pub(crate) fn parse_directives(
+    directives: Vec<Positioned<async_graphql_parser::types::ConstDirective>>,
+    source: Option<&str>,
+) -> Valid<Vec<Directive>, String> {
+    Valid::from_iter(directives, |Positioned { pos, node: directive_node }| {
+        at(
+            parse_directive(directive_node, pos.into()),
+            Some(pos.into()),
+            source,
+        )
+    })
 }
 
-fn parse_directive(directive_node: async_graphql_parser::types::ConstDirective) -> Directive {
-    let arguments = parse_arguments(directive_node.arguments);
+fn parse_directive(
+    directive_node: async_graphql_parser::types::ConstDirective,
+    pos: Pos,
+) -> Valid<Directive, String> {
+    let name = directive_node.name.to_string();
 
-    Directive { name: directive_node.name.to_string(), arguments }
+    parse_arguments(directive_node.arguments)
+        .map(|arguments| Directive { name: name.clone(), arguments, pos: Some(pos) })
+        .trace(&format!("@{}", name))
 }
 
 fn parse_arguments(
     arguments: Vec<(Positioned<Name>, Positioned<ConstValue>)>,
-) -> serde_json::Value {
-    let map: BTreeMap<String, serde_json::Value> = arguments
-        .into_iter()
-        .map(
-            |(
-                Positioned { pos: _, node: argument_name },
-                Positioned { pos: _, node: argument_node },
-            )| { (argument_name.to_string(), parse_argument(argument_node)) },
-        )
-        .collect();
-    serde_json::Value::Object(map.into_iter().collect())
+) -> Valid<serde_json::Value, String> {
+    Valid::from_iter(
+        arguments,
+        |(
+            Positioned { pos: _, node: argument_name },
+            Positioned { pos: _, node: argument_node },
+        )| {
+            let name = argument_name.to_string();
+            parse_argument(argument_node)
+                .map(|value| (name.clone(), value))
+                .trace(&name)
+        },
+    )
+    .map(|arguments| {
+        let map: BTreeMap<String, serde_json::Value> = arguments.into_iter().collect();
+        serde_json::Value::Object(map.into_iter().collect())
+    })
 }
 
-fn parse_argument(argument_node: async_graphql_value::ConstValue) -> serde_json::Value {
-    argument_node.into_json().unwrap()
+pub(crate) fn parse_argument(
+    argument_node: async_graphql_value::ConstValue,
+) -> Valid<serde_json::Value, String> {
+    match argument_node.into_json() {
+        Ok(value) => Valid::succeed(value),
+        Err(error) => Valid::fail(format!("Value can not be converted to JSON: {}", error)),
+    }
 }
 
-fn parse_join_graphs(definitions: Vec<Definition>) -> Vec<JoinGraph> {
-    definitions
-        .into_iter()
-        .find_map(|definition| {
-            if let Definition::Enum(enumeration) = definition {
-                if enumeration.name == "join__Graph" {
-                    let data =
-                        enumeration
-                            .enum_values
-                            .into_iter()
-                            .fold(Vec::new(), |mut acc, cur| {
-                                let mut join_graphs: Vec<JoinGraph> =
-                                    find_directive(&cur.directives, "join__graph");
-
-                                acc.append(&mut join_graphs);
-                                acc
-                            });
-
-                    Some(data)
-                } else {
-                    None
+fn parse_join_graphs(
+    definitions: &[Definition],
+    links: &Links,
+    source: Option<&str>,
+) -> Valid<Vec<JoinGraph>, String> {
+    let graph = links.type_name("join", "Graph");
+    let enumeration = definitions.iter().find_map(|definition| match definition {
+        Definition::Enum(enumeration) if enumeration.name == graph => Some(enumeration),
+        _ => None,
+    });
+
+    Valid::from_option(enumeration, format!("Enumeration `{}` is not found", graph))
+        .and_then(|enumeration| {
+            Valid::from_iter(enumeration.enum_values.iter(), |value| {
+                find_directive::<JoinGraph>(
+                    &value.directives,
+                    &links.directive("join", "graph"),
+                    source,
+                )
+                .trace(&value.name)
+            })
+            .trace(&enumeration.name)
+        })
+        .map(|join_graphs| join_graphs.into_iter().flatten().collect())
+}
+
+// join v0.1 is what federation 1 composes to.
+fn is_federation_1(links: &Links) -> bool {
+    links.get("join").and_then(|link| link.version) == Some(Version::new(0, 1))
+}
+
+// Federation 1 supergraphs leave out what federation 2 ones spell out: the
+// fields of an entity without `@join__field` belong to the graph in its
+// `@join__owner`, the other graphs only extend it and resolve its keys, and
+// types without `@join__type` are in every graph.
+fn parse_federation_1(
+    definitions: Vec<Definition>,
+    links: &Links,
+    source: Option<&str>,
+) -> Valid<Vec<Definition>, String> {
+    let graph = links.type_name("join", "Graph");
+    let graphs = definitions
+        .iter()
+        .find_map(|definition| match definition {
+            Definition::Enum(enumeration) if enumeration.name == graph => Some(
+                enumeration
+                    .enum_values
+                    .iter()
+                    .map(|value| Graph::new(&value.name))
+                    .collect::<Vec<_>>(),
+            ),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let owner = links.directive("join", "owner");
+
+    Valid::from_iter(definitions, |definition| {
+        let name = definition.name().to_string();
+        let definition = match definition {
+            Definition::Object(mut object) => {
+                parse_owner(&object.directives, &owner, source).map(|owner| {
+                    object.join_types = every_graph(object.join_types, &graphs);
+                    own(&mut object.join_types, &mut object.fields, owner);
+                    if object.join_implements.is_empty() {
+                        object.join_implements =
+                            join_implements(&object.join_types, &object.implements);
+                    }
+                    Definition::Object(object)
+                })
+            }
+            Definition::Interface(mut interface) => {
+                parse_owner(&interface.directives, &owner, source).map(|owner| {
+                    interface.join_types = every_graph(interface.join_types, &graphs);
+                    own(&mut interface.join_types, &mut interface.fields, owner);
+                    if interface.join_implements.is_empty() {
+                        interface.join_implements =
+                            join_implements(&interface.join_types, &interface.implements);
+                    }
+                    Definition::Interface(interface)
+                })
+            }
+            Definition::Union(mut union) => {
+                union.join_types = every_graph(union.join_types, &graphs);
+                if union.join_unions.is_empty() {
+                    union.join_unions = union
+                        .join_types
+                        .iter()
+                        .flat_map(|join_type| {
+                            union.types.iter().map(|member| JoinUnion {
+                                graph: join_type.graph.clone(),
+                                member: member.clone(),
+                            })
+                        })
+                        .collect();
                 }
-            } else {
-                None
+                Valid::succeed(Definition::Union(union))
+            }
+            Definition::Enum(mut enumeration) => {
+                if enumeration.name != graph {
+                    enumeration.join_types = every_graph(enumeration.join_types, &graphs);
+                }
+                Valid::succeed(Definition::Enum(enumeration))
+            }
+            Definition::Scalar(mut scalar) => {
+                scalar.join_types = every_graph(scalar.join_types, &graphs);
+                Valid::succeed(Definition::Scalar(scalar))
+            }
+            Definition::InputObject(mut input) => {
+                input.join_types = every_graph(input.join_types, &graphs);
+                Valid::succeed(Definition::InputObject(input))
             }
+        };
+
+        definition.trace(&name)
+    })
+}
+
+fn parse_owner(
+    directives: &[Directive],
+    name: &str,
+    source: Option<&str>,
+) -> Valid<Option<Graph>, String> {
+    find_directive::<JoinOwner>(directives, name, source).and_then(|owners| {
+        match owners.as_slice() {
+            [] => Valid::none(),
+            [owner] => Valid::succeed(Some(owner.graph.clone())),
+            _ => Valid::fail(format!("`@{}` can only be applied once", name)),
+        }
+    })
+}
+
+fn join_implements(
+    join_types: &[JoinTypeParsed],
+    implements: &BTreeSet<String>,
+) -> Vec<JoinImplements> {
+    join_types
+        .iter()
+        .flat_map(|join_type| {
+            implements.iter().map(|interface| JoinImplements {
+                graph: join_type.graph.clone(),
+                interface: interface.clone(),
+            })
         })
-        .expect("Enumeration `join__Graph` is not found")
+        .collect()
 }
 
-fn find_directive<Value: DeserializeOwned>(directives: &[Directive], name: &str) -> Vec<Value> {
-    directives
+fn every_graph(join_types: Vec<JoinTypeParsed>, graphs: &[Graph]) -> Vec<JoinTypeParsed> {
+    if !join_types.is_empty() {
+        return join_types;
+    }
+
+    graphs
         .iter()
-        .filter(|directive| directive.name == name)
-        .map(|directive| serde_json::from_value(directive.arguments.clone()).unwrap())
+        .map(|graph| JoinTypeParsed {
+            graph: graph.clone(),
+            key: None,
+            extension: false,
+            resolvable: true,
+            is_interface_object: false,
+        })
         .collect()
 }
 
+// The owner resolves the fields no other graph claims, the graphs extending
+// the entity resolve the fields of their keys.
+fn own(join_types: &mut [JoinTypeParsed], fields: &mut [FieldDefinition], owner: Option<Graph>) {
+    let Some(owner) = owner else {
+        return;
+    };
+
+    for join_type in join_types.iter_mut() {
+        join_type.extension = join_type.graph != owner;
+    }
+
+    for field in fields
+        .iter_mut()
+        .filter(|field| field.join_fields.is_empty())
+    {
+        let extensions = join_types.iter().filter(|join_type| {
+            join_type.extension
+                && join_type
+                    .key
+                    .iter()
+                    .any(|key| key.fields().any(|key| key.name == field.name))
+        });
+
+        field.join_fields = std::iter::once(&owner)
+            .chain(extensions.map(|join_type| &join_type.graph))
+            .map(|graph| JoinFieldParsed {
+                graph: Some(graph.clone()),
+                requires: None,
+                provides: None,
+                r#type: None,
+                external: None,
+                r#override: None,
+                override_label: None,
+                used_overridden: None,
+            })
+            .collect();
+    }
+}
+
+fn find_directive<Value: DeserializeOwned>(
+    directives: &[Directive],
+    name: &str,
+    source: Option<&str>,
+) -> Valid<Vec<Value>, String> {
+    find_directive_with(directives, name, source, Valid::succeed)
+}
+
+fn find_directive_with<Raw: DeserializeOwned, Value>(
+    directives: &[Directive],
+    name: &str,
+    source: Option<&str>,
+    f: impl Fn(Raw) -> Valid<Value, String>,
+) -> Valid<Vec<Value>, String> {
+    Valid::from_iter(
+        directives.iter().filter(|directive| directive.name == name),
+        |directive| {
+            let value = match serde_json::from_value(directive.arguments.clone()) {
+                Ok(raw) => f(raw),
+                Err(error) => Valid::fail(format!("Invalid arguments: {}", error)),
+            };
+
+            at(value.trace(&format!("@{}", name)), directive.pos, source)
+        },
+    )
+}
+
+// Prefixes every error with the position in the source it was found at.
+pub(crate) fn at<A>(
+    valid: Valid<A, String>,
+    pos: Option<Pos>,
+    source: Option<&str>,
+) -> Valid<A, String> {
+    match (valid.to_result(), pos) {
+        (Err(error), Some(pos)) => Valid::from_vec_cause(
+            error
+                .as_vec()
+                .iter()
+                .cloned()
+                .map(|cause| Cause {
+                    message: format!("{}: {}", pos.in_source(source), cause.message),
+                    ..cause
+                })
+                .collect(),
+        ),
+        (result, _) => Valid::from(result),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn parse_str(graphql: &str) -> Valid<Blueprint, String> {
+        let document = async_graphql_parser::parse_schema(graphql).unwrap();
+        parse(document, Some("router.graphql"))
+    }
+
     #[test]
     fn test_parse() {
         let graphql = resource::resource_str!("./src/fixtures/router.graphql");
-        let document = async_graphql_parser::parse_schema(graphql).unwrap();
-        let blueprint = parse(document);
+        let blueprint = parse_str(&graphql).to_result().unwrap();
         let blueprint = serde_json::to_string_pretty(&blueprint).unwrap();
         insta::assert_snapshot!(blueprint);
     }
+
+    #[test]
+    fn test_parse_errors() {
+        let graphql = r#"
+            type Query @join__type(graph: 1) {
+                user: User @join__field(graph: ACCOUNTS, requires: "...UserKey")
+            }
+
+            type User @join__type(graph: ACCOUNTS, key: "id {") {
+                id: ID!
+            }
+        "#;
+        let errors = parse_str(graphql).to_result().unwrap_err();
+        insta::assert_snapshot!(errors);
+    }
+
+    #[test]
+    fn test_parse_federation_1() {
+        let graphql = resource::resource_str!("./src/fixtures/router_federation_1.graphql");
+        let blueprint = parse_str(&graphql).to_result().unwrap();
+
+        // Fields without join data are in every graph of their type.
+        let graphs = |graphs: Vec<String>| match graphs.is_empty() {
+            true => "*".to_string(),
+            false => graphs.join(", "),
+        };
+        let mut joined = String::new();
+        for definition in &blueprint.definitions {
+            let Definition::Object(object) = definition.as_ref() else {
+                continue;
+            };
+            let join_types = object.join_types.iter().map(|join_type| {
+                let extension = if join_type.extension {
+                    " (extension)"
+                } else {
+                    ""
+                };
+                format!("{}{}", join_type.graph.as_str(), extension)
+            });
+            joined += &format!("{}: {}\n", object.name, graphs(join_types.collect()));
+            for field in &object.fields {
+                let join_fields = field.join_fields.iter().filter_map(|join_field| {
+                    join_field
+                        .graph
+                        .as_ref()
+                        .map(|graph| graph.as_str().to_string())
+                });
+                joined += &format!("  {}: {}\n", field.name, graphs(join_fields.collect()));
+            }
+        }
+        insta::assert_snapshot!(joined);
+    }
+
+    #[test]
+    fn test_join_type_defaults() {
+        let graphql = r#"
+schema @link(url: "https://specs.apollo.dev/join/v0.3", for: EXECUTION) {
+  query: Query
+}
+
+enum join__Graph {
+  A @join__graph(name: "a", url: "http://localhost:4001")
+}
+
+type Query @join__type(graph: A) {
+  id: ID
+}
+"#;
+        let blueprint = parse_str(graphql).to_result().unwrap();
+        let Some(Definition::Object(query)) = blueprint
+            .definitions
+            .iter()
+            .find(|definition| definition.name() == "Query")
+            .map(AsRef::as_ref)
+        else {
+            panic!("expected `Query`")
+        };
+        let join_type = &query.join_types[0];
+        assert!(!join_type.extension);
+        assert!(join_type.resolvable);
+        assert!(!join_type.is_interface_object);
+
+        // The same holds for join types read back from a Blueprint.
+        let join_type: crate::JoinType = serde_json::from_str(r#"{"graph": "A"}"#).unwrap();
+        assert!(!join_type.extension);
+        assert!(join_type.resolvable);
+        assert!(!join_type.is_interface_object);
+    }
+
+    #[test]
+    fn test_parse_doc_repeatable() {
+        let graphql = resource::resource_str!("./src/fixtures/router.graphql");
+        let document = async_graphql_parser::parse_schema(&graphql).unwrap();
+        let blueprint = Blueprint::parse_doc(document, &graphql)
+            .to_result()
+            .unwrap();
+        let repeatable = |name: &str| {
+            blueprint
+                .directives
+                .iter()
+                .find(|directive| directive.name == name)
+                .map(|directive| directive.repeatable)
+        };
+
+        assert_eq!(repeatable("link"), Some(true));
+        assert_eq!(repeatable("join__graph"), Some(false));
+        assert_eq!(repeatable("join__type"), Some(true));
+    }
 }
@@ -0,0 +1,154 @@
+use std::sync::{Arc, LazyLock};
+
+use async_graphql_parser::types::TypeSystemDefinition;
+use async_graphql_parser::Positioned;
+use valid::Validator;
+
+use crate::build::{parse_directive_definition, parse_type, repeatable};
+use crate::{Definition, DirectiveDefinition, Links};
+
+/// What every schema has without defining it: the built-in scalars and
+/// directives, and the types introspection is answered with.
+pub(crate) struct Builtins {
+    pub definitions: Vec<Arc<Definition>>,
+    pub directives: Vec<DirectiveDefinition>,
+}
+
+// The built-ins are not a supergraph, so they are read without the checks of
+// `Blueprint::parse`.
+pub(crate) static BUILTINS: LazyLock<Builtins> = LazyLock::new(|| {
+    let document = async_graphql_parser::parse_schema(SDL).expect("built-ins are valid SDL");
+    let repeatable = repeatable(&document, SDL);
+    let links = Links::default();
+
+    let mut builtins = Builtins { definitions: Vec::new(), directives: Vec::new() };
+    for definition in document.definitions {
+        match definition {
+            TypeSystemDefinition::Type(Positioned { pos, node }) => {
+                let definition = parse_type(node, pos.into(), &links, None);
+                builtins.definitions.push(Arc::new(
+                    definition.to_result().expect("built-ins are valid"),
+                ));
+            }
+            TypeSystemDefinition::Directive(Positioned { mut node, .. }) => {
+                node.is_repeatable = repeatable[node.name.node.as_str()];
+                let directive = parse_directive_definition(node, &links, None);
+                builtins
+                    .directives
+                    .push(directive.to_result().expect("built-ins are valid"));
+            }
+            TypeSystemDefinition::Schema(_) => {}
+        }
+    }
+
+    builtins
+});
+
+const SDL: &str = r#"
+scalar String
+scalar Int
+scalar Float
+scalar Boolean
+scalar ID
+
+"Directs the executor to include this field or fragment only when the `if` argument is true."
+directive @include(if: Boolean!) on FIELD | FRAGMENT_SPREAD | INLINE_FRAGMENT
+
+"Directs the executor to skip this field or fragment when the `if` argument is true."
+directive @skip(if: Boolean!) on FIELD | FRAGMENT_SPREAD | INLINE_FRAGMENT
+
+"Marks an element of a GraphQL schema as no longer supported."
+directive @deprecated(reason: String = "No longer supported") on FIELD_DEFINITION | ARGUMENT_DEFINITION | INPUT_FIELD_DEFINITION | ENUM_VALUE
+
+"Exposes a URL that specifies the behavior of this scalar."
+directive @specifiedBy(url: String!) on SCALAR
+
+type __Schema {
+  description: String
+  types: [__Type!]!
+  queryType: __Type!
+  mutationType: __Type
+  subscriptionType: __Type
+  directives: [__Directive!]!
+}
+
+type __Type {
+  kind: __TypeKind!
+  name: String
+  description: String
+  specifiedByURL: String
+  fields(includeDeprecated: Boolean = false): [__Field!]
+  interfaces: [__Type!]
+  possibleTypes: [__Type!]
+  enumValues(includeDeprecated: Boolean = false): [__EnumValue!]
+  inputFields(includeDeprecated: Boolean = false): [__InputValue!]
+  ofType: __Type
+  isOneOf: Boolean
+}
+
+enum __TypeKind {
+  SCALAR
+  OBJECT
+  INTERFACE
+  UNION
+  ENUM
+  INPUT_OBJECT
+  LIST
+  NON_NULL
+}
+
+type __Field {
+  name: String!
+  description: String
+  args(includeDeprecated: Boolean = false): [__InputValue!]!
+  type: __Type!
+  isDeprecated: Boolean!
+  deprecationReason: String
+}
+
+type __InputValue {
+  name: String!
+  description: String
+  type: __Type!
+  defaultValue: String
+  isDeprecated: Boolean!
+  deprecationReason: String
+}
+
+type __EnumValue {
+  name: String!
+  description: String
+  isDeprecated: Boolean!
+  deprecationReason: String
+}
+
+type __Directive {
+  name: String!
+  description: String
+  locations: [__DirectiveLocation!]!
+  args(includeDeprecated: Boolean = false): [__InputValue!]!
+  isRepeatable: Boolean!
+}
+
+enum __DirectiveLocation {
+  QUERY
+  MUTATION
+  SUBSCRIPTION
+  FIELD
+  FRAGMENT_DEFINITION
+  FRAGMENT_SPREAD
+  INLINE_FRAGMENT
+  VARIABLE_DEFINITION
+  SCHEMA
+  SCALAR
+  OBJECT
+  FIELD_DEFINITION
+  ARGUMENT_DEFINITION
+  INTERFACE
+  UNION
+  ENUM
+  ENUM_VALUE
+  INPUT_OBJECT
+  INPUT_FIELD_DEFINITION
+}
+"#;
@@ -0,0 +1,1641 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use async_graphql_parser::types::{self as S, BaseType, TypeKind, TypeSystemDefinition};
+use async_graphql_parser::Positioned;
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::Value;
+use valid::{Valid, ValidateFrom, Validator};
+
+use crate::build::{self, at};
+use crate::{
+    Blueprint, Definition, Directive, DirectiveDefinition, EnumTypeDefinition, EnumValueDefinition,
+    FieldDefinition, Graph, InputFieldDefinition, InputObjectTypeDefinition,
+    InterfaceTypeDefinition, JoinEnum, JoinField, JoinFieldParsed, JoinImplements, JoinType,
+    JoinTypeParsed, JoinUnion, Links, ObjectTypeDefinition, Pos, ScalarTypeDefinition,
+    SchemaDefinition, SelectionSet, UnionTypeDefinition,
+};
+
+/// A subgraph schema taking part in the composition of a supergraph.
+#[derive(Clone, Debug)]
+pub struct Subgraph {
+    /// Name of the subgraph, also used as the source of its positions.
+    pub name: String,
+    /// Url the router sends the subgraph's fetches to.
+    pub url: String,
+    /// Schema of the subgraph in SDL.
+    pub sdl: String,
+}
+
+impl Subgraph {
+    pub fn new(name: impl AsRef<str>, url: impl AsRef<str>, sdl: impl AsRef<str>) -> Self {
+        Subgraph {
+            name: name.as_ref().to_string(),
+            url: url.as_ref().to_string(),
+            sdl: sdl.as_ref().to_string(),
+        }
+    }
+}
+
+const FEDERATION_SPEC: &str = "https://specs.apollo.dev/federation/";
+
+const FEDERATION_DIRECTIVES: &[&str] = &[
+    "key",
+    "external",
+    "requires",
+    "provides",
+    "shareable",
+    "override",
+    "inaccessible",
+    "tag",
+    "extends",
+    "interfaceObject",
+    "composeDirective",
+];
+
+const FEDERATION_TYPES: &[&str] = &["_Any", "_Entity", "_Service", "FieldSet", "_FieldSet"];
+
+pub(crate) const BUILTIN_SCALARS: &[&str] = &["String", "Int", "Float", "Boolean", "ID"];
+
+const BUILTIN_DIRECTIVES: &[&str] = &["skip", "include", "deprecated", "specifiedBy", "oneOf"];
+
+const PRELUDE: &str = r#"
+directive @join__enumValue(graph: join__Graph!) repeatable on ENUM_VALUE
+
+directive @join__field(
+  graph: join__Graph
+  requires: join__FieldSet
+  provides: join__FieldSet
+  type: String
+  external: Boolean
+  override: String
+  usedOverridden: Boolean
+) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+
+directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+
+directive @join__implements(
+  graph: join__Graph!
+  interface: String!
+) repeatable on OBJECT | INTERFACE
+
+directive @join__type(
+  graph: join__Graph!
+  key: join__FieldSet
+  extension: Boolean! = false
+  resolvable: Boolean! = true
+  isInterfaceObject: Boolean! = false
+) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+
+directive @join__unionMember(
+  graph: join__Graph!
+  member: String!
+) repeatable on UNION
+
+directive @link(
+  url: String
+  as: String
+  for: link__Purpose
+  import: [link__Import]
+) repeatable on SCHEMA
+
+scalar join__FieldSet
+
+scalar link__Import
+
+enum link__Purpose {
+  """
+  `SECURITY` features provide metadata necessary to securely resolve fields.
+  """
+  SECURITY
+
+  """
+  `EXECUTION` features provide metadata necessary for operation execution.
+  """
+  EXECUTION
+}
+"#;
+
+pub(crate) const INACCESSIBLE: &str = "directive @inaccessible on FIELD_DEFINITION | OBJECT | INTERFACE | UNION | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE | INPUT_OBJECT | INPUT_FIELD_DEFINITION";
+
+const TAG: &str = "directive @tag(name: String!) repeatable on FIELD_DEFINITION | OBJECT | INTERFACE | UNION | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE | INPUT_OBJECT | INPUT_FIELD_DEFINITION";
+
+/// Composes the supergraph of `subgraphs`, collecting every composition error
+/// instead of stopping at the first one.
+pub fn compose(subgraphs: &[Subgraph]) -> Valid<Blueprint, String> {
+    if subgraphs.is_empty() {
+        return Valid::fail("At least one subgraph is required to compose a supergraph".into());
+    }
+
+    // Graphs are ordered by name so that the supergraph does not depend on the
+    // order the subgraphs are listed in.
+    let mut subgraphs = subgraphs.iter().collect::<Vec<_>>();
+    subgraphs.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Valid::from_iter(subgraphs, Schema::parse)
+        .and_then(|schemas| unique_graphs(&schemas).map_to(schemas))
+        .and_then(|schemas| Composer { schemas }.compose())
+}
+
+fn unique_graphs(schemas: &[Schema]) -> Valid<(), String> {
+    let mut graphs = BTreeMap::new();
+    Valid::from_iter(schemas, |schema| {
+        match graphs.insert(&schema.graph, &schema.name) {
+            Some(other) => Valid::fail(format!(
+                "Subgraphs `{}` and `{}` both use the graph name `{}`",
+                other,
+                schema.name,
+                schema.graph.as_str()
+            )),
+            None => Valid::succeed(()),
+        }
+    })
+    .unit()
+}
+
+// Maps the names a subgraph uses for federation directives to their names in
+// the federation spec.
+struct Federation {
+    names: BTreeMap<String, &'static str>,
+    // Federation 1 subgraphs don't `@link` the spec and share every field.
+    version1: bool,
+}
+
+impl Federation {
+    fn new(links: &[Directive]) -> Valid<Federation, String> {
+        let link = links.iter().find(|link| {
+            link.name == "link"
+                && string_argument(link, "url").is_some_and(|url| url.starts_with(FEDERATION_SPEC))
+        });
+
+        let Some(link) = link else {
+            let names = FEDERATION_DIRECTIVES
+                .iter()
+                .map(|name| (name.to_string(), *name))
+                .collect();
+            return Valid::succeed(Federation { names, version1: true });
+        };
+
+        let namespace = string_argument(link, "as").unwrap_or("federation");
+        let imports = link
+            .arguments
+            .get("import")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+
+        Valid::from_iter(imports, |import| {
+            let (name, alias) = match &import {
+                Value::String(name) => (name.clone(), name.clone()),
+                Value::Object(import) => {
+                    let name = import
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+                    let alias = import.get("as").and_then(Value::as_str).unwrap_or(name);
+                    (name.to_string(), alias.to_string())
+                }
+                _ => return Valid::fail(format!("Import `{}` is not valid", import)),
+            };
+
+            // Only directives are renamed, imported types are not needed.
+            let (Some(name), Some(alias)) = (name.strip_prefix('@'), alias.strip_prefix('@'))
+            else {
+                return Valid::none();
+            };
+
+            match FEDERATION_DIRECTIVES
+                .iter()
+                .find(|directive| **directive == name)
+            {
+                Some(directive) => Valid::succeed(Some((alias.to_string(), *directive))),
+                None => Valid::fail(format!(
+                    "Unknown federation directive `@{}` is imported",
+                    name
+                )),
+            }
+        })
+        .trace("@link")
+        .map(|imports| {
+            let names = FEDERATION_DIRECTIVES
+                .iter()
+                .map(|name| (format!("{}__{}", namespace, name), *name))
+                .chain(imports.into_iter().flatten())
+                .collect();
+            Federation { names, version1: false }
+        })
+    }
+
+    fn feature(&self, name: &str) -> Option<&'static str> {
+        self.names.get(name).copied()
+    }
+}
+
+// The parts of a subgraph schema that take part in composition.
+struct Schema {
+    name: String,
+    url: String,
+    graph: Graph,
+    federation: Federation,
+    types: IndexMap<String, TypeDef>,
+    directives: Vec<Positioned<S::DirectiveDefinition>>,
+}
+
+struct TypeDef {
+    pos: Pos,
+    description: Option<String>,
+    extension: bool,
+    directives: Vec<Directive>,
+    kind: Kind,
+}
+
+enum Kind {
+    Scalar,
+    Object(Vec<String>, Vec<FieldDef>),
+    Interface(Vec<String>, Vec<FieldDef>),
+    Union(Vec<String>),
+    Enum(Vec<ValueDef>),
+    InputObject(Vec<Positioned<S::InputValueDefinition>>),
+}
+
+struct FieldDef {
+    name: String,
+    pos: Pos,
+    description: Option<String>,
+    ty: S::Type,
+    args: Vec<Positioned<S::InputValueDefinition>>,
+    directives: Vec<Directive>,
+}
+
+struct ValueDef {
+    name: String,
+    description: Option<String>,
+    directives: Vec<Directive>,
+}
+
+type FieldSource<'a> = (&'a Schema, &'a TypeDef, &'a FieldDef);
+
+impl Schema {
+    fn parse(subgraph: &Subgraph) -> Valid<Schema, String> {
+        let name = subgraph.name.as_str();
+        let source = Some(name);
+
+        let document = match async_graphql_parser::parse_schema(&subgraph.sdl) {
+            Ok(document) => document,
+            Err(error) => return Valid::fail(format!("{}: {}", name, error)),
+        };
+
+        let repeatable = build::repeatable(&document, &subgraph.sdl);
+
+        let url = match url::Url::parse(&subgraph.url) {
+            Ok(_) => Valid::succeed(()),
+            Err(error) => Valid::fail(format!(
+                "Url `{}` of subgraph `{}` is not valid: {}",
+                subgraph.url, name, error
+            )),
+        };
+
+        let mut links = Vec::new();
+        let mut renames = BTreeMap::new();
+        let mut types = Vec::new();
+        let mut directives = Vec::new();
+
+        for definition in document.definitions {
+            match definition {
+                TypeSystemDefinition::Schema(schema) => {
+                    let roots = [
+                        (schema.node.query, "Query"),
+                        (schema.node.mutation, "Mutation"),
+                        (schema.node.subscription, "Subscription"),
+                    ];
+                    for (root, default) in roots {
+                        if let Some(root) = root.filter(|root| root.node != default) {
+                            renames.insert(root.node.to_string(), default.to_string());
+                        }
+                    }
+                    links.extend(schema.node.directives);
+                }
+                TypeSystemDefinition::Type(definition) => types.push(definition),
+                TypeSystemDefinition::Directive(mut definition) => {
+                    definition.node.is_repeatable = repeatable
+                        .get(definition.node.name.node.as_str())
+                        .copied()
+                        .unwrap_or(definition.node.is_repeatable);
+                    directives.push(definition)
+                }
+            }
+        }
+
+        let renames = Renames(renames);
+
+        build::parse_directives(links, source)
+            .and_then(|links| Federation::new(&links))
+            .trace("schema")
+            .and_then(|federation| {
+                let types = Valid::from_iter(types, |Positioned { pos, node }| {
+                    let type_name = renames.name(&node.name.node);
+                    if is_federation_type(&type_name)
+                        || (matches!(node.kind, TypeKind::Scalar)
+                            && BUILTIN_SCALARS.contains(&type_name.as_str()))
+                    {
+                        return Valid::none();
+                    }
+
+                    renames
+                        .type_def(node, pos.into(), name)
+                        .map(|type_def| Some((type_name.clone(), type_def)))
+                        .trace(&type_name)
+                })
+                .and_then(|types| {
+                    let mut merged = IndexMap::<String, TypeDef>::new();
+                    Valid::from_iter(types.into_iter().flatten(), |(type_name, type_def)| {
+                        match merged.shift_remove(&type_name) {
+                            Some(existing) => existing.merge(type_def),
+                            None => Valid::succeed(type_def),
+                        }
+                        .map(|type_def| {
+                            merged.insert(type_name.clone(), type_def);
+                        })
+                        .trace(&type_name)
+                    })
+                    .map(|_| merged)
+                });
+
+                let directives = directives
+                    .into_iter()
+                    .filter(|directive| {
+                        let directive_name = directive.node.name.node.as_str();
+                        !BUILTIN_DIRECTIVES.contains(&directive_name)
+                            && directive_name != "link"
+                            && federation.feature(directive_name).is_none()
+                            && !is_federation_type(directive_name)
+                    })
+                    .collect::<Vec<_>>();
+
+                types.map(|types| Schema {
+                    name: name.into(),
+                    url: subgraph.url.clone(),
+                    graph: Graph::new(graph_name(name)),
+                    federation,
+                    types,
+                    directives,
+                })
+            })
+            .zip(url)
+            .map(|(schema, _)| schema)
+    }
+
+    fn find<'a>(
+        &'a self,
+        directives: &'a [Directive],
+        feature: &'a str,
+    ) -> impl Iterator<Item = &'a Directive> + 'a {
+        directives
+            .iter()
+            .filter(move |directive| self.federation.feature(&directive.name) == Some(feature))
+    }
+
+    fn has(&self, directives: &[Directive], feature: &str) -> bool {
+        self.find(directives, feature).next().is_some()
+    }
+
+    fn key_fields(&self, type_def: &TypeDef) -> BTreeSet<String> {
+        self.find(&type_def.directives, "key")
+            .filter_map(|key| string_argument(key, "fields"))
+            .filter_map(|fields| {
+                let mut join_type = JoinType::new(self.graph.clone());
+                join_type.key = Some(fields.to_string());
+                JoinTypeParsed::validate_from(join_type).to_result().ok()
+            })
+            .flat_map(|join_type| top_level(join_type.key.as_ref()))
+            .collect()
+    }
+
+    fn is_shareable(&self, type_def: &TypeDef, field: &FieldDef) -> bool {
+        self.federation.version1
+            || self.has(&field.directives, "shareable")
+            || self.has(&type_def.directives, "shareable")
+            || self.key_fields(type_def).contains(&field.name)
+    }
+
+    fn is_composite(&self, type_name: &str) -> bool {
+        self.types
+            .get(type_name)
+            .is_some_and(|type_def| matches!(type_def.kind, Kind::Object(..) | Kind::Interface(..)))
+    }
+
+    // The `@join__type`s of a type in this subgraph. A subgraph that doesn't
+    // define the type only joins it as a root.
+    fn join_types(
+        &self,
+        type_name: &str,
+        type_def: Option<&TypeDef>,
+    ) -> Valid<Vec<(Directive, JoinTypeParsed)>, String> {
+        let Some(type_def) = type_def else {
+            let join_type = JoinType::new(self.graph.clone());
+            return JoinTypeParsed::validate_from(join_type.clone())
+                .map(|parsed| vec![(directive("join__type", &join_type), parsed)]);
+        };
+
+        let mut join_type = JoinType::new(self.graph.clone());
+        join_type.extension = type_def.extension || self.has(&type_def.directives, "extends");
+        join_type.is_interface_object = self.has(&type_def.directives, "interfaceObject");
+
+        let keys = self.find(&type_def.directives, "key").collect::<Vec<_>>();
+        if keys.is_empty() {
+            return JoinTypeParsed::validate_from(join_type.clone())
+                .map(|parsed| vec![(directive("join__type", &join_type), parsed)]);
+        }
+
+        Valid::from_iter(keys, |key| {
+            let join_type = JoinType {
+                key: string_argument(key, "fields").map(|fields| fields.to_string()),
+                resolvable: key
+                    .arguments
+                    .get("resolvable")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(true),
+                ..join_type.clone()
+            };
+
+            let parsed = JoinTypeParsed::validate_from(join_type.clone())
+                .and_then(|parsed| {
+                    let fields = type_def.kind.fields();
+                    Valid::from_iter(top_level(parsed.key.as_ref()), |name| {
+                        if fields.iter().any(|field| field.name == name) {
+                            Valid::succeed(())
+                        } else {
+                            Valid::fail(format!(
+                                "Key field `{}` is not a field of `{}`",
+                                name, type_name
+                            ))
+                        }
+                    })
+                    .map_to(parsed)
+                })
+                .trace("@key");
+
+            at(parsed, key.pos, Some(&self.name))
+                .map(|parsed| (directive("join__type", &join_type), parsed))
+        })
+    }
+
+    // The `@join__field` of a field in this subgraph, validating the field
+    // sets it references.
+    fn join_field(
+        &self,
+        type_def: &TypeDef,
+        field: &FieldDef,
+        of_type: &S::Type,
+    ) -> Valid<(Directive, JoinFieldParsed), String> {
+        let field_set = |feature: &str| {
+            self.find(&field.directives, feature)
+                .find_map(|directive| string_argument(directive, "fields"))
+                .map(|fields| fields.to_string())
+        };
+
+        let join_field = JoinField {
+            graph: Some(self.graph.clone()),
+            requires: field_set("requires"),
+            provides: field_set("provides"),
+            r#type: (field.ty != *of_type).then(|| field.ty.to_string()),
+            external: self.has(&field.directives, "external").then_some(true),
+            r#override: self
+                .find(&field.directives, "override")
+                .find_map(|directive| string_argument(directive, "from"))
+                .map(|from| from.to_string()),
+            override_label: None,
+            used_overridden: None,
+        };
+
+        let parsed = JoinFieldParsed::validate_from(join_field.clone()).and_then(|parsed| {
+            let provides = if parsed.provides.is_some() && !self.is_composite(base_name(&field.ty))
+            {
+                Valid::fail(format!(
+                    "@provides is used on a field of type `{}`, which is not an object or \
+                     interface",
+                    field.ty
+                ))
+            } else {
+                Valid::succeed(())
+            };
+
+            let fields = type_def.kind.fields();
+            let requires =
+                Valid::from_iter(top_level(parsed.requires.as_ref()), |name| {
+                    match fields.iter().find(|field| field.name == name) {
+                        Some(field) if self.has(&field.directives, "external") => {
+                            Valid::succeed(())
+                        }
+                        _ => Valid::fail(format!(
+                            "@requires references `{}`, which is not an @external field",
+                            name
+                        )),
+                    }
+                });
+
+            provides.zip(requires).map_to(parsed)
+        });
+
+        at(parsed, Some(field.pos), Some(&self.name))
+            .map(|parsed| (directive("join__field", &join_field), parsed))
+    }
+}
+
+// Root types are composed under their default names, whatever a subgraph
+// calls them.
+struct Renames(BTreeMap<String, String>);
+
+impl Renames {
+    fn name(&self, name: &str) -> String {
+        self.0
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    fn ty(&self, ty: &S::Type) -> S::Type {
+        let base = match &ty.base {
+            BaseType::Named(name) => {
+                BaseType::Named(async_graphql_value::Name::new(self.name(name)))
+            }
+            BaseType::List(of_type) => BaseType::List(Box::new(self.ty(of_type))),
+        };
+        S::Type { base, nullable: ty.nullable }
+    }
+
+    fn input_values(
+        &self,
+        values: Vec<Positioned<S::InputValueDefinition>>,
+    ) -> Vec<Positioned<S::InputValueDefinition>> {
+        values
+            .into_iter()
+            .map(|mut value| {
+                value.node.ty.node = self.ty(&value.node.ty.node);
+                value
+            })
+            .collect()
+    }
+
+    fn type_def(&self, node: S::TypeDefinition, pos: Pos, source: &str) -> Valid<TypeDef, String> {
+        let is_query = self.name(&node.name.node) == "Query";
+        let names = |names: Vec<Positioned<async_graphql_value::Name>>| {
+            names
+                .into_iter()
+                .map(|name| self.name(&name.node))
+                .collect::<Vec<_>>()
+        };
+
+        let kind = match node.kind {
+            TypeKind::Scalar => Valid::succeed(Kind::Scalar),
+            TypeKind::Object(object) => self
+                .fields(object.fields, is_query, source)
+                .map(|fields| Kind::Object(names(object.implements), fields)),
+            TypeKind::Interface(interface) => self
+                .fields(interface.fields, false, source)
+                .map(|fields| Kind::Interface(names(interface.implements), fields)),
+            TypeKind::Union(union) => Valid::succeed(Kind::Union(names(union.members))),
+            TypeKind::Enum(enumeration) => {
+                Valid::from_iter(enumeration.values, |Positioned { pos: _, node }| {
+                    let name = node.value.node.to_string();
+                    build::parse_directives(node.directives, Some(source))
+                        .map(|directives| ValueDef {
+                            name: name.clone(),
+                            description: node.description.map(|d| d.node),
+                            directives,
+                        })
+                        .trace(&name)
+                })
+                .map(Kind::Enum)
+            }
+            TypeKind::InputObject(input) => {
+                Valid::succeed(Kind::InputObject(self.input_values(input.fields)))
+            }
+        };
+
+        build::parse_directives(node.directives, Some(source))
+            .zip(kind)
+            .map(|(directives, kind)| TypeDef {
+                pos,
+                description: node.description.map(|d| d.node),
+                extension: node.extend,
+                directives,
+                kind,
+            })
+    }
+
+    fn fields(
+        &self,
+        fields: Vec<Positioned<S::FieldDefinition>>,
+        is_query: bool,
+        source: &str,
+    ) -> Valid<Vec<FieldDef>, String> {
+        Valid::from_iter(fields, |Positioned { pos, node }| {
+            let name = node.name.node.to_string();
+            if is_query && (name == "_service" || name == "_entities") {
+                return Valid::none();
+            }
+
+            build::parse_directives(node.directives, Some(source))
+                .map(|directives| {
+                    Some(FieldDef {
+                        name: name.clone(),
+                        pos: pos.into(),
+                        description: node.description.map(|d| d.node),
+                        ty: self.ty(&node.ty.node),
+                        args: self.input_values(node.arguments),
+                        directives,
+                    })
+                })
+                .trace(&name)
+        })
+        .map(|fields| fields.into_iter().flatten().collect())
+    }
+}
+
+impl TypeDef {
+    // A type can be defined and extended within the same subgraph.
+    fn merge(mut self, other: TypeDef) -> Valid<TypeDef, String> {
+        let kind = match (self.kind, other.kind) {
+            (Kind::Scalar, Kind::Scalar) => Kind::Scalar,
+            (Kind::Object(mut implements, mut fields), Kind::Object(more, more_fields)) => {
+                implements.extend(more);
+                fields.extend(more_fields);
+                Kind::Object(implements, fields)
+            }
+            (Kind::Interface(mut implements, mut fields), Kind::Interface(more, more_fields)) => {
+                implements.extend(more);
+                fields.extend(more_fields);
+                Kind::Interface(implements, fields)
+            }
+            (Kind::Union(mut members), Kind::Union(more)) => {
+                members.extend(more);
+                Kind::Union(members)
+            }
+            (Kind::Enum(mut values), Kind::Enum(more)) => {
+                values.extend(more);
+                Kind::Enum(values)
+            }
+            (Kind::InputObject(mut fields), Kind::InputObject(more)) => {
+                fields.extend(more);
+                Kind::InputObject(fields)
+            }
+            (kind, other_kind) => {
+                return Valid::fail(format!(
+                    "Type is defined as {} and extended as {}",
+                    kind.describe(),
+                    other_kind.describe()
+                ))
+            }
+        };
+
+        if self.extension && !other.extension {
+            self.pos = other.pos;
+        }
+        self.description = self.description.or(other.description);
+        self.extension = self.extension && other.extension;
+        self.directives.extend(other.directives);
+
+        Valid::succeed(TypeDef { kind, ..self })
+    }
+}
+
+impl Kind {
+    fn describe(&self) -> &'static str {
+        match self {
+            Kind::Scalar => "a scalar",
+            Kind::Object(..) => "an object",
+            Kind::Interface(..) => "an interface",
+            Kind::Union(..) => "a union",
+            Kind::Enum(..) => "an enum",
+            Kind::InputObject(..) => "an input object",
+        }
+    }
+
+    fn fields(&self) -> &[FieldDef] {
+        match self {
+            Kind::Object(_, fields) | Kind::Interface(_, fields) => fields,
+            _ => &[],
+        }
+    }
+}
+
+struct Composer {
+    schemas: Vec<Schema>,
+}
+
+impl Composer {
+    fn compose(&self) -> Valid<Blueprint, String> {
+        let mut types = BTreeMap::<&str, Vec<(&Schema, &TypeDef)>>::new();
+        for schema in self.schemas.iter() {
+            for (name, type_def) in schema.types.iter() {
+                types.entry(name).or_default().push((schema, type_def));
+            }
+        }
+
+        let query = Valid::from_option(
+            types.contains_key("Query").then(|| "Query".to_string()),
+            "None of the subgraphs defines a `Query` type".to_string(),
+        );
+        let schema = query.map(|query| SchemaDefinition {
+            query: Some(query),
+            mutation: types
+                .contains_key("Mutation")
+                .then(|| "Mutation".to_string()),
+            subscription: types
+                .contains_key("Subscription")
+                .then(|| "Subscription".to_string()),
+            directives: Vec::new(),
+        });
+
+        Valid::from_iter(types.iter(), |(name, defs)| {
+            self.compose_type(name, defs).trace(name)
+        })
+        .fuse(self.compose_directives())
+        .fuse(schema)
+        .and_then(|(definitions, directives, schema)| {
+            let inaccessible = uses(&definitions, "inaccessible");
+            let tag = uses(&definitions, "tag");
+
+            self.prelude(inaccessible, tag).map(|prelude| {
+                let mut definitions = prelude
+                    .definitions
+                    .into_iter()
+                    .chain(definitions.into_iter().map(Arc::new))
+                    .collect::<Vec<_>>();
+                definitions.sort_by(|a, b| a.name().cmp(b.name()));
+
+                let mut directives = prelude
+                    .directives
+                    .into_iter()
+                    .chain(directives.into_iter().flatten())
+                    .collect::<Vec<_>>();
+                directives.sort_by(|a, b| a.name.cmp(&b.name));
+
+                Blueprint {
+                    definitions,
+                    schema: SchemaDefinition { directives: prelude.schema.directives, ..schema },
+                    directives,
+                    join_graphs: prelude.join_graphs,
+                    links: prelude.links,
+                    source: None,
+                }
+            })
+        })
+    }
+
+    // The definitions every supergraph has: the `@link`ed specs, their
+    // directives and the `join__Graph` enumeration of the subgraphs.
+    fn prelude(&self, inaccessible: bool, tag: bool) -> Valid<Blueprint, String> {
+        let mut links = vec![
+            r#"@link(url: "https://specs.apollo.dev/link/v1.0")"#,
+            r#"@link(url: "https://specs.apollo.dev/join/v0.3", for: EXECUTION)"#,
+        ];
+        let mut directives = vec![PRELUDE];
+        if inaccessible {
+            links.push(r#"@link(url: "https://specs.apollo.dev/inaccessible/v0.2", import: ["@inaccessible"], for: SECURITY)"#);
+            directives.push(INACCESSIBLE);
+        }
+        if tag {
+            links.push(r#"@link(url: "https://specs.apollo.dev/tag/v0.3", import: ["@tag"])"#);
+            directives.push(TAG);
+        }
+
+        let graphs = self
+            .schemas
+            .iter()
+            .map(|schema| {
+                format!(
+                    "  {} @join__graph(name: {}, url: {})",
+                    schema.graph.as_str(),
+                    Value::from(schema.name.as_str()),
+                    Value::from(schema.url.as_str())
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let sdl = format!(
+            "extend schema {}\n{}\nenum join__Graph {{\n{}\n}}\n",
+            links.join(" "),
+            directives.join("\n"),
+            graphs.join("\n")
+        );
+
+        build::parse_text(&sdl, None).map(|mut prelude| {
+            prelude.forget_positions();
+            prelude
+        })
+    }
+
+    fn compose_type(&self, name: &str, defs: &[(&Schema, &TypeDef)]) -> Valid<Definition, String> {
+        // An `@interfaceObject` is how a subgraph contributes to an interface
+        // without knowing its implementations.
+        let describe = |(schema, type_def): &(&Schema, &TypeDef)| {
+            if schema.has(&type_def.directives, "interfaceObject") {
+                "an interface"
+            } else {
+                type_def.kind.describe()
+            }
+        };
+
+        let kinds = defs.iter().map(describe).collect::<BTreeSet<_>>();
+        if kinds.len() > 1 {
+            return Valid::fail(format!(
+                "Type is defined as {}",
+                defs.iter()
+                    .map(|def| format!("{} in `{}`", describe(def), def.0.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        let main = defs
+            .iter()
+            .find(|(schema, type_def)| !schema.has(&type_def.directives, "interfaceObject"))
+            .unwrap_or(&defs[0]);
+
+        match &main.1.kind {
+            Kind::Object(..) => self.compose_object(name, defs),
+            Kind::Interface(..) => self.compose_interface(name, defs),
+            Kind::Union(..) => self.compose_union(name, defs),
+            Kind::Enum(..) => self.compose_enum(name, defs),
+            Kind::InputObject(..) => self.compose_input_object(name, defs),
+            Kind::Scalar => self.compose_scalar(name, defs),
+        }
+    }
+
+    fn join_types(
+        &self,
+        name: &str,
+        defs: &[(&Schema, &TypeDef)],
+    ) -> Valid<(Vec<Directive>, Vec<JoinTypeParsed>), String> {
+        // The query root is joined in every subgraph, they all can resolve
+        // `_service`.
+        let graphs = if name == "Query" {
+            self.schemas
+                .iter()
+                .map(|schema| {
+                    let def = defs.iter().find(|(def, _)| def.name == schema.name);
+                    (schema, def.map(|(_, type_def)| *type_def))
+                })
+                .collect::<Vec<_>>()
+        } else {
+            defs.iter()
+                .map(|(schema, type_def)| (*schema, Some(*type_def)))
+                .collect()
+        };
+
+        Valid::from_iter(graphs, |(schema, type_def)| {
+            schema.join_types(name, type_def)
+        })
+        .map(|join_types| join_types.into_iter().flatten().unzip())
+    }
+
+    fn join_implements(defs: &[(&Schema, &TypeDef)]) -> (Vec<Directive>, Vec<JoinImplements>) {
+        defs.iter()
+            .flat_map(|(schema, type_def)| {
+                let implements = match &type_def.kind {
+                    Kind::Object(implements, _) | Kind::Interface(implements, _) => {
+                        implements.as_slice()
+                    }
+                    _ => &[],
+                };
+                implements.iter().map(|interface| {
+                    let join_implements = JoinImplements {
+                        graph: schema.graph.clone(),
+                        interface: interface.clone(),
+                    };
+                    (
+                        directive("join__implements", &join_implements),
+                        join_implements,
+                    )
+                })
+            })
+            .unzip()
+    }
+
+    fn compose_object(
+        &self,
+        name: &str,
+        defs: &[(&Schema, &TypeDef)],
+    ) -> Valid<Definition, String> {
+        self.join_types(name, defs)
+            .and_then(|(join_type_directives, join_types)| {
+                self.compose_fields(name, defs, &join_types, true)
+                    .map(|fields| {
+                        let (join_implements_directives, join_implements) =
+                            Self::join_implements(defs);
+                        let implements = join_implements
+                            .iter()
+                            .map(|join_implements| join_implements.interface.clone())
+                            .collect();
+
+                        Definition::Object(ObjectTypeDefinition {
+                            name: name.into(),
+                            fields,
+                            directives: join_implements_directives
+                                .into_iter()
+                                .chain(join_type_directives)
+                                .chain(carried(type_directives(defs)))
+                                .collect(),
+                            description: description(defs),
+                            implements,
+                            join_types,
+                            join_implements,
+                            pos: None,
+                        })
+                    })
+            })
+    }
+
+    fn compose_interface(
+        &self,
+        name: &str,
+        defs: &[(&Schema, &TypeDef)],
+    ) -> Valid<Definition, String> {
+        self.join_types(name, defs)
+            .and_then(|(join_type_directives, join_types)| {
+                self.compose_fields(name, defs, &join_types, false)
+                    .map(|fields| {
+                        let (join_implements_directives, join_implements) =
+                            Self::join_implements(defs);
+                        let implements = join_implements
+                            .iter()
+                            .map(|join_implements| join_implements.interface.clone())
+                            .collect();
+
+                        Definition::Interface(InterfaceTypeDefinition {
+                            name: name.into(),
+                            fields,
+                            implements,
+                            directives: join_implements_directives
+                                .into_iter()
+                                .chain(join_type_directives)
+                                .chain(carried(type_directives(defs)))
+                                .collect(),
+                            description: description(defs),
+                            join_types,
+                            join_implements,
+                            pos: None,
+                        })
+                    })
+            })
+    }
+
+    fn compose_union(&self, name: &str, defs: &[(&Schema, &TypeDef)]) -> Valid<Definition, String> {
+        let (join_union_directives, join_unions): (Vec<_>, Vec<_>) = defs
+            .iter()
+            .flat_map(|(schema, type_def)| {
+                let members = match &type_def.kind {
+                    Kind::Union(members) => members.as_slice(),
+                    _ => &[],
+                };
+                members.iter().map(|member| {
+                    let join_union =
+                        JoinUnion { graph: schema.graph.clone(), member: member.clone() };
+                    (directive("join__unionMember", &join_union), join_union)
+                })
+            })
+            .unzip();
+
+        self.join_types(name, defs)
+            .map(|(join_type_directives, join_types)| {
+                Definition::Union(UnionTypeDefinition {
+                    name: name.into(),
+                    directives: join_type_directives
+                        .into_iter()
+                        .chain(join_union_directives)
+                        .chain(carried(type_directives(defs)))
+                        .collect(),
+                    description: description(defs),
+                    types: join_unions
+                        .iter()
+                        .map(|join_union| join_union.member.clone())
+                        .collect(),
+                    join_types,
+                    join_unions,
+                    pos: None,
+                })
+            })
+    }
+
+    fn compose_enum(&self, name: &str, defs: &[(&Schema, &TypeDef)]) -> Valid<Definition, String> {
+        let mut values = IndexMap::<&str, Vec<(&Schema, &ValueDef)>>::new();
+        for (schema, type_def) in defs {
+            if let Kind::Enum(enum_values) = &type_def.kind {
+                for value in enum_values {
+                    values.entry(&value.name).or_default().push((schema, value));
+                }
+            }
+        }
+
+        let enum_values = values
+            .into_iter()
+            .map(|(value_name, sources)| {
+                let join_enums = sources
+                    .iter()
+                    .map(|(schema, _)| JoinEnum { graph: schema.graph.clone() })
+                    .collect::<Vec<_>>();
+
+                EnumValueDefinition {
+                    description: sources
+                        .iter()
+                        .find_map(|(_, value)| value.description.clone()),
+                    name: value_name.to_string(),
+                    directives: carried(
+                        sources
+                            .iter()
+                            .map(|(schema, value)| (*schema, value.directives.as_slice())),
+                    )
+                    .into_iter()
+                    .chain(
+                        join_enums
+                            .iter()
+                            .map(|join_enum| directive("join__enumValue", join_enum)),
+                    )
+                    .collect(),
+                    join_enums,
+                }
+            })
+            .collect();
+
+        self.join_types(name, defs)
+            .map(|(join_type_directives, join_types)| {
+                Definition::Enum(EnumTypeDefinition {
+                    name: name.into(),
+                    directives: join_type_directives
+                        .into_iter()
+                        .chain(carried(type_directives(defs)))
+                        .collect(),
+                    description: description(defs),
+                    enum_values,
+                    join_types,
+                    pos: None,
+                })
+            })
+    }
+
+    fn compose_input_object(
+        &self,
+        name: &str,
+        defs: &[(&Schema, &TypeDef)],
+    ) -> Valid<Definition, String> {
+        let sources = defs
+            .iter()
+            .map(|(schema, type_def)| {
+                let fields = match &type_def.kind {
+                    Kind::InputObject(fields) => fields.as_slice(),
+                    _ => &[],
+                };
+                (*schema, fields)
+            })
+            .collect::<Vec<_>>();
+
+        self.join_types(name, defs)
+            .fuse(compose_inputs("Input field", &sources))
+            .map(|((join_type_directives, join_types), fields)| {
+                Definition::InputObject(InputObjectTypeDefinition {
+                    name: name.into(),
+                    fields,
+                    directives: join_type_directives
+                        .into_iter()
+                        .chain(carried(type_directives(defs)))
+                        .collect(),
+                    description: description(defs),
+                    join_types,
+                    pos: None,
+                })
+            })
+    }
+
+    fn compose_scalar(
+        &self,
+        name: &str,
+        defs: &[(&Schema, &TypeDef)],
+    ) -> Valid<Definition, String> {
+        self.join_types(name, defs)
+            .map(|(join_type_directives, join_types)| {
+                Definition::Scalar(ScalarTypeDefinition {
+                    name: name.into(),
+                    directives: join_type_directives
+                        .into_iter()
+                        .chain(carried(type_directives(defs)))
+                        .collect(),
+                    description: description(defs),
+                    join_types,
+                    pos: None,
+                })
+            })
+    }
+
+    fn compose_fields(
+        &self,
+        type_name: &str,
+        defs: &[(&Schema, &TypeDef)],
+        join_types: &[JoinTypeParsed],
+        is_object: bool,
+    ) -> Valid<Vec<FieldDefinition>, String> {
+        let mut fields = IndexMap::<&str, Vec<FieldSource>>::new();
+        for (schema, type_def) in defs {
+            for field in type_def.kind.fields() {
+                fields
+                    .entry(&field.name)
+                    .or_default()
+                    .push((schema, type_def, field));
+            }
+        }
+
+        let graphs = join_types
+            .iter()
+            .map(|join_type| &join_type.graph)
+            .collect::<BTreeSet<_>>()
+            .len();
+
+        Valid::from_iter(fields, |(name, sources)| {
+            compose_field(type_name, name, &sources, graphs, is_object).trace(name)
+        })
+    }
+
+    // Executable directives are kept when every subgraph defines them, on the
+    // locations they all agree on.
+    fn compose_directives(&self) -> Valid<Vec<Option<DirectiveDefinition>>, String> {
+        let mut directives = IndexMap::<&str, Vec<(&Schema, &S::DirectiveDefinition)>>::new();
+        for schema in self.schemas.iter() {
+            for definition in schema.directives.iter() {
+                directives
+                    .entry(&definition.node.name.node)
+                    .or_default()
+                    .push((schema, &definition.node));
+            }
+        }
+
+        Valid::from_iter(directives, |(name, defs)| {
+            if defs.len() < self.schemas.len() {
+                return Valid::none();
+            }
+
+            Valid::from_iter(defs, |(schema, definition)| {
+                build::parse_directive_definition(
+                    definition.clone(),
+                    &Links::default(),
+                    Some(&schema.name),
+                )
+            })
+            .map(|definitions| {
+                let locations = definitions[0]
+                    .locations
+                    .iter()
+                    .filter(|location| location.is_executable())
+                    .filter(|location| {
+                        definitions
+                            .iter()
+                            .all(|definition| definition.locations.contains(location))
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let repeatable = definitions.iter().all(|definition| definition.repeatable);
+
+                let definition = definitions.into_iter().next()?;
+                (!locations.is_empty()).then(|| DirectiveDefinition {
+                    arguments: definition
+                        .arguments
+                        .into_iter()
+                        .map(|argument| InputFieldDefinition { pos: None, ..argument })
+                        .collect(),
+                    repeatable,
+                    locations,
+                    ..definition
+                })
+            })
+            .trace(&format!("@{}", name))
+        })
+    }
+}
+
+fn compose_field(
+    type_name: &str,
+    name: &str,
+    sources: &[FieldSource],
+    graphs: usize,
+    is_object: bool,
+) -> Valid<FieldDefinition, String> {
+    let external = |(schema, _, field): &&FieldSource| schema.has(&field.directives, "external");
+
+    let overridden = sources
+        .iter()
+        .flat_map(|(schema, _, field)| schema.find(&field.directives, "override"))
+        .filter_map(|directive| string_argument(directive, "from"))
+        .collect::<BTreeSet<_>>();
+    let kept = sources
+        .iter()
+        .filter(|source| !overridden.contains(source.0.name.as_str()) || external(source))
+        .copied()
+        .collect::<Vec<_>>();
+    let resolving = kept
+        .iter()
+        .filter(|source| !external(source))
+        .copied()
+        .collect::<Vec<_>>();
+
+    if kept.is_empty() {
+        return Valid::fail(format!(
+            "Field `{}.{}` is overridden in every subgraph that defines it",
+            type_name, name
+        ));
+    }
+
+    let resolved = if is_object && resolving.is_empty() {
+        Valid::fail(format!(
+            "Field `{}.{}` is @external in every subgraph that defines it",
+            type_name, name
+        ))
+    } else if is_object
+        && resolving.len() > 1
+        && resolving
+            .iter()
+            .any(|(schema, type_def, field)| !schema.is_shareable(type_def, field))
+    {
+        Valid::fail(format!(
+            "Non-shareable field `{}.{}` is resolved by multiple subgraphs: {}",
+            type_name,
+            name,
+            subgraph_list(resolving.iter().map(|(schema, _, _)| *schema))
+        ))
+    } else {
+        Valid::succeed(())
+    };
+
+    let of_type = kept
+        .iter()
+        .skip(1)
+        .try_fold(kept[0].2.ty.clone(), |of_type, (_, _, field)| {
+            merge_types(&of_type, &field.ty, true)
+        });
+    let of_type = Valid::from_option(
+        of_type,
+        format!(
+            "Field `{}.{}` has incompatible types across subgraphs: {}",
+            type_name,
+            name,
+            kept.iter()
+                .map(|(schema, _, field)| format!("`{}` in `{}`", field.ty, schema.name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    );
+
+    let arguments = if resolving.is_empty() {
+        &kept
+    } else {
+        &resolving
+    };
+    let arguments = arguments
+        .iter()
+        .map(|(schema, _, field)| (*schema, field.args.as_slice()))
+        .collect::<Vec<_>>();
+
+    resolved.and(of_type).and_then(|of_type| {
+        Valid::from_iter(kept.iter(), |(schema, type_def, field)| {
+            schema.join_field(type_def, field, &of_type)
+        })
+        .fuse(compose_inputs("Argument", &arguments))
+        .map(|(join_fields, args)| {
+            // Fields resolved the same way by every subgraph of the type
+            // need no `@join__field`.
+            let needed = kept.len() < graphs
+                || !overridden.is_empty()
+                || join_fields.iter().any(|(directive, _)| {
+                    directive
+                        .arguments
+                        .as_object()
+                        .is_some_and(|arguments| arguments.len() > 1)
+                });
+            let (join_field_directives, join_fields): (Vec<_>, Vec<_>) = if needed {
+                join_fields.into_iter().unzip()
+            } else {
+                Default::default()
+            };
+
+            FieldDefinition {
+                name: name.into(),
+                args,
+                of_type: build::map_type(&of_type),
+                directives: carried(
+                    kept.iter()
+                        .map(|(schema, _, field)| (*schema, field.directives.as_slice())),
+                )
+                .into_iter()
+                .chain(join_field_directives)
+                .collect(),
+                description: resolving
+                    .iter()
+                    .chain(kept.iter())
+                    .find_map(|(_, _, field)| field.description.clone()),
+                join_fields,
+                pos: None,
+            }
+        })
+    })
+}
+
+// Arguments and input fields are composed to what every subgraph accepts.
+fn compose_inputs(
+    kind: &str,
+    sources: &[(&Schema, &[Positioned<S::InputValueDefinition>])],
+) -> Valid<Vec<InputFieldDefinition>, String> {
+    let mut inputs = IndexMap::<&str, Vec<(&Schema, &Positioned<S::InputValueDefinition>)>>::new();
+    for (schema, values) in sources {
+        for value in values.iter() {
+            inputs
+                .entry(&value.node.name.node)
+                .or_default()
+                .push((schema, value));
+        }
+    }
+
+    Valid::from_iter(inputs, |(name, defs)| {
+        if defs.len() < sources.len() {
+            let required = defs
+                .iter()
+                .filter(|(_, value)| {
+                    !value.node.ty.node.nullable && value.node.default_value.is_none()
+                })
+                .map(|(schema, _)| *schema)
+                .collect::<Vec<_>>();
+            if required.is_empty() {
+                return Valid::none();
+            }
+
+            let missing = sources
+                .iter()
+                .map(|(schema, _)| *schema)
+                .filter(|schema| !defs.iter().any(|(def, _)| def.name == schema.name));
+            return Valid::fail(format!(
+                "{} `{}` is required in {} but missing in {}",
+                kind,
+                name,
+                subgraph_list(required.into_iter()),
+                subgraph_list(missing)
+            ));
+        }
+
+        let of_type = defs
+            .iter()
+            .skip(1)
+            .try_fold(defs[0].1.node.ty.node.clone(), |of_type, (_, value)| {
+                merge_types(&of_type, &value.node.ty.node, false)
+            });
+
+        Valid::from_option(
+            of_type,
+            format!(
+                "{} `{}` has incompatible types across subgraphs: {}",
+                kind,
+                name,
+                defs.iter()
+                    .map(|(schema, value)| format!("`{}` in `{}`", value.node.ty.node, schema.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        )
+        .and_then(|of_type| {
+            Valid::from_iter(defs.iter(), |(schema, value)| {
+                build::parse_input_field(
+                    value.node.clone(),
+                    value.pos.into(),
+                    &Links::default(),
+                    Some(&schema.name),
+                )
+                .map(|input| (*schema, input))
+            })
+            .map(|inputs| {
+                let directives = carried(
+                    inputs
+                        .iter()
+                        .map(|(schema, input)| (*schema, input.directives.as_slice())),
+                );
+                let description = inputs
+                    .iter()
+                    .find_map(|(_, input)| input.description.clone());
+
+                inputs
+                    .into_iter()
+                    .next()
+                    .map(|(_, input)| InputFieldDefinition {
+                        of_type: build::map_type(&of_type),
+                        directives,
+                        description,
+                        join_fields: Vec::new(),
+                        pos: None,
+                        ..input
+                    })
+            })
+        })
+        .trace(name)
+    })
+    .map(|inputs| inputs.into_iter().flatten().collect())
+}
+
+// Merges the types an element is declared with in different subgraphs. Outputs
+// take the least restrictive nullability and inputs the most restrictive one.
+fn merge_types(a: &S::Type, b: &S::Type, output: bool) -> Option<S::Type> {
+    let base = match (&a.base, &b.base) {
+        (BaseType::Named(a), BaseType::Named(b)) if a == b => BaseType::Named(a.clone()),
+        (BaseType::List(a), BaseType::List(b)) => {
+            BaseType::List(Box::new(merge_types(a, b, output)?))
+        }
+        _ => return None,
+    };
+    let nullable = if output {
+        a.nullable || b.nullable
+    } else {
+        a.nullable && b.nullable
+    };
+
+    Some(S::Type { base, nullable })
+}
+
+// The directives of the subgraphs that are kept in the supergraph, under their
+// names in the supergraph.
+fn carried<'a>(sources: impl Iterator<Item = (&'a Schema, &'a [Directive])>) -> Vec<Directive> {
+    let mut carried = Vec::<Directive>::new();
+    for (schema, directives) in sources {
+        for directive in directives {
+            let name = match schema.federation.feature(&directive.name) {
+                Some(feature @ ("inaccessible" | "tag")) => feature,
+                Some(_) => continue,
+                None if directive.name == "deprecated" || directive.name == "specifiedBy" => {
+                    directive.name.as_str()
+                }
+                None => continue,
+            };
+
+            let directive = Directive::new(name, directive.arguments.clone());
+            if !carried.iter().any(|existing| {
+                existing.name == directive.name && existing.arguments == directive.arguments
+            }) {
+                carried.push(directive);
+            }
+        }
+    }
+    carried
+}
+
+fn type_directives<'a>(
+    defs: &'a [(&'a Schema, &'a TypeDef)],
+) -> impl Iterator<Item = (&'a Schema, &'a [Directive])> {
+    defs.iter()
+        .map(|(schema, type_def)| (*schema, type_def.directives.as_slice()))
+}
+
+fn description(defs: &[(&Schema, &TypeDef)]) -> Option<String> {
+    defs.iter()
+        .find_map(|(_, type_def)| type_def.description.clone())
+}
+
+// Whether a directive is applied anywhere in the composed definitions.
+fn uses(definitions: &[Definition], name: &str) -> bool {
+    let applied = |directives: &[Directive]| directives.iter().any(|d| d.name == name);
+    let inputs =
+        |inputs: &[InputFieldDefinition]| inputs.iter().any(|input| applied(&input.directives));
+    let fields = |fields: &[FieldDefinition]| {
+        fields
+            .iter()
+            .any(|field| applied(&field.directives) || inputs(&field.args))
+    };
+
+    definitions.iter().any(|definition| match definition {
+        Definition::Interface(def) => applied(&def.directives) || fields(&def.fields),
+        Definition::Object(def) => applied(&def.directives) || fields(&def.fields),
+        Definition::InputObject(def) => applied(&def.directives) || inputs(&def.fields),
+        Definition::Scalar(def) => applied(&def.directives),
+        Definition::Enum(def) => {
+            applied(&def.directives)
+                || def
+                    .enum_values
+                    .iter()
+                    .any(|value| applied(&value.directives))
+        }
+        Definition::Union(def) => applied(&def.directives),
+    })
+}
+
+fn directive<A: Serialize>(name: &str, arguments: &A) -> Directive {
+    Directive::new(name, serde_json::to_value(arguments).unwrap_or_default())
+}
+
+fn string_argument<'a>(directive: &'a Directive, name: &str) -> Option<&'a str> {
+    directive.arguments.get(name).and_then(Value::as_str)
+}
+
+fn top_level(selections: Option<&SelectionSet>) -> Vec<String> {
+    selections
+        .map(|selections| {
+            selections
+                .fields()
+                .map(|field| field.name.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn base_name(ty: &S::Type) -> &str {
+    match &ty.base {
+        BaseType::Named(name) => name,
+        BaseType::List(of_type) => base_name(of_type),
+    }
+}
+
+fn graph_name(name: &str) -> String {
+    let graph = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    if graph.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", graph)
+    } else {
+        graph
+    }
+}
+
+fn is_federation_type(name: &str) -> bool {
+    FEDERATION_TYPES.contains(&name)
+        || name.starts_with("link__")
+        || name.starts_with("federation__")
+}
+
+fn subgraph_list<'a>(schemas: impl Iterator<Item = &'a Schema>) -> String {
+    schemas
+        .map(|schema| format!("`{}`", schema.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose() {
+        let subgraphs = [
+            Subgraph::new(
+                "reviews",
+                "http://localhost:4003",
+                include_str!("fixtures/subgraphs/reviews.graphql"),
+            ),
+            Subgraph::new(
+                "accounts",
+                "http://localhost:4001",
+                include_str!("fixtures/subgraphs/accounts.graphql"),
+            ),
+            Subgraph::new(
+                "products",
+                "http://localhost:4002",
+                include_str!("fixtures/subgraphs/products.graphql"),
+            ),
+        ];
+        let composed = compose(&subgraphs).to_result().unwrap();
+
+        let mut expected = Blueprint::parse(include_str!("fixtures/router.graphql"))
+            .to_result()
+            .unwrap();
+        expected.forget_positions();
+
+        assert_eq!(
+            serde_json::to_value(&composed).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_compose_errors() {
+        let subgraphs = [
+            Subgraph::new(
+                "accounts",
+                "http://localhost:4001",
+                include_str!("../../../GraphQL/accounts.graphql"),
+            ),
+            Subgraph::new(
+                "products",
+                "http://localhost:4002",
+                include_str!("../../../GraphQL/products.graphql"),
+            ),
+            Subgraph::new(
+                "reviews",
+                "http://localhost:4003",
+                include_str!("../../../GraphQL/reviews.graphql"),
+            ),
+        ];
+        let error = compose(&subgraphs).to_result().unwrap_err();
+
+        insta::assert_snapshot!(error.to_string());
+    }
+}
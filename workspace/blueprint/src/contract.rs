@@ -0,0 +1,517 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use valid::{Valid, Validator};
+
+use crate::compose::INACCESSIBLE;
+use crate::{
+    build, Blueprint, Definition, Directive, FieldDefinition, Import, InputFieldDefinition, Link,
+    Purpose, Type, Version,
+};
+
+/// The tags selecting what a contract variant of a supergraph serves. With
+/// an empty include set everything not excluded is served.
+#[derive(Clone, Debug, Default)]
+pub struct Contract {
+    pub include: BTreeSet<String>,
+    pub exclude: BTreeSet<String>,
+}
+
+impl Contract {
+    pub fn new<A: AsRef<str>, B: AsRef<str>>(
+        include: impl IntoIterator<Item = A>,
+        exclude: impl IntoIterator<Item = B>,
+    ) -> Self {
+        Contract {
+            include: include
+                .into_iter()
+                .map(|tag| tag.as_ref().to_string())
+                .collect(),
+            exclude: exclude
+                .into_iter()
+                .map(|tag| tag.as_ref().to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Derives the supergraph of a contract variant: what the contract doesn't
+/// select is marked `@inaccessible`, along with what can no longer be
+/// reached from the roots. The API schema of the result must be valid.
+pub fn contract(blueprint: &Blueprint, contract: &Contract) -> Valid<Blueprint, String> {
+    let mut blueprint = blueprint.clone();
+    link_inaccessible(&mut blueprint);
+
+    let filter = Filter {
+        contract,
+        tag: blueprint.links.directive("tag", "tag"),
+        inaccessible: blueprint.links.directive("inaccessible", "inaccessible"),
+    };
+
+    let links = blueprint.links.clone();
+    for definition in blueprint
+        .definitions
+        .iter_mut()
+        .filter(|definition| !links.owns(definition.name()))
+    {
+        filter.select(Arc::make_mut(definition));
+    }
+
+    while filter.cascade(&mut blueprint) {}
+    filter.prune(&mut blueprint);
+
+    blueprint.to_api_schema().map_to(blueprint)
+}
+
+// Contracts hide through `@inaccessible`, so the supergraph has to link it.
+fn link_inaccessible(blueprint: &mut Blueprint) {
+    if blueprint.links.get("inaccessible").is_some() {
+        return;
+    }
+
+    let url = "https://specs.apollo.dev/inaccessible/v0.2";
+    let link = Link {
+        url: url.to_string(),
+        name: "inaccessible".to_string(),
+        version: Some(Version::new(0, 2)),
+        namespace: "inaccessible".to_string(),
+        imports: vec![Import {
+            name: "@inaccessible".to_string(),
+            alias: "@inaccessible".to_string(),
+        }],
+        purpose: Some(Purpose::Security),
+    };
+
+    // Federation 1 supergraphs link with `@core`.
+    let bootstrap = match (blueprint.links.get("link"), blueprint.links.get("core")) {
+        (Some(link), _) => Some((link.directive("link"), "url")),
+        (None, Some(core)) => Some((core.directive("core"), "feature")),
+        (None, None) => None,
+    };
+    if let Some((name, argument)) = bootstrap {
+        blueprint.schema.directives.push(Directive::new(
+            name,
+            json!({ argument: url, "import": ["@inaccessible"], "for": "SECURITY" }),
+        ));
+    }
+    blueprint.links.push(link);
+
+    if !blueprint
+        .directives
+        .iter()
+        .any(|directive| directive.name == "inaccessible")
+    {
+        let document = async_graphql_parser::parse_schema(INACCESSIBLE).unwrap();
+        if let Some(async_graphql_parser::types::TypeSystemDefinition::Directive(directive)) =
+            document.definitions.into_iter().next()
+        {
+            if let Ok(directive) =
+                build::parse_directive_definition(directive.node, &blueprint.links, None)
+                    .to_result()
+            {
+                blueprint.directives.push(directive);
+                blueprint.directives.sort_by(|a, b| a.name.cmp(&b.name));
+            }
+        }
+    }
+}
+
+struct Filter<'a> {
+    contract: &'a Contract,
+    tag: String,
+    inaccessible: String,
+}
+
+impl Filter<'_> {
+    fn tags<'b>(&self, directives: &'b [Directive]) -> impl Iterator<Item = &'b str> + 'b {
+        let tag = self.tag.clone();
+        directives
+            .iter()
+            .filter(move |directive| directive.name == tag)
+            .filter_map(|directive| directive.arguments.get("name").and_then(Value::as_str))
+    }
+
+    fn excluded(&self, directives: &[Directive]) -> bool {
+        self.tags(directives)
+            .any(|tag| self.contract.exclude.contains(tag))
+    }
+
+    fn included(&self, directives: &[Directive]) -> bool {
+        self.tags(directives)
+            .any(|tag| self.contract.include.contains(tag))
+    }
+
+    fn filters_included(&self) -> bool {
+        !self.contract.include.is_empty()
+    }
+
+    fn is_hidden(&self, directives: &[Directive]) -> bool {
+        directives
+            .iter()
+            .any(|directive| directive.name == self.inaccessible)
+    }
+
+    // Returns whether the element was visible.
+    fn hide(&self, directives: &mut Vec<Directive>) -> bool {
+        if self.is_hidden(directives) {
+            return false;
+        }
+
+        directives.push(Directive::new(&self.inaccessible, json!({})));
+        true
+    }
+
+    // Applies the tags: exclusion wins over inclusion, and types and fields
+    // are included with their own tags or, for fields, their type's.
+    fn select(&self, definition: &mut Definition) {
+        let (directives, fields) = match definition {
+            Definition::Object(def) => (&mut def.directives, &mut def.fields),
+            Definition::Interface(def) => (&mut def.directives, &mut def.fields),
+            Definition::Union(def) => {
+                if self.excluded(&def.directives)
+                    || (self.filters_included() && !self.included(&def.directives))
+                {
+                    self.hide(&mut def.directives);
+                }
+                return;
+            }
+            Definition::InputObject(def) => {
+                if self.excluded(&def.directives) {
+                    self.hide(&mut def.directives);
+                }
+                for field in def.fields.iter_mut() {
+                    if self.excluded(&field.directives) {
+                        self.hide(&mut field.directives);
+                    }
+                }
+                return;
+            }
+            Definition::Enum(def) => {
+                if self.excluded(&def.directives) {
+                    self.hide(&mut def.directives);
+                }
+                for value in def.enum_values.iter_mut() {
+                    if self.excluded(&value.directives) {
+                        self.hide(&mut value.directives);
+                    }
+                }
+                return;
+            }
+            Definition::Scalar(def) => {
+                if self.excluded(&def.directives) {
+                    self.hide(&mut def.directives);
+                }
+                return;
+            }
+        };
+
+        let type_included = self.included(directives);
+        let mut any_included = false;
+        for field in fields.iter_mut() {
+            for arg in field.args.iter_mut() {
+                if self.excluded(&arg.directives) {
+                    self.hide(&mut arg.directives);
+                }
+            }
+
+            let included = self.included(&field.directives);
+            any_included |= included;
+            if self.excluded(&field.directives)
+                || (self.filters_included() && !included && !type_included)
+            {
+                self.hide(&mut field.directives);
+            }
+        }
+
+        if self.excluded(directives) || (self.filters_included() && !type_included && !any_included)
+        {
+            self.hide(directives);
+        }
+    }
+
+    // Hides what only refers to hidden elements: fields of hidden types,
+    // optional arguments of hidden types and types with nothing left. Roots
+    // other than the query go away with their type. Returns whether anything
+    // changed.
+    fn cascade(&self, blueprint: &mut Blueprint) -> bool {
+        let hidden = self.hidden_types(blueprint);
+        let mut changed = false;
+
+        for definition in blueprint
+            .definitions
+            .iter_mut()
+            .filter(|definition| !hidden.contains(definition.name()))
+        {
+            match Arc::make_mut(definition) {
+                Definition::Object(def) => {
+                    changed |= self.cascade_fields(&mut def.fields, &hidden);
+                    if self.none_left(def.fields.iter().map(|field| &field.directives)) {
+                        changed |= self.hide(&mut def.directives);
+                    }
+                }
+                Definition::Interface(def) => {
+                    changed |= self.cascade_fields(&mut def.fields, &hidden);
+                    if self.none_left(def.fields.iter().map(|field| &field.directives)) {
+                        changed |= self.hide(&mut def.directives);
+                    }
+                }
+                Definition::InputObject(def) => {
+                    changed |= self.cascade_inputs(&mut def.fields, &hidden);
+                    if self.none_left(def.fields.iter().map(|field| &field.directives)) {
+                        changed |= self.hide(&mut def.directives);
+                    }
+                }
+                Definition::Enum(def) => {
+                    if self.none_left(def.enum_values.iter().map(|value| &value.directives)) {
+                        changed |= self.hide(&mut def.directives);
+                    }
+                }
+                Definition::Union(def) => {
+                    if !def.types.is_empty()
+                        && def
+                            .types
+                            .iter()
+                            .all(|member| hidden.contains(member.as_str()))
+                    {
+                        changed |= self.hide(&mut def.directives);
+                    }
+                }
+                Definition::Scalar(_) => {}
+            }
+        }
+
+        let hidden = self.hidden_types(blueprint);
+        let schema = &mut blueprint.schema;
+        for root in [&mut schema.mutation, &mut schema.subscription] {
+            if root.as_ref().is_some_and(|root| hidden.contains(root)) {
+                *root = None;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    fn cascade_fields(&self, fields: &mut [FieldDefinition], hidden: &BTreeSet<String>) -> bool {
+        let mut changed = false;
+        for field in fields
+            .iter_mut()
+            .filter(|field| !self.is_hidden(&field.directives))
+        {
+            if hidden.contains(&field.of_type.as_type_str()) {
+                changed |= self.hide(&mut field.directives);
+            } else {
+                changed |= self.cascade_inputs(&mut field.args, hidden);
+            }
+        }
+        changed
+    }
+
+    // Required inputs of hidden types are left for the API schema to report.
+    fn cascade_inputs(
+        &self,
+        inputs: &mut [InputFieldDefinition],
+        hidden: &BTreeSet<String>,
+    ) -> bool {
+        let mut changed = false;
+        for input in inputs.iter_mut() {
+            let required = match &input.of_type {
+                Type::Named { required, .. } => *required,
+                Type::List { non_null, .. } => *non_null,
+            };
+            if hidden.contains(&input.of_type.as_type_str())
+                && (!required || input.default_value.is_some())
+            {
+                changed |= self.hide(&mut input.directives);
+            }
+        }
+        changed
+    }
+
+    fn none_left<'b>(&self, mut elements: impl Iterator<Item = &'b Vec<Directive>>) -> bool {
+        let mut any = false;
+        let none_left = elements.all(|directives| {
+            any = true;
+            self.is_hidden(directives)
+        });
+        any && none_left
+    }
+
+    fn hidden_types(&self, blueprint: &Blueprint) -> BTreeSet<String> {
+        blueprint
+            .definitions
+            .iter()
+            .filter(|definition| self.is_hidden(definition.directives()))
+            .map(|definition| definition.name().to_string())
+            .collect()
+    }
+
+    // Hides the types that can't be reached from the roots, or from the
+    // arguments of the directives that are not machinery, anymore.
+    fn prune(&self, blueprint: &mut Blueprint) {
+        let hidden = self.hidden_types(blueprint);
+        let schema = &blueprint.schema;
+        let mut queue = [&schema.query, &schema.mutation, &schema.subscription]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .chain(
+                blueprint
+                    .directives
+                    .iter()
+                    .filter(|directive| !blueprint.links.owns(&directive.name))
+                    .flat_map(|directive| &directive.arguments)
+                    .map(|arg| arg.of_type.as_type_str()),
+            )
+            .collect::<VecDeque<_>>();
+        let mut reachable = BTreeSet::new();
+
+        while let Some(name) = queue.pop_front() {
+            if hidden.contains(&name) || !reachable.insert(name.clone()) {
+                continue;
+            }
+            let Some(definition) = blueprint
+                .definitions
+                .iter()
+                .find(|definition| definition.name() == name)
+            else {
+                continue;
+            };
+
+            match definition.as_ref() {
+                Definition::Object(def) => {
+                    queue.extend(def.implements.iter().cloned());
+                    queue.extend(self.field_types(&def.fields));
+                }
+                Definition::Interface(def) => {
+                    queue.extend(def.implements.iter().cloned());
+                    queue.extend(self.field_types(&def.fields));
+                    queue.extend(
+                        blueprint
+                            .definitions
+                            .iter()
+                            .filter(|definition| match definition.as_ref() {
+                                Definition::Object(def) => def.implements.contains(&name),
+                                Definition::Interface(def) => def.implements.contains(&name),
+                                _ => false,
+                            })
+                            .map(|definition| definition.name().to_string()),
+                    );
+                }
+                Definition::InputObject(def) => queue.extend(self.input_types(&def.fields)),
+                Definition::Union(def) => queue.extend(def.types.iter().cloned()),
+                Definition::Enum(_) | Definition::Scalar(_) => {}
+            }
+        }
+
+        let links = &blueprint.links;
+        for definition in blueprint.definitions.iter_mut().filter(|definition| {
+            !reachable.contains(definition.name()) && !links.owns(definition.name())
+        }) {
+            self.hide(Arc::make_mut(definition).directives_mut());
+        }
+    }
+
+    fn field_types(&self, fields: &[FieldDefinition]) -> Vec<String> {
+        fields
+            .iter()
+            .filter(|field| !self.is_hidden(&field.directives))
+            .flat_map(|field| {
+                std::iter::once(field.of_type.as_type_str()).chain(self.input_types(&field.args))
+            })
+            .collect()
+    }
+
+    fn input_types(&self, inputs: &[InputFieldDefinition]) -> Vec<String> {
+        inputs
+            .iter()
+            .filter(|input| !self.is_hidden(&input.directives))
+            .map(|input| input.of_type.as_type_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUPERGRAPH: &str = r#"
+schema
+  @link(url: "https://specs.apollo.dev/link/v1.0")
+  @link(url: "https://specs.apollo.dev/join/v0.3", for: EXECUTION)
+  @link(url: "https://specs.apollo.dev/tag/v0.3") {
+  query: Query
+  mutation: Mutation
+}
+
+directive @tag(name: String!) repeatable on FIELD_DEFINITION | OBJECT | INTERFACE | UNION | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE | INPUT_OBJECT | INPUT_FIELD_DEFINITION
+
+enum join__Graph {
+  A @join__graph(name: "a", url: "http://localhost:4001")
+}
+
+type Query @tag(name: "public") {
+  product(upc: String!, region: Region): Product
+  audit: Audit @tag(name: "internal")
+}
+
+type Mutation {
+  refund(id: ID!): Boolean @tag(name: "internal")
+}
+
+type Product @tag(name: "public") {
+  upc: String!
+  cost: Int @tag(name: "internal")
+  supplier: Supplier
+  kind: Kind
+}
+
+type Supplier {
+  name: String
+}
+
+type Audit {
+  entries: [String]
+}
+
+enum Kind {
+  BOOK
+  TOY @tag(name: "internal")
+}
+
+input Region @tag(name: "internal") {
+  code: String
+}
+"#;
+
+    #[test]
+    fn test_contract() {
+        let blueprint = Blueprint::parse(SUPERGRAPH).to_result().unwrap();
+        let contract = Contract::new(["public"], ["internal"]);
+        let supergraph = blueprint.contract(&contract).to_result().unwrap();
+        let api = supergraph.to_api_schema().to_result().unwrap();
+
+        // The contract is a supergraph of its own, linking `@inaccessible`.
+        let reparsed = Blueprint::parse(&supergraph.to_string())
+            .to_result()
+            .unwrap();
+        assert!(reparsed.links.get("inaccessible").is_some());
+        insta::assert_snapshot!(api.to_string());
+    }
+
+    #[test]
+    fn test_contract_errors() {
+        let graphql = SUPERGRAPH.replace(
+            "product(upc: String!,",
+            r#"product(upc: String! @tag(name: "internal"),"#,
+        );
+        let blueprint = Blueprint::parse_source("router.graphql", &graphql)
+            .to_result()
+            .unwrap();
+        let contract = Contract::new(["public"], ["internal"]);
+        let errors = blueprint.contract(&contract).to_result().unwrap_err();
+
+        insta::assert_snapshot!(errors);
+    }
+}
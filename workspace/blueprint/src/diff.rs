@@ -0,0 +1,617 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    Blueprint, Definition, EnumValueDefinition, FieldDefinition, Graph, InputFieldDefinition,
+    JoinTypeParsed, Type,
+};
+
+/// How a change affects the clients of a schema.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Operations that were valid may fail.
+    Breaking,
+    /// Operations stay valid but may behave differently, like a client that
+    /// doesn't expect a new enum value.
+    Dangerous,
+    Safe,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ChangeKind {
+    TypeAdded,
+    TypeRemoved,
+    TypeKindChanged {
+        from: String,
+        to: String,
+    },
+    FieldAdded,
+    FieldRemoved,
+    FieldTypeChanged {
+        from: String,
+        to: String,
+    },
+    /// An argument or an input field was added.
+    InputValueAdded {
+        of_type: String,
+    },
+    InputValueRemoved,
+    InputValueTypeChanged {
+        from: String,
+        to: String,
+    },
+    DefaultValueChanged {
+        from: Option<Value>,
+        to: Option<Value>,
+    },
+    EnumValueAdded,
+    EnumValueRemoved,
+    MemberAdded {
+        member: String,
+    },
+    MemberRemoved {
+        member: String,
+    },
+    InterfaceAdded {
+        interface: String,
+    },
+    InterfaceRemoved {
+        interface: String,
+    },
+    /// The subgraphs resolving a type or a field changed.
+    OwnershipChanged {
+        from: Vec<Graph>,
+        to: Vec<Graph>,
+    },
+    RootChanged {
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
+
+/// A change to one element of the schema, `Query.product(upc:)` for an
+/// argument.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Change {
+    pub path: String,
+    pub severity: Severity,
+    #[serde(flatten)]
+    pub kind: ChangeKind,
+}
+
+/// The changes from a Blueprint to another, leaving out the machinery of the
+/// linked specs.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|change| change.severity == Severity::Breaking)
+    }
+
+    pub fn severity(&self, severity: Severity) -> impl Iterator<Item = &Change> {
+        self.changes
+            .iter()
+            .filter(move |change| change.severity == severity)
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Breaking => write!(f, "breaking"),
+            Severity::Dangerous => write!(f, "dangerous"),
+            Severity::Safe => write!(f, "safe"),
+        }
+    }
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let value = |value: &Option<Value>| match value {
+            Some(value) => format!("`{}`", value),
+            None => "none".to_string(),
+        };
+        let graphs = |graphs: &[Graph]| match graphs {
+            [] => "none".to_string(),
+            graphs => graphs
+                .iter()
+                .map(Graph::as_str)
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+
+        match self {
+            ChangeKind::TypeAdded => write!(f, "type was added"),
+            ChangeKind::TypeRemoved => write!(f, "type was removed"),
+            ChangeKind::TypeKindChanged { from, to } => {
+                write!(f, "type changed from {} to {}", from, to)
+            }
+            ChangeKind::FieldAdded => write!(f, "field was added"),
+            ChangeKind::FieldRemoved => write!(f, "field was removed"),
+            ChangeKind::FieldTypeChanged { from, to }
+            | ChangeKind::InputValueTypeChanged { from, to } => {
+                write!(f, "type changed from `{}` to `{}`", from, to)
+            }
+            ChangeKind::InputValueAdded { of_type } => {
+                write!(f, "input of type `{}` was added", of_type)
+            }
+            ChangeKind::InputValueRemoved => write!(f, "input was removed"),
+            ChangeKind::DefaultValueChanged { from, to } => write!(
+                f,
+                "default value changed from {} to {}",
+                value(from),
+                value(to)
+            ),
+            ChangeKind::EnumValueAdded => write!(f, "enum value was added"),
+            ChangeKind::EnumValueRemoved => write!(f, "enum value was removed"),
+            ChangeKind::MemberAdded { member } => write!(f, "member `{}` was added", member),
+            ChangeKind::MemberRemoved { member } => write!(f, "member `{}` was removed", member),
+            ChangeKind::InterfaceAdded { interface } => {
+                write!(f, "implements `{}` now", interface)
+            }
+            ChangeKind::InterfaceRemoved { interface } => {
+                write!(f, "no longer implements `{}`", interface)
+            }
+            ChangeKind::OwnershipChanged { from, to } => {
+                write!(f, "resolved by {} instead of {}", graphs(to), graphs(from))
+            }
+            ChangeKind::RootChanged { from, to } => write!(
+                f,
+                "root changed from {} to {}",
+                from.as_deref().unwrap_or("none"),
+                to.as_deref().unwrap_or("none")
+            ),
+        }
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.severity, self.path, self.kind)
+    }
+}
+
+impl Display for Diff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists what changed from `old` to `new`, in the order of the schema.
+pub fn diff(old: &Blueprint, new: &Blueprint) -> Diff {
+    let mut differ = Differ { changes: Vec::new() };
+
+    for (root, from, to) in [
+        ("query", &old.schema.query, &new.schema.query),
+        ("mutation", &old.schema.mutation, &new.schema.mutation),
+        (
+            "subscription",
+            &old.schema.subscription,
+            &new.schema.subscription,
+        ),
+    ] {
+        if from != to {
+            let severity = if from.is_none() {
+                Severity::Safe
+            } else {
+                Severity::Breaking
+            };
+            differ.push(
+                format!("schema.{}", root),
+                severity,
+                ChangeKind::RootChanged { from: from.clone(), to: to.clone() },
+            );
+        }
+    }
+
+    let old_definitions = definitions(old);
+    let new_definitions = definitions(new);
+
+    let names = old_definitions
+        .keys()
+        .chain(new_definitions.keys())
+        .copied()
+        .collect::<BTreeSet<_>>();
+    for name in names {
+        match (old_definitions.get(name), new_definitions.get(name)) {
+            (Some(_), None) => differ.push(name, Severity::Breaking, ChangeKind::TypeRemoved),
+            (None, Some(_)) => differ.push(name, Severity::Safe, ChangeKind::TypeAdded),
+            (Some(old), Some(new)) => differ.definition(name, old, new),
+            (None, None) => {}
+        }
+    }
+
+    Diff { changes: differ.changes }
+}
+
+struct Differ {
+    changes: Vec<Change>,
+}
+
+impl Differ {
+    fn push(&mut self, path: impl Into<String>, severity: Severity, kind: ChangeKind) {
+        self.changes
+            .push(Change { path: path.into(), severity, kind });
+    }
+
+    fn definition(&mut self, name: &str, old: &Definition, new: &Definition) {
+        self.ownership(name, graphs(old.join_types()), graphs(new.join_types()));
+
+        match (old, new) {
+            (Definition::Object(old), Definition::Object(new)) => {
+                self.sets(
+                    name,
+                    &old.implements,
+                    &new.implements,
+                    |interface| ChangeKind::InterfaceAdded { interface },
+                    |interface| ChangeKind::InterfaceRemoved { interface },
+                );
+                self.fields(
+                    name,
+                    (&old.fields, &old.join_types),
+                    (&new.fields, &new.join_types),
+                );
+            }
+            (Definition::Interface(old), Definition::Interface(new)) => {
+                self.sets(
+                    name,
+                    &old.implements,
+                    &new.implements,
+                    |interface| ChangeKind::InterfaceAdded { interface },
+                    |interface| ChangeKind::InterfaceRemoved { interface },
+                );
+                self.fields(
+                    name,
+                    (&old.fields, &old.join_types),
+                    (&new.fields, &new.join_types),
+                );
+            }
+            (Definition::InputObject(old), Definition::InputObject(new)) => {
+                self.inputs(name, &old.fields, &new.fields);
+            }
+            (Definition::Enum(old), Definition::Enum(new)) => {
+                self.enum_values(name, &old.enum_values, &new.enum_values);
+            }
+            (Definition::Union(old), Definition::Union(new)) => {
+                self.sets(
+                    name,
+                    &old.types,
+                    &new.types,
+                    |member| ChangeKind::MemberAdded { member },
+                    |member| ChangeKind::MemberRemoved { member },
+                );
+            }
+            (Definition::Scalar(_), Definition::Scalar(_)) => {}
+            (old, new) => self.push(
+                name,
+                Severity::Breaking,
+                ChangeKind::TypeKindChanged {
+                    from: kind(old).to_string(),
+                    to: kind(new).to_string(),
+                },
+            ),
+        }
+    }
+
+    // Additions are dangerous: clients switching over the members or
+    // interfaces may not expect the new one.
+    fn sets(
+        &mut self,
+        name: &str,
+        old: &BTreeSet<String>,
+        new: &BTreeSet<String>,
+        added: impl Fn(String) -> ChangeKind,
+        removed: impl Fn(String) -> ChangeKind,
+    ) {
+        for member in old.difference(new) {
+            self.push(name, Severity::Breaking, removed(member.clone()));
+        }
+        for member in new.difference(old) {
+            self.push(name, Severity::Dangerous, added(member.clone()));
+        }
+    }
+
+    fn fields(
+        &mut self,
+        name: &str,
+        (old, old_types): (&[FieldDefinition], &[JoinTypeParsed]),
+        (new, new_types): (&[FieldDefinition], &[JoinTypeParsed]),
+    ) {
+        for old_field in old {
+            let path = format!("{}.{}", name, old_field.name);
+            let Some(new_field) = new.iter().find(|field| field.name == old_field.name) else {
+                self.push(path, Severity::Breaking, ChangeKind::FieldRemoved);
+                continue;
+            };
+
+            if old_field.of_type != new_field.of_type {
+                let severity = if narrows(&old_field.of_type, &new_field.of_type) {
+                    Severity::Safe
+                } else {
+                    Severity::Breaking
+                };
+                self.push(
+                    &path,
+                    severity,
+                    ChangeKind::FieldTypeChanged {
+                        from: old_field.of_type.to_string(),
+                        to: new_field.of_type.to_string(),
+                    },
+                );
+            }
+
+            // Fields without join data follow their type, whose changes are
+            // reported already.
+            if !old_field.join_fields.is_empty() || !new_field.join_fields.is_empty() {
+                self.ownership(
+                    &path,
+                    field_graphs(old_field, old_types),
+                    field_graphs(new_field, new_types),
+                );
+            }
+
+            self.arguments(&path, &old_field.args, &new_field.args);
+        }
+
+        for new_field in new
+            .iter()
+            .filter(|new_field| !old.iter().any(|field| field.name == new_field.name))
+        {
+            self.push(
+                format!("{}.{}", name, new_field.name),
+                Severity::Safe,
+                ChangeKind::FieldAdded,
+            );
+        }
+    }
+
+    fn arguments(
+        &mut self,
+        path: &str,
+        old: &[InputFieldDefinition],
+        new: &[InputFieldDefinition],
+    ) {
+        self.input_values(old, new, |name| format!("{}({}:)", path, name));
+    }
+
+    fn inputs(&mut self, name: &str, old: &[InputFieldDefinition], new: &[InputFieldDefinition]) {
+        self.input_values(old, new, |field| format!("{}.{}", name, field));
+    }
+
+    fn input_values(
+        &mut self,
+        old: &[InputFieldDefinition],
+        new: &[InputFieldDefinition],
+        path: impl Fn(&str) -> String,
+    ) {
+        for old_input in old {
+            let Some(new_input) = new.iter().find(|input| input.name == old_input.name) else {
+                self.push(
+                    path(&old_input.name),
+                    Severity::Breaking,
+                    ChangeKind::InputValueRemoved,
+                );
+                continue;
+            };
+
+            if old_input.of_type != new_input.of_type {
+                let severity = if narrows(&new_input.of_type, &old_input.of_type) {
+                    Severity::Safe
+                } else {
+                    Severity::Breaking
+                };
+                self.push(
+                    path(&old_input.name),
+                    severity,
+                    ChangeKind::InputValueTypeChanged {
+                        from: old_input.of_type.to_string(),
+                        to: new_input.of_type.to_string(),
+                    },
+                );
+            }
+
+            if old_input.default_value != new_input.default_value {
+                self.push(
+                    path(&old_input.name),
+                    Severity::Dangerous,
+                    ChangeKind::DefaultValueChanged {
+                        from: old_input.default_value.clone(),
+                        to: new_input.default_value.clone(),
+                    },
+                );
+            }
+        }
+
+        for new_input in new
+            .iter()
+            .filter(|new_input| !old.iter().any(|input| input.name == new_input.name))
+        {
+            // Operations that don't pass a new required input fail.
+            let severity = if is_required(&new_input.of_type) && new_input.default_value.is_none() {
+                Severity::Breaking
+            } else {
+                Severity::Dangerous
+            };
+            self.push(
+                path(&new_input.name),
+                severity,
+                ChangeKind::InputValueAdded { of_type: new_input.of_type.to_string() },
+            );
+        }
+    }
+
+    fn enum_values(
+        &mut self,
+        name: &str,
+        old: &[EnumValueDefinition],
+        new: &[EnumValueDefinition],
+    ) {
+        for value in old
+            .iter()
+            .filter(|value| !new.iter().any(|new| new.name == value.name))
+        {
+            self.push(
+                format!("{}.{}", name, value.name),
+                Severity::Breaking,
+                ChangeKind::EnumValueRemoved,
+            );
+        }
+        for value in new
+            .iter()
+            .filter(|value| !old.iter().any(|old| old.name == value.name))
+        {
+            self.push(
+                format!("{}.{}", name, value.name),
+                Severity::Dangerous,
+                ChangeKind::EnumValueAdded,
+            );
+        }
+    }
+
+    // Clients don't see which subgraphs resolve what, but query plans do: a
+    // subgraph no longer resolving an element may not serve it the same way.
+    fn ownership(&mut self, path: &str, old: BTreeSet<Graph>, new: BTreeSet<Graph>) {
+        if old == new {
+            return;
+        }
+
+        let severity = if new.is_superset(&old) {
+            Severity::Safe
+        } else {
+            Severity::Dangerous
+        };
+        self.push(
+            path,
+            severity,
+            ChangeKind::OwnershipChanged {
+                from: old.into_iter().collect(),
+                to: new.into_iter().collect(),
+            },
+        );
+    }
+}
+
+// Whether every value of `new` is a value of `old`: a field can become
+// non-null, an input can become nullable.
+fn narrows(old: &Type, new: &Type) -> bool {
+    match (old, new) {
+        (
+            Type::Named { name: old, required: old_required },
+            Type::Named { name: new, required },
+        ) => old == new && (*required || !old_required),
+        (
+            Type::List { of_type: old, non_null: old_non_null },
+            Type::List { of_type: new, non_null },
+        ) => (*non_null || !old_non_null) && narrows(old, new),
+        _ => false,
+    }
+}
+
+fn is_required(of_type: &Type) -> bool {
+    match of_type {
+        Type::Named { required, .. } => *required,
+        Type::List { non_null, .. } => *non_null,
+    }
+}
+
+fn definitions(blueprint: &Blueprint) -> BTreeMap<&str, &Definition> {
+    blueprint
+        .definitions
+        .iter()
+        .filter(|definition| !blueprint.links.owns(definition.name()))
+        .map(|definition| (definition.name(), definition.as_ref()))
+        .collect()
+}
+
+fn kind(definition: &Definition) -> &'static str {
+    match definition {
+        Definition::Interface(_) => "interface",
+        Definition::Object(_) => "object",
+        Definition::InputObject(_) => "input object",
+        Definition::Scalar(_) => "scalar",
+        Definition::Enum(_) => "enum",
+        Definition::Union(_) => "union",
+    }
+}
+
+fn graphs(join_types: &[JoinTypeParsed]) -> BTreeSet<Graph> {
+    join_types
+        .iter()
+        .map(|join_type| join_type.graph.clone())
+        .collect()
+}
+
+fn field_graphs(field: &FieldDefinition, join_types: &[JoinTypeParsed]) -> BTreeSet<Graph> {
+    if field.join_fields.is_empty() {
+        graphs(join_types)
+    } else {
+        field
+            .join_fields
+            .iter()
+            .filter(|join_field| join_field.external != Some(true))
+            .filter_map(|join_field| join_field.graph.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use valid::Validator;
+
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let graphql = include_str!("fixtures/router.graphql");
+        let changed = graphql
+            .replace("  width: Int!\n", "  width: Int\n")
+            .replace("  height: Int!\n", "  height: Int!\n  depth: Int\n")
+            .replace("  NOT_TRUSTED @join__enumValue(graph: REVIEWS)\n", "")
+            .replace(
+                "  body: String!\n",
+                "  body(format: String!, locale: String): String\n",
+            )
+            .replace(
+                "  name: String! @join__field(graph: PRODUCTS)",
+                "  name: String! @join__field(graph: REVIEWS)",
+            );
+
+        let old = Blueprint::parse(graphql).to_result().unwrap();
+        let new = Blueprint::parse(&changed).to_result().unwrap();
+        let diff = old.diff(&new);
+
+        assert!(diff.is_breaking());
+        assert!(old.diff(&old).is_empty());
+        insta::assert_snapshot!(diff.to_string());
+    }
+
+    #[test]
+    fn test_diff_json() {
+        let old = Blueprint::parse(include_str!("fixtures/router.graphql"))
+            .to_result()
+            .unwrap();
+        let mut new = old.clone();
+        new.definitions
+            .retain(|definition| definition.name() != "Trustworthiness");
+
+        let diff = serde_json::to_string_pretty(&old.diff(&new)).unwrap();
+        insta::assert_snapshot!(diff);
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::BTreeSet;
+
+use valid::{Valid, Validator};
+
+use crate::compose::BUILTIN_SCALARS;
+use crate::{Definition, Index, QueryField, Selection, SelectionSet, Type};
+
+impl Index {
+    /// Checks the fields of a `@requires` or `@provides` against
+    /// `type_name`, the type they select from.
+    pub fn validate_field_set(
+        &self,
+        type_name: &str,
+        field_set: &SelectionSet,
+    ) -> Valid<(), String> {
+        self.field_set(type_name, field_set, false)
+    }
+
+    /// Checks the fields of a `@key` of `type_name`, which can't select
+    /// through lists of objects.
+    pub fn validate_key(&self, type_name: &str, field_set: &SelectionSet) -> Valid<(), String> {
+        self.field_set(type_name, field_set, true)
+    }
+
+    /// Checks every field set of the supergraph, the keys of its types and
+    /// the `@requires` and `@provides` of their fields.
+    pub fn validate_field_sets(&self) -> Valid<(), String> {
+        Valid::from_iter(self.definitions(), |definition| {
+            let keys =
+                Valid::from_iter(definition.join_types(), |join_type| match &join_type.key {
+                    Some(key) => self.validate_key(definition.name(), key).trace("key"),
+                    None => Valid::succeed(()),
+                })
+                .trace("@join__type");
+
+            let fields = match definition {
+                Definition::Object(def) => def.fields.as_slice(),
+                Definition::Interface(def) => def.fields.as_slice(),
+                _ => &[],
+            };
+            let fields = Valid::from_iter(fields, |field| {
+                Valid::from_iter(&field.join_fields, |join_field| {
+                    let requires = match &join_field.requires {
+                        Some(requires) => self
+                            .validate_field_set(definition.name(), requires)
+                            .trace("requires"),
+                        None => Valid::succeed(()),
+                    };
+                    let provides = match &join_field.provides {
+                        Some(provides) => self
+                            .validate_field_set(field.of_type.name(), provides)
+                            .trace("provides"),
+                        None => Valid::succeed(()),
+                    };
+                    requires.and(provides)
+                })
+                .trace("@join__field")
+                .trace(&field.name)
+                .map_err(&|message| self.at(definition.name(), Some(&field.name), message))
+            });
+
+            keys.map_err(&|message| self.at(definition.name(), None, message))
+                .and(fields)
+                .trace(definition.name())
+                .unit()
+        })
+        .unit()
+    }
+
+    fn field_set(&self, type_name: &str, field_set: &SelectionSet, key: bool) -> Valid<(), String> {
+        Valid::from_iter(&field_set.0, |selection| match selection {
+            Selection::Field(field) if field.name == "__typename" => {
+                if field.selections.0.is_empty() {
+                    Valid::succeed(())
+                } else {
+                    Valid::fail(format!(
+                        "Field `{}.__typename` of type `String` has no fields to select",
+                        type_name
+                    ))
+                }
+            }
+            Selection::Field(field) => {
+                let Some(QueryField::Field(def)) = self.get_field(type_name, &field.name) else {
+                    return Valid::fail(format!(
+                        "Field `{}` is not defined on `{}`",
+                        field.name, type_name
+                    ));
+                };
+                let of_type = &def.definition.of_type;
+                let is_leaf = BUILTIN_SCALARS.contains(&of_type.name())
+                    || self.type_is_scalar(of_type.name())
+                    || self.type_is_enum(of_type.name());
+
+                let arguments =
+                    Valid::from_iter(field.arguments.keys(), |name| match def.get_arg(name) {
+                        Some(_) => Valid::succeed(()),
+                        None => Valid::fail(format!(
+                            "Argument `{}` is not defined on `{}.{}`",
+                            name, type_name, field.name
+                        )),
+                    })
+                    .and(Valid::from_iter(&def.definition.args, |arg| {
+                        let missing = arg.of_type.is_required()
+                            && arg.default_value.is_none()
+                            && !field.arguments.contains_key(arg.name.as_str());
+                        if missing {
+                            Valid::fail(format!(
+                                "Argument `{}` of `{}.{}` is required",
+                                arg.name, type_name, field.name
+                            ))
+                        } else {
+                            Valid::succeed(())
+                        }
+                    }));
+
+                let selections = match (is_leaf, field.selections.0.is_empty()) {
+                    (true, true) => Valid::succeed(()),
+                    (true, false) => Valid::fail(format!(
+                        "Field `{}.{}` of type `{}` has no fields to select",
+                        type_name,
+                        field.name,
+                        of_type.name()
+                    )),
+                    (false, true) => Valid::fail(format!(
+                        "Field `{}.{}` of type `{}` must select its fields",
+                        type_name,
+                        field.name,
+                        of_type.name()
+                    )),
+                    (false, false) if key && is_list(of_type) => Valid::fail(format!(
+                        "Field `{}.{}` is a list of objects, which a key can't select",
+                        type_name, field.name
+                    )),
+                    (false, false) => self.field_set(of_type.name(), &field.selections, key),
+                };
+
+                arguments.and(selections).trace(&field.name)
+            }
+            Selection::InlineFragment(fragment) => {
+                let type_condition = fragment.type_condition.as_deref().unwrap_or(type_name);
+                if self.get_type(type_condition).is_none() {
+                    return Valid::fail(format!("Type `{}` is not defined", type_condition));
+                }
+                if self
+                    .runtime_types(type_name)
+                    .is_disjoint(&self.runtime_types(type_condition))
+                {
+                    return Valid::fail(format!(
+                        "Fragment on `{}` can never apply to `{}`",
+                        type_condition, type_name
+                    ));
+                }
+
+                self.field_set(type_condition, &fragment.selections, key)
+                    .trace(&format!("... on {}", type_condition))
+            }
+        })
+        .unit()
+    }
+
+    // The object types a value of `type_name` can be.
+    fn runtime_types<'a>(&'a self, type_name: &'a str) -> BTreeSet<&'a str> {
+        match self.get_object_type_definition(type_name) {
+            Some(_) => BTreeSet::from([type_name]),
+            None => self
+                .possible_types(type_name)
+                .iter()
+                .map(String::as_str)
+                .collect(),
+        }
+    }
+
+    fn at(&self, type_name: &str, field_name: Option<&str>, message: String) -> String {
+        match self.location(type_name, field_name) {
+            Some(location) => format!("{}: {}", location, message),
+            None => message,
+        }
+    }
+}
+
+fn is_list(of_type: &Type) -> bool {
+    matches!(of_type, Type::List { .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use valid::Validator;
+
+    use crate::{Blueprint, Definition};
+
+    const PRELUDE: &str = r#"
+schema
+  @link(url: "https://specs.apollo.dev/link/v1.0")
+  @link(url: "https://specs.apollo.dev/join/v0.3", for: EXECUTION) {
+  query: Query
+}
+
+directive @join__type(graph: join__Graph!, key: join__FieldSet, resolvable: Boolean = true) repeatable on OBJECT | INTERFACE
+directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, external: Boolean) repeatable on FIELD_DEFINITION
+
+scalar join__FieldSet
+
+enum join__Graph {
+  A @join__graph(name: "a", url: "http://localhost:4001")
+  B @join__graph(name: "b", url: "http://localhost:4002")
+}
+
+enum Unit @join__type(graph: A) @join__type(graph: B) {
+  CM
+  IN
+}
+
+interface Media @join__type(graph: A) @join__type(graph: B) {
+  id: ID!
+}
+
+type Book implements Media @join__type(graph: A) @join__type(graph: B) {
+  id: ID!
+  pages: Int
+}
+
+type Movie implements Media @join__type(graph: A) @join__type(graph: B) {
+  id: ID!
+  size(unit: Unit!): Int
+}
+
+type Shelf @join__type(graph: A, key: "id") @join__type(graph: B, key: "id") {
+  id: ID!
+  media: [Media] @join__field(graph: A)
+"#;
+
+    fn parse(fields: &str) -> Blueprint {
+        let graphql = format!(
+            "{}{}\n}}\n\ntype Query @join__type(graph: A) {{\n  shelf: Shelf\n}}\n",
+            PRELUDE, fields
+        );
+        Blueprint::parse_source("supergraph.graphql", &graphql)
+            .to_result()
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    #[test]
+    fn test_field_set() {
+        let blueprint = parse(
+            r#"
+  height(unit: Unit): Int @join__field(graph: A)
+  items: [Media]
+    @join__field(graph: B, provides: "... on Book { pages } ... on Movie { small: size(unit: CM) }")
+  label: String
+    @join__field(graph: B, requires: "media { id ... on Book { pages } } tall: height(unit: IN)")
+"#,
+        );
+        let Some(Definition::Object(shelf)) = blueprint
+            .definitions
+            .iter()
+            .find(|definition| definition.name() == "Shelf")
+            .map(AsRef::as_ref)
+        else {
+            panic!("expected `Shelf`")
+        };
+        let field_set = |name: &str| {
+            let field = shelf
+                .fields
+                .iter()
+                .find(|field| field.name == name)
+                .unwrap();
+            let join_field = &field.join_fields[0];
+            join_field
+                .requires
+                .as_ref()
+                .or(join_field.provides.as_ref())
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(
+            field_set("items"),
+            "... on Book { pages } ... on Movie { small: size(unit: CM) }"
+        );
+        assert_eq!(
+            field_set("label"),
+            "media { id ... on Book { pages } } tall: height(unit: IN)"
+        );
+    }
+
+    #[test]
+    fn test_field_set_errors() {
+        let graphql = format!(
+            "{}{}\n}}\n\ntype Query @join__type(graph: A) {{\n  shelf: Shelf\n}}\n",
+            PRELUDE.replace(
+                r#"key: "id") @join__type(graph: B, key: "id")"#,
+                r#"key: "id media { id }")"#
+            ),
+            r#"
+  height(unit: Unit): Int @join__field(graph: A)
+  a: String @join__field(graph: B, requires: "title")
+  b: String @join__field(graph: B, requires: "media")
+  c: String @join__field(graph: B, requires: "id { value }")
+  d: String @join__field(graph: B, requires: "height(scale: 2)")
+  e: [Media] @join__field(graph: B, provides: "... on Shelf { id }")
+  f: [Media] @join__field(graph: B, provides: "... on Movie { size }")
+  g: [Media] @join__field(graph: B, provides: "... on Album { id }")
+"#
+        );
+        let errors = Blueprint::parse_source("supergraph.graphql", &graphql)
+            .to_result()
+            .unwrap_err();
+
+        insta::assert_snapshot!(errors);
+    }
+}
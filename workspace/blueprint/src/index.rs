@@ -1,30 +1,166 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
 use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
 
+use crate::builtins::BUILTINS;
+use crate::name::Interner;
+use crate::view::{self, GraphView};
 use crate::{
-    Blueprint, Definition, FieldDefinition, InputFieldDefinition, InputObjectTypeDefinition,
-    ObjectTypeDefinition, SchemaDefinition,
+    Blueprint, Definition, DirectiveDefinition, FieldDefinition, Graph, InputFieldDefinition,
+    InputObjectTypeDefinition, InterfaceTypeDefinition, JoinFieldParsed, Name,
+    ObjectTypeDefinition, OverrideLabel, Pos, SchemaDefinition,
 };
 
+static EMPTY: BTreeSet<String> = BTreeSet::new();
+
 ///
 /// A read optimized index of all the types in the Blueprint. Provide O(1)
 /// access to getting any field information.
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Index {
-    map: IndexMap<String, (Definition, IndexMap<String, QueryField>)>,
+    map: IndexMap<Name, Entry>,
+    /// The directives of the schema, with the built-in ones it doesn't define.
+    directives: IndexMap<Name, DirectiveDefinition>,
+    /// The labels of the progressive `@override`s, each once.
+    override_labels: Vec<OverrideLabel>,
     schema: SchemaDefinition,
+    source: Option<String>,
+    abstracts: Abstracts,
+    graph_abstracts: BTreeMap<Graph, Abstracts>,
+    views: BTreeMap<Graph, GraphView>,
+}
+
+// The relations between abstract types and the types they can be, resolved
+// through interfaces implementing interfaces.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Abstracts {
+    // The interfaces of an object or interface type.
+    interfaces: BTreeMap<String, BTreeSet<String>>,
+    // The object and interface types implementing an interface.
+    implementations: BTreeMap<String, BTreeSet<String>>,
+    // The object types an abstract type can be at runtime.
+    possible_types: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Abstracts {
+    fn new(
+        implements: BTreeMap<&str, BTreeSet<&str>>,
+        unions: BTreeMap<&str, BTreeSet<&str>>,
+        objects: BTreeSet<&str>,
+    ) -> Self {
+        let mut abstracts = Abstracts::default();
+
+        for name in implements.keys() {
+            let mut interfaces = BTreeSet::new();
+            let mut stack = vec![*name];
+            while let Some(name) = stack.pop() {
+                for interface in implements.get(name).into_iter().flatten() {
+                    if interfaces.insert(interface.to_string()) {
+                        stack.push(interface);
+                    }
+                }
+            }
+
+            for interface in &interfaces {
+                abstracts
+                    .implementations
+                    .entry(interface.clone())
+                    .or_default()
+                    .insert(name.to_string());
+                if objects.contains(name) {
+                    abstracts
+                        .possible_types
+                        .entry(interface.clone())
+                        .or_default()
+                        .insert(name.to_string());
+                }
+            }
+            abstracts.interfaces.insert(name.to_string(), interfaces);
+        }
+
+        for (union, members) in unions {
+            abstracts.possible_types.insert(
+                union.to_string(),
+                members
+                    .into_iter()
+                    .filter(|member| objects.contains(member))
+                    .map(str::to_string)
+                    .collect(),
+            );
+        }
+
+        abstracts
+    }
+}
+
+/// A type of the Index, sharing its definition with the fields indexed under
+/// it.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    definition: Arc<Definition>,
+    fields: IndexMap<Name, FieldEntry>,
+}
+
+// What the Index knows of a field beyond its definition, which is kept in the
+// definition of its type at `index`.
+#[derive(Debug, Serialize, Deserialize)]
+struct FieldEntry {
+    index: usize,
+    args: IndexMap<Name, usize>,
+    type_name: Name,
+    join_fields: Arc<[JoinFieldParsed]>,
+    graphs: Arc<[Graph]>,
+}
+
+/// A field of the Index, borrowed from the definition of its type.
+#[derive(Clone, Copy, Debug)]
+pub enum QueryField<'a> {
+    Field(IndexedField<'a>),
+    InputField(&'a InputFieldDefinition),
 }
 
-#[derive(Debug)]
-pub enum QueryField {
-    Field((FieldDefinition, IndexMap<String, InputFieldDefinition>)),
-    InputField(InputFieldDefinition),
+#[derive(Clone, Copy, Debug)]
+pub struct IndexedField<'a> {
+    pub definition: &'a FieldDefinition,
+    entry: &'a FieldEntry,
 }
 
-impl QueryField {
-    pub fn get_arg(&self, arg_name: &str) -> Option<&InputFieldDefinition> {
+impl<'a> IndexedField<'a> {
+    /// Name of the type the field is of, without its list and non-null
+    /// wrappers.
+    pub fn type_name(&self) -> &'a Name {
+        &self.entry.type_name
+    }
+
+    pub fn join_fields(&self) -> &'a Arc<[JoinFieldParsed]> {
+        &self.entry.join_fields
+    }
+
+    /// The graphs resolving the field on their own.
+    pub fn graphs(&self) -> &'a Arc<[Graph]> {
+        &self.entry.graphs
+    }
+
+    pub fn get_arg(&self, arg_name: &str) -> Option<&'a InputFieldDefinition> {
+        let index = *self.entry.args.get(arg_name)?;
+        self.definition.args.get(index)
+    }
+}
+
+impl<'a> QueryField<'a> {
+    pub fn pos(&self) -> Option<Pos> {
         match self {
-            QueryField::Field((_, args)) => args.get(arg_name),
+            QueryField::Field(field) => field.definition.pos,
+            QueryField::InputField(field) => field.pos,
+        }
+    }
+
+    pub fn get_arg(&self, arg_name: &str) -> Option<&'a InputFieldDefinition> {
+        match self {
+            QueryField::Field(field) => field.get_arg(arg_name),
             QueryField::InputField(_) => None,
         }
     }
@@ -32,35 +168,85 @@ impl QueryField {
 
 impl Index {
     pub fn type_is_scalar(&self, type_name: &str) -> bool {
-        let def = self.map.get(type_name).map(|(def, _)| def);
-
-        matches!(def, Some(Definition::Scalar(_)))
+        matches!(
+            self.get_type(type_name).map(AsRef::as_ref),
+            Some(Definition::Scalar(_))
+        )
     }
 
     pub fn type_is_enum(&self, type_name: &str) -> bool {
-        let def = self.map.get(type_name).map(|(def, _)| def);
-
-        matches!(def, Some(Definition::Enum(_)))
+        matches!(
+            self.get_type(type_name).map(AsRef::as_ref),
+            Some(Definition::Enum(_))
+        )
     }
 
     pub fn validate_enum_value(&self, type_name: &str, value: &str) -> bool {
-        let def = self.map.get(type_name).map(|(def, _)| def);
-
-        if let Some(Definition::Enum(enum_)) = def {
+        if let Some(Definition::Enum(enum_)) = self.get_type(type_name).map(AsRef::as_ref) {
             enum_.enum_values.iter().any(|v| v.name == value)
         } else {
             false
         }
     }
 
-    pub fn get_field(&self, type_name: &str, field_name: &str) -> Option<&QueryField> {
-        self.map
-            .get(type_name)
-            .and_then(|(_, fields_map)| fields_map.get(field_name))
+    pub fn get_field(&self, type_name: &str, field_name: &str) -> Option<QueryField<'_>> {
+        let Entry { definition, fields } = self.map.get(type_name)?;
+        let entry = fields.get(field_name)?;
+
+        match definition.as_ref() {
+            Definition::Object(ObjectTypeDefinition { fields, .. })
+            | Definition::Interface(InterfaceTypeDefinition { fields, .. }) => {
+                Some(QueryField::Field(IndexedField {
+                    definition: &fields[entry.index],
+                    entry,
+                }))
+            }
+            Definition::InputObject(input) => {
+                Some(QueryField::InputField(&input.fields[entry.index]))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn definitions(&self) -> impl Iterator<Item = &Definition> {
+        self.map.values().map(|entry| entry.definition.as_ref())
+    }
+
+    pub fn get_directive(&self, name: &str) -> Option<&DirectiveDefinition> {
+        self.directives.get(name)
+    }
+
+    pub fn directives(&self) -> impl Iterator<Item = &DirectiveDefinition> {
+        self.directives.values()
+    }
+
+    pub fn override_labels(&self) -> &[OverrideLabel] {
+        &self.override_labels
+    }
+
+    pub fn get_type(&self, type_name: &str) -> Option<&Arc<Definition>> {
+        self.map.get(type_name).map(|entry| &entry.definition)
+    }
+
+    /// The name of a type as shared by the Index.
+    pub fn type_name(&self, type_name: &str) -> Option<&Name> {
+        self.map.get_key_value(type_name).map(|(name, _)| name)
     }
 
-    pub fn get_type(&self, type_name: &str) -> Option<&(Definition, IndexMap<String, QueryField>)> {
-        self.map.get(type_name)
+    /// The source the Blueprint was read from.
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Location of a type, or of one of its fields, in the source the
+    /// Blueprint was read from.
+    pub fn location(&self, type_name: &str, field_name: Option<&str>) -> Option<String> {
+        let pos = match field_name {
+            Some(field_name) => self.get_field(type_name, field_name)?.pos(),
+            None => self.get_type(type_name)?.pos(),
+        };
+
+        pos.map(|pos| pos.in_source(self.source.as_deref()))
     }
 
     pub fn get_query(&self) -> Option<&str> {
@@ -71,122 +257,450 @@ impl Index {
         self.schema.mutation.as_deref()
     }
 
+    pub fn get_subscription(&self) -> Option<&str> {
+        self.schema.subscription.as_deref()
+    }
+
     pub fn is_type_implements(&self, type_name: &str, type_or_interface: &str) -> bool {
-        if type_name == type_or_interface {
-            return true;
+        type_name == type_or_interface || self.interfaces(type_name).contains(type_or_interface)
+    }
+
+    /// The interfaces an object or interface type implements, directly or
+    /// through the interfaces it implements.
+    pub fn interfaces(&self, type_name: &str) -> &BTreeSet<String> {
+        self.abstracts.interfaces.get(type_name).unwrap_or(&EMPTY)
+    }
+
+    /// The object and interface types implementing an interface.
+    pub fn implementations(&self, interface: &str) -> &BTreeSet<String> {
+        self.abstracts
+            .implementations
+            .get(interface)
+            .unwrap_or(&EMPTY)
+    }
+
+    pub fn union_members(&self, union: &str) -> Option<&BTreeSet<String>> {
+        match self.get_type(union).map(AsRef::as_ref) {
+            Some(Definition::Union(union)) => Some(&union.types),
+            _ => None,
         }
+    }
 
-        if let Some((Definition::Object(obj), _)) = self.map.get(type_name) {
-            obj.implements.contains(type_or_interface)
-        } else {
-            false
+    /// The object types a value of an abstract type can be, an interface's
+    /// implementations or a union's members.
+    pub fn possible_types(&self, abstract_type: &str) -> &BTreeSet<String> {
+        self.abstracts
+            .possible_types
+            .get(abstract_type)
+            .unwrap_or(&EMPTY)
+    }
+
+    /// Whether a value of `type_name` can be of type `object`, like a fragment
+    /// on `object` applying to a `type_name` field.
+    pub fn is_possible_type(&self, type_name: &str, object: &str) -> bool {
+        type_name == object || self.possible_types(type_name).contains(object)
+    }
+
+    /// The object types a value of an abstract type can be when it is
+    /// resolved by `graph`, which may know fewer of them than the supergraph.
+    pub fn possible_types_in(&self, graph: &Graph, abstract_type: &str) -> &BTreeSet<String> {
+        self.graph_abstracts
+            .get(graph)
+            .and_then(|abstracts| abstracts.possible_types.get(abstract_type))
+            .unwrap_or(&EMPTY)
+    }
+
+    /// What one graph of the supergraph holds.
+    pub fn graph(&self, graph: &Graph) -> Option<&GraphView> {
+        self.views.get(graph)
+    }
+
+    pub fn graphs(&self) -> impl Iterator<Item = &GraphView> {
+        self.views.values()
+    }
+
+    /// The graphs resolving a field on their own.
+    pub fn graphs_resolving(&self, type_name: &str, field_name: &str) -> &[Graph] {
+        match self.get_field(type_name, field_name) {
+            Some(QueryField::Field(field)) => field.graphs(),
+            _ => &[],
         }
     }
 
+    /// The schema of a subgraph as the supergraph tells it, to serve as its
+    /// `_service { sdl }`.
+    pub fn subgraph_sdl(&self, graph: &Graph) -> Option<String> {
+        let view = self.views.get(graph)?;
+        Some(view::sdl(view, self.definitions(), &self.schema))
+    }
+
     pub fn get_input_type_definition(&self, type_name: &str) -> Option<&InputObjectTypeDefinition> {
-        match self.map.get(type_name) {
-            Some((Definition::InputObject(input), _)) => Some(input),
+        match self.get_type(type_name).map(AsRef::as_ref) {
+            Some(Definition::InputObject(input)) => Some(input),
             _ => None,
         }
     }
 
     pub fn get_object_type_definition(&self, type_name: &str) -> Option<&ObjectTypeDefinition> {
-        match self.map.get(type_name) {
-            Some((Definition::Object(input), _)) => Some(input),
+        match self.get_type(type_name).map(AsRef::as_ref) {
+            Some(Definition::Object(input)) => Some(input),
             _ => None,
         }
     }
 }
 
+fn definition_name(definition: &Definition) -> &Name {
+    match definition {
+        Definition::Interface(def) => &def.name,
+        Definition::Object(def) => &def.name,
+        Definition::InputObject(def) => &def.name,
+        Definition::Scalar(def) => &def.name,
+        Definition::Enum(def) => &def.name,
+        Definition::Union(def) => &def.name,
+    }
+}
+
 impl From<&Blueprint> for Index {
     fn from(blueprint: &Blueprint) -> Self {
+        let views = view::views(blueprint);
+        let mut names = Interner::default();
         let mut map = IndexMap::new();
 
+        // The types are named before their fields refer to them, so that the
+        // Index holds the names of the Blueprint rather than copies of them.
         for definition in blueprint.definitions.iter() {
-            match definition {
-                Definition::Object(object_def) => {
-                    let type_name = object_def.name.clone();
-                    let mut fields_map = IndexMap::new();
-
-                    for field in &object_def.fields {
-                        let args_map = IndexMap::from_iter(
-                            field
-                                .args
-                                .iter()
-                                .map(|v| (v.name.clone(), v.clone()))
-                                .collect::<Vec<_>>(),
-                        );
-                        fields_map.insert(
-                            field.name.clone(),
-                            QueryField::Field((field.clone(), args_map)),
-                        );
-                    }
-
-                    map.insert(
-                        type_name,
-                        (Definition::Object(object_def.to_owned()), fields_map),
-                    );
-                }
-                Definition::Interface(interface_def) => {
-                    let type_name = interface_def.name.clone();
-                    let mut fields_map = IndexMap::new();
+            names.share(definition_name(definition));
+        }
 
-                    for field in interface_def.fields.clone() {
-                        let args_map = IndexMap::from_iter(
-                            field
+        for definition in blueprint.definitions.iter() {
+            let type_name = names.share(definition_name(definition));
+            let fields = match definition.as_ref() {
+                Definition::Object(ObjectTypeDefinition { fields, .. })
+                | Definition::Interface(InterfaceTypeDefinition { fields, .. }) => fields
+                    .iter()
+                    .enumerate()
+                    .map(|(index, field)| {
+                        let graphs = views
+                            .values()
+                            .filter(|view| view.can_resolve(&type_name, &field.name))
+                            .map(|view| view.graph.clone())
+                            .collect();
+                        let entry = FieldEntry {
+                            index,
+                            args: field
                                 .args
                                 .iter()
-                                .map(|v| (v.name.clone(), v.clone()))
-                                .collect::<Vec<_>>(),
-                        );
-                        fields_map.insert(field.name.clone(), QueryField::Field((field, args_map)));
-                    }
-
-                    map.insert(
-                        type_name,
-                        (Definition::Interface(interface_def.to_owned()), fields_map),
-                    );
+                                .enumerate()
+                                .map(|(index, arg)| (names.share(&arg.name), index))
+                                .collect(),
+                            type_name: names.intern(field.of_type.name()),
+                            join_fields: Arc::from(field.join_fields.as_slice()),
+                            graphs,
+                        };
+                        (names.share(&field.name), entry)
+                    })
+                    .collect(),
+                Definition::InputObject(input_object_def) => input_object_def
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(index, field)| {
+                        let entry = FieldEntry {
+                            index,
+                            args: IndexMap::new(),
+                            type_name: names.intern(field.of_type.name()),
+                            join_fields: Arc::from(field.join_fields.as_slice()),
+                            graphs: Arc::from([]),
+                        };
+                        (names.share(&field.name), entry)
+                    })
+                    .collect(),
+                Definition::Scalar(_) | Definition::Enum(_) | Definition::Union(_) => {
+                    IndexMap::new()
                 }
-                Definition::InputObject(input_object_def) => {
-                    let type_name = input_object_def.name.clone();
-                    let mut fields_map = IndexMap::new();
+            };
 
-                    for field in input_object_def.fields.clone() {
-                        fields_map.insert(field.name.clone(), QueryField::InputField(field));
-                    }
+            map.insert(type_name, Entry { definition: definition.clone(), fields });
+        }
 
-                    map.insert(
-                        type_name,
-                        (
-                            Definition::InputObject(input_object_def.to_owned()),
-                            fields_map,
-                        ),
-                    );
-                }
-                Definition::Scalar(scalar_def) => {
-                    let type_name = scalar_def.name.clone();
-                    map.insert(
-                        type_name.clone(),
-                        (Definition::Scalar(scalar_def.to_owned()), IndexMap::new()),
-                    );
-                }
-                Definition::Enum(enum_def) => {
-                    let type_name = enum_def.name.clone();
-                    map.insert(
-                        type_name.clone(),
-                        (Definition::Enum(enum_def.to_owned()), IndexMap::new()),
-                    );
-                }
-                Definition::Union(union_def) => {
-                    let type_name = union_def.name.clone();
-                    map.insert(
-                        type_name.clone(),
-                        (Definition::Union(union_def.to_owned()), IndexMap::new()),
-                    );
-                }
+        let directives = blueprint
+            .directives
+            .iter()
+            .chain(BUILTINS.directives.iter().filter(|builtin| {
+                !blueprint
+                    .directives
+                    .iter()
+                    .any(|directive| directive.name == builtin.name)
+            }))
+            .map(|directive| (names.intern(&directive.name), directive.clone()))
+            .collect();
+
+        let mut override_labels = Vec::<OverrideLabel>::new();
+        for label in map
+            .values()
+            .flat_map(|entry| entry.fields.values())
+            .flat_map(|field| field.join_fields.iter())
+            .filter_map(|join_field| join_field.override_label.as_ref())
+        {
+            if !override_labels.contains(label) {
+                override_labels.push(label.clone());
             }
         }
 
-        Self { map, schema: blueprint.schema.to_owned() }
+        Self {
+            map,
+            directives,
+            override_labels,
+            schema: blueprint.schema.to_owned(),
+            source: blueprint.source.to_owned(),
+            abstracts: abstracts(blueprint, None),
+            views,
+            graph_abstracts: graphs(blueprint)
+                .into_iter()
+                .map(|graph| {
+                    let abstracts = abstracts(blueprint, Some(&graph));
+                    (graph, abstracts)
+                })
+                .collect(),
+        }
+    }
+}
+
+fn graphs(blueprint: &Blueprint) -> BTreeSet<Graph> {
+    blueprint
+        .definitions
+        .iter()
+        .flat_map(|definition| definition.join_types())
+        .map(|join_type| join_type.graph.clone())
+        .collect()
+}
+
+// The abstract types of the supergraph, or of one of its graphs as told by
+// `@join__implements` and `@join__unionMember`. Without them a graph is
+// taken to know the relations between the types it has.
+fn abstracts(blueprint: &Blueprint, graph: Option<&Graph>) -> Abstracts {
+    let in_graph = |definition: &Definition| match graph {
+        Some(graph) => definition
+            .join_types()
+            .iter()
+            .any(|join_type| &join_type.graph == graph),
+        None => true,
+    };
+    let definitions = blueprint
+        .definitions
+        .iter()
+        .filter(|definition| in_graph(definition))
+        .map(AsRef::as_ref)
+        .collect::<Vec<_>>();
+    let names = definitions
+        .iter()
+        .map(|definition| definition.name())
+        .collect::<BTreeSet<_>>();
+
+    let mut implements = BTreeMap::<&str, BTreeSet<&str>>::new();
+    let mut unions = BTreeMap::<&str, BTreeSet<&str>>::new();
+    let mut objects = BTreeSet::new();
+    for definition in &definitions {
+        let (interfaces, join_implements) = match definition {
+            Definition::Object(def) => {
+                objects.insert(def.name.as_str());
+                (&def.implements, &def.join_implements)
+            }
+            Definition::Interface(def) => (&def.implements, &def.join_implements),
+            Definition::Union(def) => {
+                let members = match graph {
+                    Some(graph) if !def.join_unions.is_empty() => def
+                        .join_unions
+                        .iter()
+                        .filter(|join_union| &join_union.graph == graph)
+                        .map(|join_union| join_union.member.as_str())
+                        .collect(),
+                    _ => def.types.iter().map(String::as_str).collect(),
+                };
+                unions.insert(def.name.as_str(), members);
+                continue;
+            }
+            _ => continue,
+        };
+
+        let interfaces = match graph {
+            Some(graph) if !join_implements.is_empty() => join_implements
+                .iter()
+                .filter(|join_implements| &join_implements.graph == graph)
+                .map(|join_implements| join_implements.interface.as_str())
+                .collect(),
+            _ => interfaces.iter().map(String::as_str).collect(),
+        };
+        implements.insert(definition.name(), interfaces);
+    }
+
+    // Only the types the graph has.
+    for types in implements.values_mut().chain(unions.values_mut()) {
+        types.retain(|name: &&str| names.contains(name));
+    }
+
+    Abstracts::new(implements, unions, objects)
+}
+
+#[cfg(test)]
+mod tests {
+    use valid::Validator;
+
+    use super::*;
+
+    fn index() -> Index {
+        let graphql = r#"
+schema
+  @link(url: "https://specs.apollo.dev/link/v1.0")
+  @link(url: "https://specs.apollo.dev/join/v0.3", for: EXECUTION) {
+  query: Query
+}
+
+enum join__Graph {
+  A @join__graph(name: "a", url: "http://localhost:4001")
+  B @join__graph(name: "b", url: "http://localhost:4002")
+}
+
+type Query @join__type(graph: A) @join__type(graph: B) {
+  search: [SearchResult] @join__field(graph: A)
+  node: Node @join__field(graph: B)
+}
+
+interface Node @join__type(graph: A) @join__type(graph: B) {
+  id: ID!
+}
+
+interface Entity implements Node
+  @join__implements(graph: A, interface: "Node")
+  @join__type(graph: A) {
+  id: ID!
+}
+
+type User implements Entity & Node
+  @join__implements(graph: A, interface: "Entity")
+  @join__implements(graph: A, interface: "Node")
+  @join__implements(graph: B, interface: "Node")
+  @join__type(graph: A, key: "id")
+  @join__type(graph: B, key: "id") {
+  id: ID!
+}
+
+type Product implements Node
+  @join__implements(graph: B, interface: "Node")
+  @join__type(graph: A)
+  @join__type(graph: B) {
+  id: ID!
+}
+
+union SearchResult
+  @join__type(graph: A)
+  @join__unionMember(graph: A, member: "User") = User | Product
+"#;
+        Blueprint::parse(graphql).to_result().unwrap().to_index()
+    }
+
+    #[test]
+    fn test_possible_types() {
+        let index = index();
+        let names = |types: &BTreeSet<String>| types.iter().cloned().collect::<Vec<_>>();
+
+        assert_eq!(names(index.interfaces("Entity")), ["Node"]);
+        assert_eq!(names(index.interfaces("User")), ["Entity", "Node"]);
+        assert_eq!(
+            names(index.implementations("Node")),
+            ["Entity", "Product", "User"]
+        );
+        assert_eq!(names(index.possible_types("Node")), ["Product", "User"]);
+        assert_eq!(names(index.possible_types("Entity")), ["User"]);
+        assert_eq!(
+            names(index.possible_types("SearchResult")),
+            ["Product", "User"]
+        );
+        assert_eq!(
+            names(index.union_members("SearchResult").unwrap()),
+            ["Product", "User"]
+        );
+        assert!(index.is_type_implements("User", "Node"));
+        assert!(index.is_type_implements("Entity", "Node"));
+        assert!(index.is_possible_type("SearchResult", "Product"));
+        assert!(!index.is_possible_type("Entity", "Product"));
+    }
+
+    #[test]
+    fn test_possible_types_in_graph() {
+        let index = index();
+        let names = |types: &BTreeSet<String>| types.iter().cloned().collect::<Vec<_>>();
+        let (a, b) = (Graph::new("A"), Graph::new("B"));
+
+        assert_eq!(names(index.possible_types_in(&a, "Node")), ["User"]);
+        assert_eq!(
+            names(index.possible_types_in(&b, "Node")),
+            ["Product", "User"]
+        );
+        assert_eq!(names(index.possible_types_in(&a, "Entity")), ["User"]);
+        assert!(index.possible_types_in(&b, "Entity").is_empty());
+        assert_eq!(names(index.possible_types_in(&a, "SearchResult")), ["User"]);
+        assert!(index.possible_types_in(&b, "SearchResult").is_empty());
+    }
+
+    #[test]
+    fn test_override_labels() {
+        let supergraph = |labels: [&str; 3]| {
+            include_str!("fixtures/router.graphql")
+                .replace(
+                    "  username: String! @join__field(graph: ACCOUNTS)\n",
+                    &format!(
+                        "  username: String! @join__field(graph: ACCOUNTS, overrideLabel: \
+                         \"{}\")\n",
+                        labels[0]
+                    ),
+                )
+                .replace(
+                    "override: \"accounts\")",
+                    &format!("override: \"accounts\", overrideLabel: \"{}\")", labels[1]),
+                )
+                .replace(
+                    "  reviews: [Review!]! @join__field(graph: REVIEWS)\n",
+                    &format!(
+                        "  reviews: [Review!]! @join__field(graph: REVIEWS, overrideLabel: \
+                         \"{}\")\n",
+                        labels[2]
+                    ),
+                )
+        };
+
+        let index = Blueprint::parse(&supergraph(["percent(0.5)", "beta:v2", "beta:v2"]))
+            .to_result()
+            .unwrap()
+            .to_index();
+        assert_eq!(
+            index.override_labels(),
+            [
+                OverrideLabel::Custom("beta:v2".to_string()),
+                OverrideLabel::Percent(0.5)
+            ]
+        );
+
+        let errors = Blueprint::parse(&supergraph(["percent(101)", "percent(.5)", "2fast"]))
+            .to_result()
+            .unwrap_err();
+        insta::assert_snapshot!(errors);
+    }
+
+    #[test]
+    fn test_shares_blueprint() {
+        let blueprint = Blueprint::parse(include_str!("fixtures/router.graphql"))
+            .to_result()
+            .unwrap();
+        let index = blueprint.to_index();
+
+        for definition in &blueprint.definitions {
+            let indexed = index.get_type(definition.name()).unwrap();
+            assert!(Arc::ptr_eq(indexed, definition));
+            assert!(std::ptr::eq(
+                index.type_name(definition.name()).unwrap().as_str(),
+                definition.name()
+            ));
+        }
     }
 }
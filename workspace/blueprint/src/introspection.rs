@@ -0,0 +1,589 @@
+use std::collections::BTreeSet;
+
+use async_graphql_value::ConstValue;
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+use valid::{Valid, Validator};
+
+use crate::builtins::BUILTINS;
+use crate::print::print_value;
+use crate::{
+    Blueprint, Definition, Directive, DirectiveDefinition, EnumValueDefinition, Field,
+    FieldDefinition, Index, InputFieldDefinition, Selection, SelectionSet, Type,
+};
+
+/// Answers the `__schema`, `__type` and `__typename` fields of the query root
+/// from the API schema, so that introspection never reaches a subgraph.
+#[derive(Debug)]
+pub struct Introspection {
+    schema: Blueprint,
+    index: Index,
+}
+
+impl Blueprint {
+    /// Introspection of what clients see of the supergraph, its API schema.
+    pub fn introspection(&self) -> Valid<Introspection, String> {
+        self.to_api_schema().map(Introspection::new)
+    }
+}
+
+impl Introspection {
+    /// Introspects `api`, which is expected to be an API schema already.
+    pub fn new(api: Blueprint) -> Self {
+        let mut schema = api;
+        let defined = schema
+            .definitions
+            .iter()
+            .map(|definition| definition.name().to_string())
+            .collect::<BTreeSet<_>>();
+        for definition in BUILTINS.definitions.iter() {
+            if !defined.contains(definition.name()) {
+                schema.definitions.push(definition.clone());
+            }
+        }
+        for directive in BUILTINS.directives.iter() {
+            if !schema.directives.iter().any(|d| d.name == directive.name) {
+                schema.directives.push(directive.clone());
+            }
+        }
+        schema.definitions.sort_by(|a, b| a.name().cmp(b.name()));
+
+        let index = schema.to_index();
+        Introspection { schema, index }
+    }
+
+    /// The index of the API schema along with the introspection types, to
+    /// validate operations selecting from both against.
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Whether a field of the query root is answered by introspection.
+    pub fn is_introspection_field(name: &str) -> bool {
+        matches!(name, "__schema" | "__type" | "__typename")
+    }
+
+    /// Answers a selection of the query root made of introspection fields.
+    pub fn resolve(&self, selection_set: &SelectionSet) -> Valid<Value, String> {
+        Valid::from_option(
+            self.schema.schema.query.as_deref(),
+            "Root operation for `query` is not defined".to_string(),
+        )
+        .and_then(|query| {
+            self.check(selection_set)
+                .and_then(|_| self.object(&Node::Root(query), selection_set))
+        })
+    }
+
+    // Checks what is selected of the introspection types up front, so that
+    // a mistake is reported once rather than for every type it is made on.
+    fn check(&self, selection_set: &SelectionSet) -> Valid<(), String> {
+        Valid::from_iter(&selection_set.0, |selection| match selection {
+            Selection::Field(field) => {
+                let checked = match field.name.as_str() {
+                    "__schema" => self.index.validate_field_set("__Schema", &field.selections),
+                    "__type" => self.index.validate_field_set("__Type", &field.selections),
+                    _ => Valid::succeed(()),
+                };
+                checked.trace(field.alias.as_deref().unwrap_or(&field.name))
+            }
+            Selection::InlineFragment(fragment) => self.check(&fragment.selections),
+        })
+        .unit()
+    }
+
+    fn object(&self, node: &Node<'_>, selection_set: &SelectionSet) -> Valid<Value, String> {
+        let mut fields = IndexMap::<&str, Vec<&Field>>::new();
+        collect(node.type_name(), selection_set, &mut fields);
+
+        Valid::from_iter(fields, |(key, fields)| {
+            let field = fields[0];
+            let selections = SelectionSet(
+                fields
+                    .iter()
+                    .flat_map(|field| field.selections.0.iter().cloned())
+                    .collect(),
+            );
+
+            self.field(node, field)
+                .and_then(|output| self.complete(node, field, output, &selections))
+                .map(|value| (key.to_string(), value))
+                .trace(key)
+        })
+        .map(|entries| Value::Object(entries.into_iter().collect()))
+    }
+
+    fn complete(
+        &self,
+        node: &Node<'_>,
+        field: &Field,
+        output: Output<'_>,
+        selections: &SelectionSet,
+    ) -> Valid<Value, String> {
+        match output {
+            Output::Null => Valid::succeed(Value::Null),
+            Output::Value(value) if selections.0.is_empty() => Valid::succeed(value),
+            Output::Value(_) => Valid::fail(format!(
+                "Field `{}.{}` has no fields to select",
+                node.type_name(),
+                field.name
+            )),
+            _ if selections.0.is_empty() => Valid::fail(format!(
+                "Field `{}.{}` must select its fields",
+                node.type_name(),
+                field.name
+            )),
+            Output::Node(node) => self.object(&node, selections),
+            Output::Nodes(nodes) => {
+                Valid::from_iter(nodes, |node| self.object(&node, selections)).map(Value::Array)
+            }
+        }
+    }
+
+    fn field<'a>(&'a self, node: &Node<'a>, field: &Field) -> Valid<Output<'a>, String> {
+        if field.name == "__typename" {
+            return Valid::succeed(Output::Value(json!(node.type_name())));
+        }
+
+        let output = match (node, field.name.as_str()) {
+            (Node::Root(_), "__schema") => Output::Node(Node::Schema),
+            (Node::Root(_), "__type") => {
+                return string_argument(field, "name").map(|name| {
+                    match self.index.get_type(&name) {
+                        Some(_) => Output::Node(Node::Type(Type::named(name))),
+                        None => Output::Null,
+                    }
+                });
+            }
+
+            (Node::Schema, "description") => Output::Null,
+            (Node::Schema, "types") => Output::Nodes(
+                self.schema
+                    .definitions
+                    .iter()
+                    .map(|definition| Node::Type(Type::named(definition.name())))
+                    .collect(),
+            ),
+            (Node::Schema, "queryType") => named(self.schema.schema.query.as_deref()),
+            (Node::Schema, "mutationType") => named(self.schema.schema.mutation.as_deref()),
+            (Node::Schema, "subscriptionType") => named(self.schema.schema.subscription.as_deref()),
+            (Node::Schema, "directives") => {
+                Output::Nodes(self.schema.directives.iter().map(Node::Directive).collect())
+            }
+
+            (Node::Type(of_type), name) => return self.type_field(of_type, field, name),
+
+            (Node::Field(def), "name") => Output::Value(json!(def.name)),
+            (Node::Field(def), "description") => Output::Value(json!(def.description)),
+            (Node::Field(def), "args") => {
+                return input_values(field, &def.args);
+            }
+            (Node::Field(def), "type") => Output::Node(Node::Type(def.of_type.clone())),
+            (Node::Field(def), "isDeprecated") => {
+                Output::Value(json!(deprecation(&def.directives).is_some()))
+            }
+            (Node::Field(def), "deprecationReason") => {
+                Output::Value(json!(deprecation(&def.directives)))
+            }
+
+            (Node::InputValue(def), "name") => Output::Value(json!(def.name)),
+            (Node::InputValue(def), "description") => Output::Value(json!(def.description)),
+            (Node::InputValue(def), "type") => Output::Node(Node::Type(def.of_type.clone())),
+            (Node::InputValue(def), "defaultValue") => Output::Value(json!(def
+                .default_value
+                .as_ref()
+                .map(|value| print_value(&self.schema, value, &def.of_type)))),
+            (Node::InputValue(def), "isDeprecated") => {
+                Output::Value(json!(deprecation(&def.directives).is_some()))
+            }
+            (Node::InputValue(def), "deprecationReason") => {
+                Output::Value(json!(deprecation(&def.directives)))
+            }
+
+            (Node::EnumValue(def), "name") => Output::Value(json!(def.name)),
+            (Node::EnumValue(def), "description") => Output::Value(json!(def.description)),
+            (Node::EnumValue(def), "isDeprecated") => {
+                Output::Value(json!(deprecation(&def.directives).is_some()))
+            }
+            (Node::EnumValue(def), "deprecationReason") => {
+                Output::Value(json!(deprecation(&def.directives)))
+            }
+
+            (Node::Directive(def), "name") => Output::Value(json!(def.name)),
+            (Node::Directive(def), "description") => Output::Value(json!(def.description)),
+            (Node::Directive(def), "locations") => Output::Value(json!(def
+                .locations
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>())),
+            (Node::Directive(def), "args") => {
+                return input_values(field, &def.arguments);
+            }
+            (Node::Directive(def), "isRepeatable") => Output::Value(json!(def.repeatable)),
+
+            (node, name) => {
+                return Valid::fail(format!(
+                    "Field `{}` is not defined on `{}`",
+                    name,
+                    node.type_name()
+                ))
+            }
+        };
+
+        Valid::succeed(output)
+    }
+
+    fn type_field<'a>(
+        &'a self,
+        of_type: &Type,
+        field: &Field,
+        name: &str,
+    ) -> Valid<Output<'a>, String> {
+        // Wrapping types only have a kind and the type they wrap.
+        let wrapped = match of_type {
+            _ if of_type.is_required() => Some(("NON_NULL", of_type.to_nullable())),
+            Type::List { of_type, .. } => Some(("LIST", of_type.as_ref().clone())),
+            Type::Named { .. } => None,
+        };
+        if let Some((kind, of_type)) = wrapped {
+            return match name {
+                "kind" => Valid::succeed(Output::Value(json!(kind))),
+                "ofType" => Valid::succeed(Output::Node(Node::Type(of_type))),
+                "name" | "description" | "specifiedByURL" | "fields" | "interfaces"
+                | "possibleTypes" | "enumValues" | "inputFields" | "isOneOf" => {
+                    Valid::succeed(Output::Null)
+                }
+                name => Valid::fail(format!("Field `{}` is not defined on `__Type`", name)),
+            };
+        }
+
+        let Some(definition) = self.index.get_type(of_type.name()) else {
+            return Valid::fail(format!("Type `{}` is not defined", of_type.name()));
+        };
+        let definition = definition.as_ref();
+
+        let output = match (definition, name) {
+            (_, "kind") => Output::Value(json!(match definition {
+                Definition::Interface(_) => "INTERFACE",
+                Definition::Object(_) => "OBJECT",
+                Definition::InputObject(_) => "INPUT_OBJECT",
+                Definition::Scalar(_) => "SCALAR",
+                Definition::Enum(_) => "ENUM",
+                Definition::Union(_) => "UNION",
+            })),
+            (_, "name") => Output::Value(json!(definition.name())),
+            (_, "description") => Output::Value(json!(match definition {
+                Definition::Interface(def) => &def.description,
+                Definition::Object(def) => &def.description,
+                Definition::InputObject(def) => &def.description,
+                Definition::Scalar(def) => &def.description,
+                Definition::Enum(def) => &def.description,
+                Definition::Union(def) => &def.description,
+            })),
+            (Definition::Scalar(def), "specifiedByURL") => Output::Value(
+                def.directives
+                    .iter()
+                    .find(|directive| directive.name == "specifiedBy")
+                    .and_then(|directive| directive.arguments.get("url"))
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+            (Definition::Object(def), "fields") => return fields(field, &def.fields),
+            (Definition::Interface(def), "fields") => return fields(field, &def.fields),
+            (Definition::Object(def), "interfaces") => interfaces(&def.implements),
+            (Definition::Interface(def), "interfaces") => interfaces(&def.implements),
+            (Definition::Interface(_) | Definition::Union(_), "possibleTypes") => Output::Nodes(
+                self.index
+                    .possible_types(definition.name())
+                    .iter()
+                    .map(|name| Node::Type(Type::named(name)))
+                    .collect(),
+            ),
+            (Definition::Enum(def), "enumValues") => {
+                return include_deprecated(field).map(|include| {
+                    Output::Nodes(
+                        def.enum_values
+                            .iter()
+                            .filter(|value| include || deprecation(&value.directives).is_none())
+                            .map(Node::EnumValue)
+                            .collect(),
+                    )
+                });
+            }
+            (Definition::InputObject(def), "inputFields") => {
+                return input_values(field, &def.fields);
+            }
+            (Definition::InputObject(def), "isOneOf") => Output::Value(json!(def
+                .directives
+                .iter()
+                .any(|directive| directive.name == "oneOf"))),
+            (
+                _,
+                "specifiedByURL" | "fields" | "interfaces" | "possibleTypes" | "enumValues"
+                | "inputFields" | "ofType" | "isOneOf",
+            ) => Output::Null,
+            (_, name) => {
+                return Valid::fail(format!("Field `{}` is not defined on `__Type`", name));
+            }
+        };
+
+        Valid::succeed(output)
+    }
+}
+
+// An object introspection answers with.
+enum Node<'a> {
+    Root(&'a str),
+    Schema,
+    Type(Type),
+    Field(&'a FieldDefinition),
+    InputValue(&'a InputFieldDefinition),
+    EnumValue(&'a EnumValueDefinition),
+    Directive(&'a DirectiveDefinition),
+}
+
+impl Node<'_> {
+    fn type_name(&self) -> &str {
+        match self {
+            Node::Root(name) => name,
+            Node::Schema => "__Schema",
+            Node::Type(_) => "__Type",
+            Node::Field(_) => "__Field",
+            Node::InputValue(_) => "__InputValue",
+            Node::EnumValue(_) => "__EnumValue",
+            Node::Directive(_) => "__Directive",
+        }
+    }
+}
+
+// What a field of a node gives, before its selections are applied.
+enum Output<'a> {
+    Null,
+    Value(Value),
+    Node(Node<'a>),
+    Nodes(Vec<Node<'a>>),
+}
+
+// Groups the fields selected on an object by the key they are answered under,
+// through the fragments that apply to it.
+fn collect<'a>(
+    type_name: &str,
+    selection_set: &'a SelectionSet,
+    fields: &mut IndexMap<&'a str, Vec<&'a Field>>,
+) {
+    for selection in &selection_set.0 {
+        match selection {
+            Selection::Field(field) => {
+                let key = field.alias.as_deref().unwrap_or(&field.name);
+                fields.entry(key).or_default().push(field);
+            }
+            Selection::InlineFragment(fragment) => {
+                if fragment
+                    .type_condition
+                    .as_deref()
+                    .is_none_or(|type_condition| type_condition == type_name)
+                {
+                    collect(type_name, &fragment.selections, fields);
+                }
+            }
+        }
+    }
+}
+
+fn named(name: Option<&str>) -> Output<'_> {
+    match name {
+        Some(name) => Output::Node(Node::Type(Type::named(name))),
+        None => Output::Null,
+    }
+}
+
+fn interfaces(implements: &BTreeSet<String>) -> Output<'_> {
+    Output::Nodes(
+        implements
+            .iter()
+            .map(|name| Node::Type(Type::named(name)))
+            .collect(),
+    )
+}
+
+fn fields<'a>(field: &Field, fields: &'a [FieldDefinition]) -> Valid<Output<'a>, String> {
+    include_deprecated(field).map(|include| {
+        Output::Nodes(
+            fields
+                .iter()
+                .filter(|def| include || deprecation(&def.directives).is_none())
+                .map(Node::Field)
+                .collect(),
+        )
+    })
+}
+
+fn input_values<'a>(
+    field: &Field,
+    inputs: &'a [InputFieldDefinition],
+) -> Valid<Output<'a>, String> {
+    include_deprecated(field).map(|include| {
+        Output::Nodes(
+            inputs
+                .iter()
+                .filter(|def| include || deprecation(&def.directives).is_none())
+                .map(Node::InputValue)
+                .collect(),
+        )
+    })
+}
+
+// The reason an element is deprecated for, if it is.
+fn deprecation(directives: &[Directive]) -> Option<String> {
+    let directive = directives
+        .iter()
+        .find(|directive| directive.name == "deprecated")?;
+
+    Some(
+        directive
+            .arguments
+            .get("reason")
+            .and_then(Value::as_str)
+            .unwrap_or("No longer supported")
+            .to_string(),
+    )
+}
+
+fn include_deprecated(field: &Field) -> Valid<bool, String> {
+    match field.arguments.get("includeDeprecated") {
+        None | Some(ConstValue::Null) => Valid::succeed(false),
+        Some(ConstValue::Boolean(include)) => Valid::succeed(*include),
+        Some(_) => Valid::fail(format!(
+            "Argument `includeDeprecated` of `{}` must be a Boolean",
+            field.name
+        )),
+    }
+}
+
+fn string_argument(field: &Field, name: &str) -> Valid<String, String> {
+    match field.arguments.get(name) {
+        Some(ConstValue::String(value)) => Valid::succeed(value.clone()),
+        _ => Valid::fail(format!(
+            "Argument `{}` of `{}` must be a String",
+            name, field.name
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use valid::Validator;
+
+    use super::*;
+    use crate::parse_query_string;
+
+    const SUPERGRAPH: &str = r#"
+schema
+  @link(url: "https://specs.apollo.dev/link/v1.0")
+  @link(url: "https://specs.apollo.dev/join/v0.3", for: EXECUTION)
+  @link(url: "https://specs.apollo.dev/inaccessible/v0.2", for: SECURITY) {
+  query: Query
+}
+
+directive @join__type(graph: join__Graph!, key: join__FieldSet, resolvable: Boolean = true) repeatable on OBJECT | INTERFACE | SCALAR | ENUM | INPUT_OBJECT
+directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, external: Boolean) repeatable on FIELD_DEFINITION
+directive @inaccessible on FIELD_DEFINITION | OBJECT | ARGUMENT_DEFINITION | ENUM_VALUE
+
+"Marks a field as costly to resolve."
+directive @cost(weight: Int!) on FIELD_DEFINITION
+
+scalar join__FieldSet
+
+enum join__Graph {
+  A @join__graph(name: "a", url: "http://localhost:4001")
+}
+
+scalar Url @join__type(graph: A) @specifiedBy(url: "https://url.spec.whatwg.org")
+
+enum Unit @join__type(graph: A) {
+  CM
+  IN @deprecated(reason: "Use `CM`")
+}
+
+input Size @join__type(graph: A) {
+  value: Int = 1
+  unit: Unit = CM
+}
+
+"A thing for sale."
+type Product @join__type(graph: A, key: "id") {
+  id: ID!
+  url: Url
+  size(unit: [Unit] = [CM]): Int
+  weight: Int @deprecated
+  secret: String @inaccessible
+}
+
+type Query @join__type(graph: A) {
+  product(id: ID!, size: Size = {value: 2}): Product
+}
+"#;
+
+    fn resolve(query: &str) -> Valid<Value, String> {
+        Blueprint::parse(SUPERGRAPH)
+            .and_then(|blueprint| blueprint.introspection())
+            .zip(parse_query_string(Some(query.to_string())))
+            .and_then(|(introspection, selection_set)| {
+                introspection.resolve(&selection_set.unwrap())
+            })
+    }
+
+    #[test]
+    fn test_introspection() {
+        let value = resolve(
+            r#"
+            __typename
+            __schema {
+              queryType { name }
+              mutationType { name }
+              types { name kind }
+              directives { name description locations args { name type { kind name ofType { name } } } }
+            }
+            product: __type(name: "Product") {
+              name
+              description
+              fields { name isDeprecated }
+              all: fields(includeDeprecated: true) {
+                name
+                isDeprecated
+                deprecationReason
+                args { name defaultValue type { kind ofType { kind name } } }
+                type { kind name ofType { kind name } }
+              }
+            }
+            unit: __type(name: "Unit") {
+              enumValues(includeDeprecated: true) { name isDeprecated deprecationReason }
+            }
+            url: __type(name: "Url") { kind specifiedByURL }
+            size: __type(name: "Size") { inputFields { name defaultValue } }
+            query: __type(name: "Query") {
+              fields { args { name defaultValue } }
+            }
+            missing: __type(name: "Missing") { name }
+            "#,
+        )
+        .to_result()
+        .unwrap_or_else(|error| panic!("{}", error));
+
+        insta::assert_snapshot!(serde_json::to_string_pretty(&value).unwrap());
+    }
+
+    #[test]
+    fn test_introspection_errors() {
+        let errors = resolve(
+            r#"
+            __type(name: 1) { name }
+            __schema { types { name size } queryType }
+            "#,
+        )
+        .to_result()
+        .unwrap_err();
+
+        insta::assert_snapshot!(errors);
+    }
+}
@@ -1,8 +1,36 @@
+mod api;
 mod blueprint;
 mod build;
+mod builtins;
+mod compose;
+mod contract;
+mod diff;
+mod field_set;
 mod index;
+mod introspection;
+mod link;
+mod name;
+mod pos;
+mod print;
+mod satisfiability;
+mod snapshot;
+mod source;
+mod types;
+mod view;
 
 // Public API
 pub mod error;
 pub use blueprint::*;
+pub use compose::Subgraph;
+pub use contract::Contract;
+pub use diff::{Change, ChangeKind, Diff, Severity};
 pub use index::*;
+pub use introspection::Introspection;
+pub use link::*;
+pub use name::Name;
+pub use pos::*;
+pub use snapshot::SNAPSHOT_VERSION;
+pub use source::{DirectorySource, FileSource, Revision, Source, Supergraph};
+#[cfg(not(target_arch = "wasm32"))]
+pub use source::{HttpSource, Watcher};
+pub use view::{GraphField, GraphType, GraphView, Key, Resolution};
@@ -0,0 +1,410 @@
+use std::fmt::{self, Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use valid::{Valid, Validator};
+
+use crate::build::at;
+use crate::Directive;
+
+const SPECS: &str = "https://specs.apollo.dev/";
+
+// The specs we know how to read, with the versions we read them in.
+const SUPPORTED: &[(&str, Version, Version)] = &[
+    ("link", Version::new(1, 0), Version::new(1, 0)),
+    ("core", Version::new(0, 1), Version::new(0, 2)),
+    ("join", Version::new(0, 1), Version::new(0, 5)),
+    ("inaccessible", Version::new(0, 1), Version::new(0, 2)),
+    ("tag", Version::new(0, 1), Version::new(0, 3)),
+];
+
+/// A spec the supergraph links to, with `@link` or, in federation 1
+/// supergraphs, with `@core`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Link {
+    /// Url of the spec, `https://specs.apollo.dev/join/v0.3`.
+    pub url: String,
+    /// Name of the spec, `join`.
+    pub name: String,
+    pub version: Option<Version>,
+    /// Prefix of the spec's elements in the document, its name unless it is
+    /// renamed with `as`.
+    pub namespace: String,
+    pub imports: Vec<Import>,
+    pub purpose: Option<Purpose>,
+}
+
+/// An element of a spec used without its namespace. Directives keep their
+/// `@`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Import {
+    pub name: String,
+    pub alias: String,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Purpose {
+    Security,
+    Execution,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Version { major, minor }
+    }
+
+    fn parse(version: &str) -> Option<Version> {
+        let (major, minor) = version.strip_prefix('v')?.split_once('.')?;
+        Some(Version::new(major.parse().ok()?, minor.parse().ok()?))
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}.{}", self.major, self.minor)
+    }
+}
+
+impl Display for Purpose {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Purpose::Security => write!(f, "SECURITY"),
+            Purpose::Execution => write!(f, "EXECUTION"),
+        }
+    }
+}
+
+impl Link {
+    // A spec that is not linked is read under its own name.
+    fn unlinked(name: &str) -> Self {
+        Link {
+            url: format!("{}{}", SPECS, name),
+            name: name.to_string(),
+            version: None,
+            namespace: name.to_string(),
+            imports: Vec::new(),
+            purpose: None,
+        }
+    }
+
+    /// Name of one of the spec's directives in the document. The directive
+    /// named after the spec, like `@inaccessible`, goes by the namespace.
+    pub fn directive(&self, name: &str) -> String {
+        let imported = format!("@{}", name);
+        match self.imports.iter().find(|import| import.name == imported) {
+            Some(import) => import.alias.trim_start_matches('@').to_string(),
+            None if name == self.name => self.namespace.clone(),
+            None => format!("{}__{}", self.namespace, name),
+        }
+    }
+
+    /// Name of one of the spec's types in the document.
+    pub fn type_name(&self, name: &str) -> String {
+        match self.imports.iter().find(|import| import.name == name) {
+            Some(import) => import.alias.clone(),
+            None => format!("{}__{}", self.namespace, name),
+        }
+    }
+
+    /// Whether a directive or type of the document belongs to this spec.
+    pub fn owns(&self, name: &str) -> bool {
+        name == self.namespace
+            || name
+                .split_once("__")
+                .is_some_and(|(namespace, _)| namespace == self.namespace)
+            || self
+                .imports
+                .iter()
+                .any(|import| import.alias.trim_start_matches('@') == name)
+    }
+
+    fn is_supported(&self) -> bool {
+        let Some(version) = self.version else {
+            return false;
+        };
+
+        self.url.starts_with(SPECS)
+            && SUPPORTED
+                .iter()
+                .any(|(name, min, max)| *name == self.name && (*min..=*max).contains(&version))
+    }
+
+    // Specs needed to execute or secure operations must be understood, the
+    // others can be ignored.
+    fn check(&self) -> Valid<(), String> {
+        if self.name == "join" && !self.is_supported() {
+            return Valid::fail(format!(
+                "Version `{}` of the join spec is not supported, only v0.1 to v0.5 are",
+                self.version
+                    .map(|version| version.to_string())
+                    .unwrap_or_default()
+            ));
+        }
+
+        match self.purpose {
+            Some(purpose) if !self.is_supported() => Valid::fail(format!(
+                "`{}` is linked for {} but is not supported",
+                self.url, purpose
+            )),
+            _ => Valid::succeed(()),
+        }
+    }
+}
+
+/// The specs linked by a supergraph.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Links(Vec<Link>);
+
+impl Links {
+    /// Reads the `@link`s, or `@core`s, applied to the schema. The link spec
+    /// can itself be renamed, so it is found by its url first.
+    pub(crate) fn parse(directives: &[Directive], source: Option<&str>) -> Valid<Links, String> {
+        let bootstrap = directives.iter().find_map(|directive| {
+            ["link", "core"]
+                .into_iter()
+                .find(|spec| {
+                    let url = directive
+                        .arguments
+                        .get(url_argument(spec))
+                        .and_then(Value::as_str);
+                    let namespace = directive
+                        .arguments
+                        .get("as")
+                        .and_then(Value::as_str)
+                        .unwrap_or(spec);
+
+                    url.and_then(|url| split_url(url).map(|(name, _)| name == *spec))
+                        .unwrap_or_default()
+                        && directive.name == namespace
+                })
+                .map(|spec| (directive.name.as_str(), url_argument(spec)))
+        });
+
+        let Some((name, url)) = bootstrap else {
+            return Valid::succeed(Links::default());
+        };
+
+        Valid::from_iter(
+            directives.iter().filter(|directive| directive.name == name),
+            |directive| {
+                let link = parse_link(directive, url).and_then(|link| link.check().map_to(link));
+                at(link.trace(&format!("@{}", name)), directive.pos, source)
+            },
+        )
+        .map(Links)
+    }
+
+    pub(crate) fn push(&mut self, link: Link) {
+        self.0.push(link)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Link> {
+        self.0.iter()
+    }
+
+    /// The link to a spec, by the spec's name.
+    pub fn get(&self, name: &str) -> Option<&Link> {
+        self.0.iter().find(|link| link.name == name)
+    }
+
+    /// Whether a directive or type of the document belongs to a linked spec.
+    /// The join and link specs are machinery even when they are not linked.
+    pub fn owns(&self, name: &str) -> bool {
+        self.0.iter().any(|link| link.owns(name))
+            || ["join", "link"]
+                .into_iter()
+                .filter(|spec| self.get(spec).is_none())
+                .any(|spec| Link::unlinked(spec).owns(name))
+    }
+
+    /// Name of a directive of a spec in the document, see [Link::directive].
+    pub fn directive(&self, spec: &str, name: &str) -> String {
+        match self.get(spec) {
+            Some(link) => link.directive(name),
+            None => Link::unlinked(spec).directive(name),
+        }
+    }
+
+    /// Name of a type of a spec in the document, see [Link::type_name].
+    pub fn type_name(&self, spec: &str, name: &str) -> String {
+        match self.get(spec) {
+            Some(link) => link.type_name(name),
+            None => Link::unlinked(spec).type_name(name),
+        }
+    }
+}
+
+fn url_argument(spec: &str) -> &'static str {
+    if spec == "core" {
+        "feature"
+    } else {
+        "url"
+    }
+}
+
+// `https://specs.apollo.dev/join/v0.3` is version v0.3 of the `join` spec.
+fn split_url(url: &str) -> Option<(&str, Option<Version>)> {
+    let (rest, last) = url.trim_end_matches('/').rsplit_once('/')?;
+    match Version::parse(last) {
+        Some(version) => Some((rest.rsplit_once('/')?.1, Some(version))),
+        None => Some((last, None)),
+    }
+}
+
+fn parse_link(directive: &Directive, url_argument: &str) -> Valid<Link, String> {
+    let arguments = &directive.arguments;
+    let string = |name: &str| arguments.get(name).and_then(Value::as_str);
+
+    let Some(url) = string(url_argument) else {
+        return Valid::fail(format!("Argument `{}` is required", url_argument));
+    };
+    let Some((name, version)) = split_url(url).filter(|(name, _)| is_name(name)) else {
+        return Valid::fail(format!("`{}` is not the url of a spec", url));
+    };
+    let namespace = string("as").unwrap_or(name);
+
+    let imports = Valid::from_iter(
+        arguments
+            .get("import")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten(),
+        |import| {
+            let (name, alias) = match import {
+                Value::String(name) => (name.as_str(), name.as_str()),
+                Value::Object(import) => {
+                    let name = import.get("name").and_then(Value::as_str);
+                    let alias = import.get("as").and_then(Value::as_str);
+                    match name {
+                        Some(name) => (name, alias.unwrap_or(name)),
+                        None => {
+                            return Valid::fail(format!(
+                                "Import `{}` has no name",
+                                Value::Object(import.clone())
+                            ))
+                        }
+                    }
+                }
+                _ => return Valid::fail(format!("Import `{}` is not valid", import)),
+            };
+
+            if name.starts_with('@') != alias.starts_with('@') {
+                Valid::fail(format!(
+                    "Import `{}` can not be renamed to `{}`, only directives start with `@`",
+                    name, alias
+                ))
+            } else {
+                Valid::succeed(Import { name: name.to_string(), alias: alias.to_string() })
+            }
+        },
+    )
+    .trace("import");
+
+    let purpose = match string("for") {
+        None => Valid::none(),
+        Some("SECURITY") => Valid::succeed(Some(Purpose::Security)),
+        Some("EXECUTION") => Valid::succeed(Some(Purpose::Execution)),
+        Some(purpose) => Valid::fail(format!("Purpose `{}` is not known", purpose)).trace("for"),
+    };
+
+    imports.zip(purpose).map(|(imports, purpose)| Link {
+        url: url.to_string(),
+        name: name.to_string(),
+        version,
+        namespace: namespace.to_string(),
+        imports,
+        purpose,
+    })
+}
+
+fn is_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use valid::Validator;
+
+    use crate::Blueprint;
+
+    // The join data and fields of the types clients see, whatever the specs'
+    // elements are named.
+    fn joined(blueprint: &Blueprint) -> Value {
+        fn strip(value: &mut Value) {
+            match value {
+                Value::Object(map) => {
+                    map.remove("directives");
+                    map.remove("pos");
+                    map.values_mut().for_each(strip);
+                }
+                Value::Array(values) => values.iter_mut().for_each(strip),
+                _ => {}
+            }
+        }
+
+        let definitions = blueprint
+            .definitions
+            .iter()
+            .filter(|definition| {
+                !blueprint
+                    .links
+                    .iter()
+                    .any(|link| link.owns(definition.name()))
+            })
+            .collect::<Vec<_>>();
+        let mut value = serde_json::json!([definitions, blueprint.join_graphs]);
+        strip(&mut value);
+        value
+    }
+
+    #[test]
+    fn test_renamed_join() {
+        let graphql = include_str!("fixtures/router.graphql");
+        let renamed = graphql
+            .replace(
+                r#"join/v0.3", for: EXECUTION"#,
+                r#"join/v0.3", as: "j", import: [{ name: "@field", as: "@resolved" }], for: EXECUTION"#,
+            )
+            .replace("@join__field", "@resolved")
+            .replace("join__", "j__");
+
+        let expected = Blueprint::parse(graphql).to_result().unwrap();
+        let actual = Blueprint::parse(&renamed).to_result().unwrap();
+
+        assert_eq!(joined(&actual), joined(&expected));
+        assert_eq!(
+            actual.to_api_schema().to_result().unwrap().to_string(),
+            expected.to_api_schema().to_result().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn test_link_errors() {
+        let graphql = r#"
+            schema
+              @link(url: "https://specs.apollo.dev/link/v1.0")
+              @link(url: "https://specs.apollo.dev/join/v0.6", for: EXECUTION)
+              @link(url: "https://example.com/authorization/v1.0", for: SECURITY)
+              @link(url: "https://example.com/cache/v0.1")
+              @link(url: "https://specs.apollo.dev/tag/v0.3", import: [{ name: "@tag", as: "Tag" }]) {
+              query: Query
+            }
+
+            type Query {
+              me: String
+            }
+        "#;
+        let errors = Blueprint::parse(graphql).to_result().unwrap_err();
+        insta::assert_snapshot!(errors);
+    }
+}
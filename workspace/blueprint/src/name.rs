@@ -0,0 +1,167 @@
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::{self, Debug, Display};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Name of a type, field or argument, shared by the Index and the query plans
+/// built with it so that cloning one doesn't allocate.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(transparent)]
+pub struct Name(Arc<str>);
+
+impl Name {
+    pub fn new(name: &str) -> Self {
+        Name(Arc::from(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Name {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Name {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Name::new(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Name(Arc::from(name))
+    }
+}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Name {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl PartialEq<Name> for String {
+    fn eq(&self, other: &Name) -> bool {
+        **self == *other.0
+    }
+}
+
+// Names are read through the interner of `Interner::decode` when there is
+// one, so that a value read back shares its names like the one written.
+impl<'de> Deserialize<'de> for Name {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NameVisitor;
+
+        impl Visitor<'_> for NameVisitor {
+            type Value = Name;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Name, E> {
+                Ok(DECODING.with_borrow_mut(|interner| match interner {
+                    Some(interner) => interner.intern(name),
+                    None => Name::new(name),
+                }))
+            }
+        }
+
+        deserializer.deserialize_str(NameVisitor)
+    }
+}
+
+impl Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&*self.0, f)
+    }
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+thread_local! {
+    static DECODING: RefCell<Option<Interner>> = const { RefCell::new(None) };
+}
+
+/// Hands out one shared `Name` for all the occurrences of a string.
+#[derive(Default)]
+pub(crate) struct Interner(HashSet<Name>);
+
+impl Interner {
+    pub(crate) fn intern(&mut self, name: &str) -> Name {
+        match self.0.get(name) {
+            Some(name) => name.clone(),
+            None => {
+                let name = Name::new(name);
+                self.0.insert(name.clone());
+                name
+            }
+        }
+    }
+
+    /// Like `intern`, but hands out `name` itself for a string first seen
+    /// there, so that names already shared aren't allocated again.
+    pub(crate) fn share(&mut self, name: &Name) -> Name {
+        match self.0.get(name.as_str()) {
+            Some(name) => name.clone(),
+            None => {
+                self.0.insert(name.clone());
+                name.clone()
+            }
+        }
+    }
+
+    /// Runs `decode` with every `Name` it deserializes interned, as
+    /// deserializing otherwise allocates each occurrence of a name apart.
+    pub(crate) fn decode<A>(decode: impl FnOnce() -> A) -> A {
+        // Restores the interner of an outer `decode` even if this one panics.
+        struct Restore(Option<Interner>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                DECODING.set(self.0.take());
+            }
+        }
+
+        let _restore = Restore(DECODING.replace(Some(Interner::default())));
+        decode()
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// Line and column of an element in the document a Blueprint was read from.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pos {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Pos {
+    pub fn new(line: usize, column: usize) -> Self {
+        Pos { line, column }
+    }
+
+    /// Renders the position as `file:line:col`, falling back to `line:col`
+    /// when the source document has no name.
+    pub fn in_source(&self, source: Option<&str>) -> String {
+        match source {
+            Some(source) => format!("{}:{}", source, self),
+            None => self.to_string(),
+        }
+    }
+}
+
+impl Display for Pos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl From<async_graphql_parser::Pos> for Pos {
+    fn from(pos: async_graphql_parser::Pos) -> Self {
+        Pos::new(pos.line, pos.column)
+    }
+}
@@ -0,0 +1,677 @@
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+
+use serde_json::Value;
+
+use crate::{
+    Blueprint, Definition, Directive, DirectiveDefinition, DirectiveLocation, FieldDefinition,
+    InputFieldDefinition, Selection, SelectionSet, Type,
+};
+
+/// Prints the Blueprint back as a supergraph document.
+impl Display for Blueprint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let printer = Printer { blueprint: self };
+        let blocks = printer
+            .schema()
+            .into_iter()
+            .chain(
+                self.directives
+                    .iter()
+                    .map(|definition| printer.directive_definition(definition)),
+            )
+            .chain(
+                self.definitions
+                    .iter()
+                    .map(|definition| printer.definition(definition)),
+            )
+            .collect::<Vec<_>>();
+
+        writeln!(f, "{}", blocks.join("\n\n"))
+    }
+}
+
+/// Prints a field set the way `@key` and `@requires` take it, `id organization
+/// { id }`.
+impl Display for SelectionSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let selections = self
+            .0
+            .iter()
+            .map(|selection| match selection {
+                Selection::Field(field) => {
+                    let mut printed = match &field.alias {
+                        Some(alias) => format!("{}: {}", alias, field.name),
+                        None => field.name.clone(),
+                    };
+                    if !field.arguments.is_empty() {
+                        let arguments = field
+                            .arguments
+                            .iter()
+                            .map(|(name, value)| format!("{}: {}", name, value))
+                            .collect::<Vec<_>>();
+                        printed = format!("{}({})", printed, arguments.join(", "));
+                    }
+                    if !field.selections.0.is_empty() {
+                        printed = format!("{} {{ {} }}", printed, field.selections);
+                    }
+                    printed
+                }
+                Selection::InlineFragment(fragment) => match &fragment.type_condition {
+                    Some(type_condition) => {
+                        format!("... on {} {{ {} }}", type_condition, fragment.selections)
+                    }
+                    None => format!("... {{ {} }}", fragment.selections),
+                },
+            })
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", selections.join(" "))
+    }
+}
+
+// Directive arguments and default values are kept as JSON, the definitions
+// they are checked against tell enum values apart from strings.
+struct Printer<'a> {
+    blueprint: &'a Blueprint,
+}
+
+impl Printer<'_> {
+    fn schema(&self) -> Option<String> {
+        let schema = &self.blueprint.schema;
+        let roots = [
+            ("query", &schema.query),
+            ("mutation", &schema.mutation),
+            ("subscription", &schema.subscription),
+        ]
+        .into_iter()
+        .filter_map(|(operation, root)| {
+            root.as_ref()
+                .map(|root| format!("  {}: {}", operation, root))
+        })
+        .collect::<Vec<_>>();
+
+        match (roots.is_empty(), schema.directives.is_empty()) {
+            (true, true) => None,
+            (true, false) => Some(format!(
+                "extend schema{}",
+                self.directives(&schema.directives)
+            )),
+            (false, _) => Some(format!(
+                "schema{} {{\n{}\n}}",
+                self.directives(&schema.directives),
+                roots.join("\n")
+            )),
+        }
+    }
+
+    fn directive_definition(&self, definition: &DirectiveDefinition) -> String {
+        let locations = definition
+            .locations
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        format!(
+            "{}directive @{}{}{} on {}",
+            description(&definition.description, ""),
+            definition.name,
+            self.arguments(&definition.arguments, ""),
+            if definition.repeatable {
+                " repeatable"
+            } else {
+                ""
+            },
+            locations.join(" | ")
+        )
+    }
+
+    fn definition(&self, definition: &Definition) -> String {
+        match definition {
+            Definition::Object(def) => format!(
+                "{}type {}{}{}{}",
+                description(&def.description, ""),
+                def.name,
+                implements(&def.implements),
+                self.directives(&def.directives),
+                self.fields(&def.fields)
+            ),
+            Definition::Interface(def) => format!(
+                "{}interface {}{}{}{}",
+                description(&def.description, ""),
+                def.name,
+                implements(&def.implements),
+                self.directives(&def.directives),
+                self.fields(&def.fields)
+            ),
+            Definition::InputObject(def) => {
+                let fields = def
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        format!(
+                            "{}  {}",
+                            description(&field.description, "  "),
+                            self.input_value(field)
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                format!(
+                    "{}input {}{}{}",
+                    description(&def.description, ""),
+                    def.name,
+                    self.directives(&def.directives),
+                    block(fields)
+                )
+            }
+            Definition::Scalar(def) => format!(
+                "{}scalar {}{}",
+                description(&def.description, ""),
+                def.name,
+                self.directives(&def.directives)
+            ),
+            Definition::Enum(def) => {
+                let values = def
+                    .enum_values
+                    .iter()
+                    .map(|value| {
+                        format!(
+                            "{}  {}{}",
+                            description(&value.description, "  "),
+                            value.name,
+                            self.directives(&value.directives)
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                format!(
+                    "{}enum {}{}{}",
+                    description(&def.description, ""),
+                    def.name,
+                    self.directives(&def.directives),
+                    block(values)
+                )
+            }
+            Definition::Union(def) => {
+                let members = if def.types.is_empty() {
+                    String::new()
+                } else {
+                    let types = def.types.iter().cloned().collect::<Vec<_>>();
+                    format!(" = {}", types.join(" | "))
+                };
+
+                format!(
+                    "{}union {}{}{}",
+                    description(&def.description, ""),
+                    def.name,
+                    self.directives(&def.directives),
+                    members
+                )
+            }
+        }
+    }
+
+    fn fields(&self, fields: &[FieldDefinition]) -> String {
+        block(
+            fields
+                .iter()
+                .map(|field| {
+                    format!(
+                        "{}  {}{}: {}{}",
+                        description(&field.description, "  "),
+                        field.name,
+                        self.arguments(&field.args, "  "),
+                        field.of_type,
+                        self.directives(&field.directives)
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    // Arguments go on their own lines once one of them has a description.
+    fn arguments(&self, arguments: &[InputFieldDefinition], indent: &str) -> String {
+        if arguments.is_empty() {
+            return String::new();
+        }
+
+        if arguments
+            .iter()
+            .all(|argument| argument.description.is_none())
+        {
+            let arguments = arguments
+                .iter()
+                .map(|argument| self.input_value(argument))
+                .collect::<Vec<_>>();
+            return format!("({})", arguments.join(", "));
+        }
+
+        let nested = format!("{}  ", indent);
+        let arguments = arguments
+            .iter()
+            .map(|argument| {
+                format!(
+                    "{}{}{}",
+                    description(&argument.description, &nested),
+                    nested,
+                    self.input_value(argument)
+                )
+            })
+            .collect::<Vec<_>>();
+
+        format!("(\n{}\n{})", arguments.join("\n"), indent)
+    }
+
+    fn input_value(&self, input: &InputFieldDefinition) -> String {
+        let default_value = match &input.default_value {
+            Some(value) => format!(" = {}", self.value(value, Some(&input.of_type))),
+            None => String::new(),
+        };
+
+        format!(
+            "{}: {}{}{}",
+            input.name,
+            input.of_type,
+            default_value,
+            self.directives(&input.directives)
+        )
+    }
+
+    fn directives(&self, directives: &[Directive]) -> String {
+        directives
+            .iter()
+            .map(|directive| format!(" {}", self.directive(directive)))
+            .collect()
+    }
+
+    fn directive(&self, directive: &Directive) -> String {
+        let definition = self
+            .blueprint
+            .directives
+            .iter()
+            .find(|definition| definition.name == directive.name);
+
+        let arguments = match &directive.arguments {
+            Value::Object(arguments) if !arguments.is_empty() => arguments
+                .iter()
+                .map(|(name, value)| {
+                    let of_type = definition
+                        .and_then(|definition| {
+                            definition
+                                .arguments
+                                .iter()
+                                .find(|argument| &argument.name == name)
+                        })
+                        .map(|argument| &argument.of_type);
+                    format!("{}: {}", name, self.value(value, of_type))
+                })
+                .collect::<Vec<_>>(),
+            _ => return format!("@{}", directive.name),
+        };
+
+        format!("@{}({})", directive.name, arguments.join(", "))
+    }
+
+    fn value(&self, value: &Value, of_type: Option<&Type>) -> String {
+        match value {
+            Value::Null | Value::Bool(_) | Value::Number(_) => value.to_string(),
+            Value::String(string) => match of_type {
+                Some(Type::Named { name, .. }) if self.is_enum(name) => string.clone(),
+                _ => value.to_string(),
+            },
+            Value::Array(items) => {
+                let of_type = match of_type {
+                    Some(Type::List { of_type, .. }) => Some(of_type.as_ref()),
+                    of_type => of_type,
+                };
+                let items = items
+                    .iter()
+                    .map(|item| self.value(item, of_type))
+                    .collect::<Vec<_>>();
+                format!("[{}]", items.join(", "))
+            }
+            Value::Object(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(name, value)| {
+                        format!(
+                            "{}: {}",
+                            name,
+                            self.value(value, self.field_type(of_type, name))
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                format!("{{{}}}", fields.join(", "))
+            }
+        }
+    }
+
+    fn is_enum(&self, name: &str) -> bool {
+        self.blueprint.definitions.iter().any(
+            |definition| matches!(definition.as_ref(), Definition::Enum(def) if def.name == name),
+        )
+    }
+
+    // The type of a field of an input object value.
+    fn field_type(&self, of_type: Option<&Type>, field: &str) -> Option<&Type> {
+        let Some(Type::Named { name, .. }) = of_type else {
+            return None;
+        };
+
+        self.blueprint
+            .definitions
+            .iter()
+            .find_map(|definition| match definition.as_ref() {
+                Definition::InputObject(def) if &def.name == name => def
+                    .fields
+                    .iter()
+                    .find(|input| input.name == field)
+                    .map(|input| &input.of_type),
+                _ => None,
+            })
+    }
+}
+
+/// Prints a default value the way it is written in a document, `[CM, IN]`.
+pub(crate) fn print_value(blueprint: &Blueprint, value: &Value, of_type: &Type) -> String {
+    Printer { blueprint }.value(value, Some(of_type))
+}
+
+fn implements(interfaces: &BTreeSet<String>) -> String {
+    if interfaces.is_empty() {
+        String::new()
+    } else {
+        let interfaces = interfaces.iter().cloned().collect::<Vec<_>>();
+        format!(" implements {}", interfaces.join(" & "))
+    }
+}
+
+/// Prints a directive location the way it is written in a document,
+/// `FIELD_DEFINITION`.
+impl Display for DirectiveLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DirectiveLocation::Query => "QUERY",
+            DirectiveLocation::Mutation => "MUTATION",
+            DirectiveLocation::Subscription => "SUBSCRIPTION",
+            DirectiveLocation::Field => "FIELD",
+            DirectiveLocation::FragmentDefinition => "FRAGMENT_DEFINITION",
+            DirectiveLocation::FragmentSpread => "FRAGMENT_SPREAD",
+            DirectiveLocation::InlineFragment => "INLINE_FRAGMENT",
+            DirectiveLocation::VariableDefinition => "VARIABLE_DEFINITION",
+            DirectiveLocation::Schema => "SCHEMA",
+            DirectiveLocation::Scalar => "SCALAR",
+            DirectiveLocation::Object => "OBJECT",
+            DirectiveLocation::FieldDefinition => "FIELD_DEFINITION",
+            DirectiveLocation::ArgumentDefinition => "ARGUMENT_DEFINITION",
+            DirectiveLocation::Interface => "INTERFACE",
+            DirectiveLocation::Union => "UNION",
+            DirectiveLocation::Enum => "ENUM",
+            DirectiveLocation::EnumValue => "ENUM_VALUE",
+            DirectiveLocation::InputObject => "INPUT_OBJECT",
+            DirectiveLocation::InputFieldDefinition => "INPUT_FIELD_DEFINITION",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Prints a type the way it is written in a document, `[Int!]!`.
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Named { name, required } => {
+                write!(f, "{}{}", name, if *required { "!" } else { "" })
+            }
+            Type::List { of_type, non_null } => {
+                write!(f, "[{}]{}", of_type, if *non_null { "!" } else { "" })
+            }
+        }
+    }
+}
+
+fn block(lines: Vec<String>) -> String {
+    if lines.is_empty() {
+        String::new()
+    } else {
+        format!(" {{\n{}\n}}", lines.join("\n"))
+    }
+}
+
+// Descriptions are printed as block strings, unless reading one back would
+// change the text: block strings drop indentation and blank leading and
+// trailing lines.
+fn description(description: &Option<String>, indent: &str) -> String {
+    let Some(description) = description else {
+        return String::new();
+    };
+
+    let is_blank = |line: &str| line.chars().all(|c| c == ' ' || c == '\t');
+    let as_block = !description.starts_with(char::is_whitespace)
+        && !description.lines().last().is_none_or(is_blank)
+        && !description.ends_with('\n')
+        && !description.contains("\"\"\"")
+        && !description
+            .chars()
+            .any(|c| c.is_control() && c != '\n' && c != '\t');
+
+    if as_block {
+        let lines = description
+            .split('\n')
+            .map(|line| {
+                if line.is_empty() {
+                    String::new()
+                } else {
+                    format!("{}{}", indent, line)
+                }
+            })
+            .collect::<Vec<_>>();
+        format!("{}\"\"\"\n{}\n{}\"\"\"\n", indent, lines.join("\n"), indent)
+    } else {
+        format!("{}{}\n", indent, Value::from(description.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use proptest::sample::select;
+    use valid::Validator;
+
+    use super::*;
+
+    fn round_trip(graphql: &str) -> (Blueprint, Blueprint, String) {
+        let mut parsed = Blueprint::parse(graphql).to_result().unwrap();
+        let printed = parsed.to_string();
+        let mut reparsed = Blueprint::parse(&printed)
+            .to_result()
+            .unwrap_or_else(|error| panic!("{}\n{}", error, printed));
+
+        parsed.forget_positions();
+        reparsed.forget_positions();
+        (parsed, reparsed, printed)
+    }
+
+    fn assert_same(expected: &Blueprint, actual: &Blueprint) {
+        assert_eq!(
+            serde_json::to_value(expected).unwrap(),
+            serde_json::to_value(actual).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_print() {
+        let (parsed, reparsed, printed) = round_trip(include_str!("fixtures/router.graphql"));
+
+        assert_same(&parsed, &reparsed);
+        insta::assert_snapshot!(printed);
+    }
+
+    const PRELUDE: &str = r#"
+directive @join__type(graph: join__Graph!, key: join__FieldSet) repeatable on OBJECT | INTERFACE | UNION | ENUM | INPUT_OBJECT | SCALAR
+directive @join__field(graph: join__Graph, requires: join__FieldSet, external: Boolean) repeatable on FIELD_DEFINITION | INPUT_FIELD_DEFINITION
+directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+directive @meta(
+  "A note"
+  note: String = "\"quoted\"\n"
+  level: Level
+  tags: [String!]
+) repeatable on OBJECT | FIELD_DEFINITION | ARGUMENT_DEFINITION | ENUM_VALUE | QUERY
+scalar join__FieldSet
+enum join__Graph {
+  A @join__graph(name: "a", url: "http://localhost:4001")
+  B @join__graph(name: "b", url: "http://localhost:4002")
+}
+enum Level { LOW HIGH }
+"#;
+
+    fn string() -> impl Strategy<Value = String> {
+        "[ -~\n\té\u{1}]{0,12}"
+    }
+
+    fn description() -> impl Strategy<Value = String> {
+        prop::option::of(string()).prop_map(|description| match description {
+            Some(description) => format!("{}\n", Value::from(description)),
+            None => String::new(),
+        })
+    }
+
+    fn directive() -> impl Strategy<Value = String> {
+        prop_oneof![
+            Just(String::new()),
+            select(vec!["A", "B"]).prop_map(|graph| format!(" @join__type(graph: {})", graph)),
+            (
+                string(),
+                select(vec!["LOW", "HIGH"]),
+                prop::collection::vec(string(), 0..3)
+            )
+                .prop_map(|(note, level, tags)| format!(
+                    " @meta(note: {}, level: {}, tags: {})",
+                    Value::from(note),
+                    level,
+                    Value::from(tags)
+                )),
+        ]
+    }
+
+    fn type_of(names: Vec<&'static str>) -> impl Strategy<Value = String> {
+        (select(names), prop::collection::vec(any::<bool>(), 1..4)).prop_map(|(name, required)| {
+            let mut of_type = name.to_string();
+            for (depth, required) in required.into_iter().enumerate() {
+                if depth > 0 {
+                    of_type = format!("[{}]", of_type);
+                }
+                if required {
+                    of_type.push('!');
+                }
+            }
+            of_type
+        })
+    }
+
+    fn argument() -> impl Strategy<Value = String> {
+        let default_value = prop_oneof![
+            Just(("Int".to_string(), String::new())),
+            any::<i32>().prop_map(|value| ("Int".to_string(), format!(" = {}", value))),
+            string().prop_map(|value| ("String".to_string(), format!(" = {}", Value::from(value)))),
+            select(vec!["LOW", "HIGH"])
+                .prop_map(|value| ("Level".to_string(), format!(" = {}", value))),
+            prop::collection::vec(any::<bool>(), 0..3).prop_map(|value| (
+                "[Boolean]".to_string(),
+                format!(" = {}", Value::from(value))
+            )),
+            Just((
+                "Filter".to_string(),
+                " = {level: HIGH, names: [\"a\"]}".to_string()
+            )),
+        ];
+
+        (description(), default_value).prop_map(|(description, (of_type, default_value))| {
+            format!("{}arg: {}{}", description, of_type, default_value)
+        })
+    }
+
+    fn field(index: usize) -> impl Strategy<Value = String> {
+        (
+            description(),
+            prop::collection::vec(argument(), 0..3),
+            type_of(vec!["String", "Int", "Level", "Node", "Search"]),
+            directive(),
+            prop_oneof![
+                Just(String::new()),
+                Just(" @join__field(graph: B, external: true)".to_string()),
+                Just(" @join__field(graph: A, requires: \"id\")".to_string()),
+            ],
+        )
+            .prop_map(
+                move |(description, arguments, of_type, directive, join_field)| {
+                    let arguments = arguments
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, argument)| argument.replacen("arg", &format!("arg{}", i), 1))
+                        .collect::<Vec<_>>();
+                    let arguments = if arguments.is_empty() {
+                        String::new()
+                    } else {
+                        format!("({})", arguments.join(", "))
+                    };
+
+                    format!(
+                        "{}field{}{}: {}{}{}",
+                        description, index, arguments, of_type, directive, join_field
+                    )
+                },
+            )
+    }
+
+    fn supergraph() -> impl Strategy<Value = String> {
+        let fields = |count| {
+            prop::collection::vec(0..1usize, 1..count).prop_flat_map(|indices| {
+                indices
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, _)| field(index))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        (
+            description(),
+            directive(),
+            fields(5),
+            fields(3),
+            description(),
+            string(),
+        )
+            .prop_map(
+                |(description, directive, node, query, input_description, value_description)| {
+                    format!(
+                        "{}\nschema {{ query: Query }}\n{}type Node{} {{\nid: ID\n{}\n}}\n\
+                         type Query {{\nid: ID\n{}\n}}\n\
+                         union Search = Node | Query\n\
+                         {}input Filter {{ level: Level names: [String] = [] }}\n\
+                         enum Kind {{ {}\nFIRST @meta(level: LOW) }}\n",
+                        PRELUDE,
+                        description,
+                        directive,
+                        node.join("\n"),
+                        query.join("\n"),
+                        input_description,
+                        Value::from(value_description)
+                    )
+                },
+            )
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(128))]
+
+        #[test]
+        fn test_print_round_trip(graphql in supergraph()) {
+            let (parsed, reparsed, _) = round_trip(&graphql);
+
+            assert_same(&parsed, &reparsed);
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use valid::{Valid, Validator};
+
+use crate::{
+    Blueprint, Definition, FieldDefinition, Graph, GraphView, Index, Resolution, Selection,
+    SelectionSet,
+};
+
+impl Blueprint {
+    /// Checks that every field clients can select is resolved by some graph
+    /// along every path from the query, mutation and subscription roots,
+    /// through the keys of the entities and the fields each graph
+    /// `@requires`. Fails with an example query for each field that can't
+    /// be, naming every selection it is missing a `@requires` for.
+    pub fn check_satisfiability(&self) -> Valid<(), String> {
+        self.to_api_schema()
+            .and_then(|api| Satisfiability { index: self.to_index(), api: api.to_index() }.check())
+    }
+}
+
+struct Satisfiability {
+    index: Index,
+    api: Index,
+}
+
+// A graph the planner fetched the current value from, with what the field
+// that got it `@provides` at this position.
+#[derive(Clone)]
+struct State {
+    graph: Graph,
+    provided: Option<SelectionSet>,
+}
+
+impl State {
+    fn new(graph: Graph) -> Self {
+        State { graph, provided: None }
+    }
+
+    fn id(&self) -> (Graph, String) {
+        let provided = self.provided.as_ref().map(ToString::to_string);
+        (self.graph.clone(), provided.unwrap_or_default())
+    }
+}
+
+// Why a graph can't resolve a field from a state.
+enum Failure {
+    Missing,
+    Requires(SelectionSet),
+}
+
+struct Path {
+    type_name: String,
+    states: Vec<State>,
+    segments: Vec<String>,
+}
+
+impl Satisfiability {
+    fn check(&self) -> Valid<(), String> {
+        let mut queue = VecDeque::new();
+        for (operation, root) in [
+            ("query", self.api.get_query()),
+            ("mutation", self.api.get_mutation()),
+            ("subscription", self.api.get_subscription()),
+        ] {
+            let Some(root) = root else { continue };
+            let states = self
+                .index
+                .graphs()
+                .filter(|view| view.get_type(root).is_some())
+                .map(|view| State::new(view.graph.clone()))
+                .collect();
+            queue.push_back(Path {
+                type_name: root.to_string(),
+                states,
+                segments: vec![operation.to_string()],
+            });
+        }
+
+        let mut visited = BTreeSet::new();
+        let mut failures = BTreeMap::<(String, String), String>::new();
+        while let Some(path) = queue.pop_front() {
+            let mut id = path.states.iter().map(State::id).collect::<Vec<_>>();
+            id.sort();
+            if !visited.insert((path.type_name.clone(), id)) {
+                continue;
+            }
+
+            let states = self.jumps(&path.type_name, &path.states);
+            for field in self.fields(&path.type_name) {
+                let key = (path.type_name.clone(), field.name.to_string());
+                if failures.contains_key(&key) {
+                    continue;
+                }
+
+                let mut next = Vec::new();
+                let mut requires = BTreeSet::new();
+                for state in &states {
+                    match self.resolve(&path.type_name, &field.name, state, &mut Vec::new()) {
+                        Ok(state) => next.push(state),
+                        Err(Failure::Requires(selection)) => {
+                            requires.insert(format!("`{}`", selection));
+                        }
+                        Err(Failure::Missing) => {}
+                    }
+                }
+
+                let mut segments = path.segments.clone();
+                segments.push(field.name.to_string());
+                if next.is_empty() {
+                    let graphs = path
+                        .states
+                        .iter()
+                        .map(|state| state.graph.as_str())
+                        .collect::<BTreeSet<_>>();
+                    let reason = match requires.is_empty() {
+                        true => String::new(),
+                        false => format!(
+                            ", it requires {}",
+                            requires.into_iter().collect::<Vec<_>>().join(" or ")
+                        ),
+                    };
+                    failures.insert(
+                        key,
+                        format!(
+                            "`{}.{}` can not be resolved from {}{} in `{}`",
+                            path.type_name,
+                            field.name,
+                            graphs.into_iter().collect::<Vec<_>>().join(", "),
+                            reason,
+                            query(&segments)
+                        ),
+                    );
+                    continue;
+                }
+
+                self.enqueue(&mut queue, field.of_type.name(), next, segments);
+            }
+        }
+
+        Valid::from_iter(failures.into_values(), Valid::<(), _>::fail).unit()
+    }
+
+    // Follows a field to its type and, for abstract types, to the object
+    // types the graphs say it can be.
+    fn enqueue(
+        &self,
+        queue: &mut VecDeque<Path>,
+        type_name: &str,
+        states: Vec<State>,
+        segments: Vec<String>,
+    ) {
+        let Some(definition) = self.api.get_type(type_name) else {
+            return;
+        };
+        if !matches!(
+            definition.as_ref(),
+            Definition::Object(_) | Definition::Interface(_) | Definition::Union(_)
+        ) {
+            return;
+        }
+
+        for object in self.api.possible_types(type_name) {
+            let states = states
+                .iter()
+                .filter(|state| {
+                    self.index
+                        .possible_types_in(&state.graph, type_name)
+                        .contains(object)
+                })
+                .map(|state| State {
+                    graph: state.graph.clone(),
+                    provided: state
+                        .provided
+                        .as_ref()
+                        .map(|provided| on_type(provided, object)),
+                })
+                .collect::<Vec<_>>();
+            if !states.is_empty() {
+                let mut segments = segments.clone();
+                segments.push(format!("... on {}", object));
+                queue.push_back(Path { type_name: object.clone(), states, segments });
+            }
+        }
+
+        queue.push_back(Path { type_name: type_name.to_string(), states, segments });
+    }
+
+    fn fields(&self, type_name: &str) -> &[FieldDefinition] {
+        match self.api.get_type(type_name).map(AsRef::as_ref) {
+            Some(Definition::Object(def)) => &def.fields,
+            Some(Definition::Interface(def)) => &def.fields,
+            _ => &[],
+        }
+    }
+
+    fn view(&self, graph: &Graph) -> Option<&GraphView> {
+        self.index.graph(graph)
+    }
+
+    // The states reachable from `states` by fetching the entity from other
+    // graphs by its keys.
+    fn jumps(&self, type_name: &str, states: &[State]) -> Vec<State> {
+        let mut states = states.to_vec();
+        let mut graphs = states
+            .iter()
+            .map(|state| state.graph.clone())
+            .collect::<BTreeSet<_>>();
+
+        loop {
+            let jump = self.index.graphs().find(|view| {
+                !graphs.contains(&view.graph)
+                    && view.get_type(type_name).is_some_and(|graph_type| {
+                        graph_type.keys.iter().any(|key| {
+                            key.resolvable
+                                && states
+                                    .iter()
+                                    .any(|state| self.supplies(state, type_name, &key.fields))
+                        })
+                    })
+            });
+            let Some(view) = jump else {
+                return states;
+            };
+
+            graphs.insert(view.graph.clone());
+            states.push(State::new(view.graph.clone()));
+        }
+    }
+
+    // Whether a graph can give the fields of a key without fetching from
+    // another graph.
+    fn supplies(&self, state: &State, type_name: &str, fields: &SelectionSet) -> bool {
+        let Some(graph_type) = self
+            .view(&state.graph)
+            .and_then(|view| view.get_type(type_name))
+        else {
+            return false;
+        };
+
+        fields.0.iter().all(|selection| match selection {
+            Selection::Field(field) => {
+                let Some(graph_field) = graph_type.get_field(&field.name) else {
+                    return false;
+                };
+                let held = graph_field.resolution == Resolution::Owned
+                    || graph_type.keys.iter().any(|key| {
+                        key.fields
+                            .fields()
+                            .any(|key_field| key_field.name == field.name)
+                    })
+                    || provides(state.provided.as_ref(), &field.name).is_some();
+
+                held && (field.selections.0.is_empty()
+                    || self.supplies(
+                        &State::new(state.graph.clone()),
+                        graph_field.of_type.name(),
+                        &field.selections,
+                    ))
+            }
+            Selection::InlineFragment(fragment) => self.supplies(
+                state,
+                fragment.type_condition.as_deref().unwrap_or(type_name),
+                &fragment.selections,
+            ),
+        })
+    }
+
+    // Resolves a field from the graph of `state`, giving the state its value
+    // is fetched in.
+    fn resolve(
+        &self,
+        type_name: &str,
+        field_name: &str,
+        state: &State,
+        requiring: &mut Vec<(String, String, Graph)>,
+    ) -> Result<State, Failure> {
+        let graph_field = self
+            .view(&state.graph)
+            .and_then(|view| view.get_field(type_name, field_name))
+            .ok_or(Failure::Missing)?;
+
+        if let Some(provided) = provides(state.provided.as_ref(), field_name) {
+            return Ok(State { graph: state.graph.clone(), provided });
+        }
+        if graph_field.resolution != Resolution::Owned {
+            return Err(Failure::Missing);
+        }
+
+        if let Some(requires) = &graph_field.requires {
+            let id = (
+                type_name.to_string(),
+                field_name.to_string(),
+                state.graph.clone(),
+            );
+            if requiring.contains(&id) {
+                return Err(Failure::Requires(requires.clone()));
+            }
+
+            requiring.push(id);
+            let states = self.jumps(type_name, &[State::new(state.graph.clone())]);
+            let met = self.selects(type_name, requires, &states, requiring);
+            requiring.pop();
+
+            if !met {
+                return Err(Failure::Requires(requires.clone()));
+            }
+        }
+
+        Ok(State {
+            graph: state.graph.clone(),
+            provided: graph_field.provides.clone(),
+        })
+    }
+
+    // Whether the fields of a `@requires` resolve from some of `states`.
+    fn selects(
+        &self,
+        type_name: &str,
+        selections: &SelectionSet,
+        states: &[State],
+        requiring: &mut Vec<(String, String, Graph)>,
+    ) -> bool {
+        selections.0.iter().all(|selection| match selection {
+            Selection::Field(field) => states.iter().any(|state| {
+                let Ok(next) = self.resolve(type_name, &field.name, state, requiring) else {
+                    return false;
+                };
+                if field.selections.0.is_empty() {
+                    return true;
+                }
+
+                let of_type = self
+                    .view(&state.graph)
+                    .and_then(|view| view.get_field(type_name, &field.name))
+                    .map(|graph_field| graph_field.of_type.name().to_string())
+                    .unwrap_or_default();
+                let states = self.jumps(&of_type, &[next]);
+                self.selects(&of_type, &field.selections, &states, requiring)
+            }),
+            Selection::InlineFragment(fragment) => {
+                let type_condition = fragment.type_condition.as_deref().unwrap_or(type_name);
+                let states = self.jumps(type_condition, states);
+                self.selects(type_condition, &fragment.selections, &states, requiring)
+            }
+        })
+    }
+}
+
+// What a `@provides` gives of a field, `Some` with the selections under it
+// when it gives the field.
+fn provides(provided: Option<&SelectionSet>, field_name: &str) -> Option<Option<SelectionSet>> {
+    let field = provided?.fields().find(|field| field.name == field_name)?;
+
+    if field.selections.0.is_empty() {
+        Some(None)
+    } else {
+        Some(Some(field.selections.clone()))
+    }
+}
+
+// The part of a `@provides` that applies to values of `object`.
+fn on_type(provided: &SelectionSet, object: &str) -> SelectionSet {
+    SelectionSet(
+        provided
+            .0
+            .iter()
+            .flat_map(|selection| match selection {
+                Selection::Field(_) => vec![selection.clone()],
+                Selection::InlineFragment(fragment) => match fragment.type_condition.as_deref() {
+                    Some(type_condition) if type_condition != object => Vec::new(),
+                    _ => fragment.selections.0.clone(),
+                },
+            })
+            .collect(),
+    )
+}
+
+// An example operation selecting down a path, `query { me { name } }`.
+fn query(segments: &[String]) -> String {
+    let (operation, fields) = segments
+        .split_first()
+        .expect("a path starts at its operation");
+    let selection =
+        fields
+            .iter()
+            .rev()
+            .fold(String::new(), |inner, segment| match inner.is_empty() {
+                true => segment.clone(),
+                false => format!("{} {{ {} }}", segment, inner),
+            });
+
+    format!("{} {{ {} }}", operation, selection)
+}
+
+#[cfg(test)]
+mod tests {
+    use valid::Validator;
+
+    use crate::Blueprint;
+
+    const SUPERGRAPH: &str = r#"
+schema
+  @link(url: "https://specs.apollo.dev/link/v1.0")
+  @link(url: "https://specs.apollo.dev/join/v0.3", for: EXECUTION) {
+  query: Query
+  subscription: Subscription
+}
+
+directive @join__type(graph: join__Graph!, key: join__FieldSet, resolvable: Boolean = true) repeatable on OBJECT | INTERFACE
+directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet, external: Boolean) repeatable on FIELD_DEFINITION
+
+scalar join__FieldSet
+
+enum join__Graph {
+  A @join__graph(name: "a", url: "http://localhost:4001")
+  B @join__graph(name: "b", url: "http://localhost:4002")
+  C @join__graph(name: "c", url: "http://localhost:4003")
+}
+
+type Query @join__type(graph: A) @join__type(graph: B) {
+  users: [User] @join__field(graph: A)
+  reviews: [Review] @join__field(graph: B)
+}
+
+type User @join__type(graph: A, key: "id") @join__type(graph: B, key: "id", resolvable: false) {
+  id: ID!
+  name: String @join__field(graph: A)
+}
+
+type Review @join__type(graph: B, key: "id") @join__type(graph: C, key: "id", resolvable: false) {
+  id: ID!
+  author: User @join__field(graph: B)
+  product: Product @join__field(graph: B, provides: "name")
+  score: Int @join__field(graph: C)
+}
+
+type Product @join__type(graph: B, key: "upc") @join__type(graph: C, key: "upc", resolvable: false) {
+  upc: String!
+  name: String @join__field(graph: B, external: true) @join__field(graph: C)
+  weight: Int @join__field(graph: B, external: true)
+  price: Int @join__field(graph: B)
+  shipping: Int @join__field(graph: B, requires: "weight")
+}
+
+type Subscription @join__type(graph: B) {
+  votes: Vote @join__field(graph: B)
+}
+
+type Vote @join__type(graph: B) @join__type(graph: C) {
+  id: ID!
+  count: Int @join__field(graph: C)
+}
+"#;
+
+    #[test]
+    fn test_satisfiable() {
+        Blueprint::parse(include_str!("fixtures/router.graphql"))
+            .and_then(|blueprint| blueprint.check_satisfiability())
+            .to_result()
+            .unwrap_or_else(|error| panic!("{}", error));
+    }
+
+    #[test]
+    fn test_satisfiability_errors() {
+        let errors = Blueprint::parse(SUPERGRAPH)
+            .and_then(|blueprint| blueprint.check_satisfiability())
+            .to_result()
+            .unwrap_err();
+
+        insta::assert_snapshot!(errors);
+    }
+}
@@ -0,0 +1,325 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use valid::{Valid, Validator};
+
+use crate::name::Interner;
+use crate::{Blueprint, Index};
+
+// A snapshot is a fixed header followed by the value encoded as CBOR. Loading
+// one still deserializes the value, it only skips parsing, validating and
+// indexing the SDL. Its names are interned as they are read, so that they are
+// shared as in a value built from the SDL.
+const MAGIC: &[u8; 4] = b"BPSN";
+
+/// Version of the snapshot format, to be bumped whenever the layout of the
+/// Blueprint or the Index changes so that older snapshots are rebuilt.
+pub const SNAPSHOT_VERSION: u16 = 6;
+
+// Magic, version, kind and the fingerprint of the source and the SDL.
+const HEADER_LEN: usize = 4 + 2 + 1 + 8;
+
+/// What a snapshot holds, so that a Blueprint isn't read as an Index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum Kind {
+    Blueprint = 0,
+    Index = 1,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Blueprint => "a Blueprint",
+            Kind::Index => "an Index",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Header {
+    version: u16,
+    kind: u8,
+    fingerprint: u64,
+}
+
+impl Header {
+    fn new(kind: Kind, source: Option<&str>, sdl: &str) -> Self {
+        Header {
+            version: SNAPSHOT_VERSION,
+            kind: kind as u8,
+            fingerprint: fingerprint(source, sdl),
+        }
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.push(self.kind);
+        bytes.extend_from_slice(&self.fingerprint.to_le_bytes());
+    }
+
+    fn read(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return None;
+        }
+
+        Some(Header {
+            version: u16::from_le_bytes(bytes[4..6].try_into().ok()?),
+            kind: bytes[6],
+            fingerprint: u64::from_le_bytes(bytes[7..15].try_into().ok()?),
+        })
+    }
+}
+
+// FNV-1a, which unlike the std hashers is stable across builds. The source is
+// taken as it ends up in the positions of the value. It is ended by a byte that
+// is never part of UTF-8, so that no other source and SDL hash the same bytes.
+fn fingerprint(source: Option<&str>, sdl: &str) -> u64 {
+    let source = source.unwrap_or_default().bytes();
+    source
+        .chain([0xff])
+        .chain(sdl.bytes())
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+fn encode<A: Serialize>(value: &A, kind: Kind, source: Option<&str>, sdl: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    Header::new(kind, source, sdl).write(&mut bytes);
+    ciborium::into_writer(value, &mut bytes).expect("writing to a Vec can not fail");
+    bytes
+}
+
+fn decode<A: DeserializeOwned>(
+    bytes: &[u8],
+    kind: Kind,
+    source: Option<&str>,
+    sdl: &str,
+) -> Valid<A, String> {
+    let Some(header) = Header::read(bytes) else {
+        return Valid::fail("Not a snapshot".to_string());
+    };
+
+    if header.version != SNAPSHOT_VERSION {
+        return Valid::fail(format!(
+            "Snapshot version `{}` is stale, the current version is `{}`",
+            header.version, SNAPSHOT_VERSION
+        ));
+    }
+    if header.kind != kind as u8 {
+        return Valid::fail(format!("Snapshot does not hold {}", kind.name()));
+    }
+    if header.fingerprint != fingerprint(source, sdl) {
+        return Valid::fail("Snapshot was taken of a different supergraph".to_string());
+    }
+
+    match Interner::decode(|| ciborium::from_reader(&bytes[HEADER_LEN..])) {
+        Ok(value) => Valid::succeed(value),
+        Err(error) => Valid::fail(format!("Snapshot is corrupt: {}", error)),
+    }
+}
+
+// Reads the snapshot at `path`, or builds the value and writes its snapshot
+// when there is none or it is rejected. Failing to write only costs the next
+// start its speed, so it is not an error.
+fn load<A: Serialize + DeserializeOwned>(
+    path: &Path,
+    kind: Kind,
+    source: &str,
+    sdl: &str,
+    build: impl FnOnce() -> Valid<A, String>,
+) -> Valid<A, String> {
+    if let Ok(bytes) = fs::read(path) {
+        if let Ok(value) = decode(&bytes, kind, Some(source), sdl).to_result() {
+            return Valid::succeed(value);
+        }
+    }
+
+    build().map(|value| {
+        let _ = write(path, &encode(&value, kind, Some(source), sdl));
+        value
+    })
+}
+
+// Writes through a temporary file so that readers never see half a snapshot.
+fn write(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    File::create(&tmp)?.write_all(bytes)?;
+    fs::rename(tmp, path)
+}
+
+impl Blueprint {
+    /// A binary snapshot of the Blueprint read from `sdl`, to be read back
+    /// with [`Blueprint::from_snapshot`] instead of parsing the SDL again.
+    pub fn to_snapshot(&self, sdl: &str) -> Vec<u8> {
+        encode(self, Kind::Blueprint, self.source.as_deref(), sdl)
+    }
+
+    /// Reads a snapshot, rejecting it if it was taken by another version of
+    /// the format or of another source and SDL than `source` and `sdl`.
+    pub fn from_snapshot(
+        bytes: &[u8],
+        source: Option<&str>,
+        sdl: &str,
+    ) -> Valid<Blueprint, String> {
+        decode(bytes, Kind::Blueprint, source, sdl)
+    }
+
+    /// Reads the Blueprint from the snapshot at `path`, or parses `schema`
+    /// and snapshots it there when the snapshot is missing or stale.
+    pub fn load(path: impl AsRef<Path>, source: &str, schema: &str) -> Valid<Blueprint, String> {
+        load(path.as_ref(), Kind::Blueprint, source, schema, || {
+            Blueprint::parse_source(source, schema)
+        })
+    }
+}
+
+impl Index {
+    /// A binary snapshot of the Index built from `sdl`, to be read back with
+    /// [`Index::from_snapshot`] instead of parsing and indexing the SDL again.
+    pub fn to_snapshot(&self, sdl: &str) -> Vec<u8> {
+        encode(self, Kind::Index, self.source(), sdl)
+    }
+
+    /// Reads a snapshot, rejecting it if it was taken by another version of
+    /// the format or of another source and SDL than `source` and `sdl`.
+    pub fn from_snapshot(bytes: &[u8], source: Option<&str>, sdl: &str) -> Valid<Index, String> {
+        decode(bytes, Kind::Index, source, sdl)
+    }
+
+    /// Reads the Index from the snapshot at `path`, or builds it from
+    /// `schema` and snapshots it there when the snapshot is missing or stale.
+    pub fn load(path: impl AsRef<Path>, source: &str, schema: &str) -> Valid<Index, String> {
+        load(path.as_ref(), Kind::Index, source, schema, || {
+            Blueprint::parse_source(source, schema).map(|blueprint| blueprint.to_index())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QueryField;
+
+    const SDL: &str = include_str!("fixtures/router.graphql");
+
+    fn blueprint() -> Blueprint {
+        Blueprint::parse(SDL).to_result().unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let blueprint = blueprint();
+        let bytes = blueprint.to_snapshot(SDL);
+        let actual = Blueprint::from_snapshot(&bytes, None, SDL)
+            .to_result()
+            .unwrap();
+        assert_eq!(actual.to_string(), blueprint.to_string());
+
+        let index = blueprint.to_index();
+        let bytes = index.to_snapshot(SDL);
+        let actual = Index::from_snapshot(&bytes, None, SDL).to_result().unwrap();
+        assert_eq!(format!("{:?}", actual), format!("{:?}", index));
+    }
+
+    #[test]
+    fn test_snapshot_shares_names() {
+        let index = blueprint().to_index();
+        let index = Index::from_snapshot(&index.to_snapshot(SDL), None, SDL)
+            .to_result()
+            .unwrap();
+
+        // The name of a type is the one its definition and its fields hold.
+        let name = index.type_name("Product").unwrap();
+        assert!(std::ptr::eq(
+            name.as_str(),
+            index.get_type("Product").unwrap().name()
+        ));
+        let Some(QueryField::Field(field)) = index.get_field("Query", "topProducts") else {
+            panic!("expected `Query.topProducts`")
+        };
+        assert!(std::ptr::eq(name.as_str(), field.type_name().as_str()));
+    }
+
+    #[test]
+    fn test_snapshot_rejected() {
+        let blueprint = blueprint();
+        let reject = |bytes: &[u8], source: Option<&str>, sdl: &str| {
+            Index::from_snapshot(bytes, source, sdl)
+                .to_result()
+                .unwrap_err()
+                .to_string()
+        };
+
+        let mut stale = blueprint.to_index().to_snapshot(SDL);
+        stale[4..6].copy_from_slice(&0u16.to_le_bytes());
+        let mut corrupt = blueprint.to_index().to_snapshot(SDL);
+        corrupt.truncate(HEADER_LEN + 8);
+
+        let errors = [
+            reject(b"type Query", None, SDL),
+            reject(&stale, None, SDL),
+            reject(&blueprint.to_snapshot(SDL), None, SDL),
+            reject(
+                &blueprint.to_index().to_snapshot(SDL),
+                None,
+                "type Query { a: Int }",
+            ),
+            reject(
+                &blueprint.to_index().to_snapshot(SDL),
+                Some("router.graphql"),
+                SDL,
+            ),
+            reject(&corrupt, None, SDL),
+        ];
+
+        insta::assert_snapshot!(errors.join("\n"));
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("router.index");
+
+        let built = Index::load(&path, "router.graphql", SDL)
+            .to_result()
+            .unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert!(Index::from_snapshot(&bytes, Some("router.graphql"), SDL)
+            .to_result()
+            .is_ok());
+
+        let loaded = Index::load(&path, "router.graphql", SDL)
+            .to_result()
+            .unwrap();
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", built));
+
+        // A changed supergraph replaces the snapshot.
+        let sdl = SDL.replace("topProducts", "bestProducts");
+        let rebuilt = Index::load(&path, "router.graphql", &sdl)
+            .to_result()
+            .unwrap();
+        assert!(rebuilt.get_field("Query", "bestProducts").is_some());
+        let bytes = fs::read(&path).unwrap();
+        assert!(Index::from_snapshot(&bytes, Some("router.graphql"), &sdl)
+            .to_result()
+            .is_ok());
+
+        // So does a supergraph read from another source.
+        let moved = Index::load(&path, "moved.graphql", &sdl)
+            .to_result()
+            .unwrap();
+        assert_eq!(
+            moved.location("Query", Some("bestProducts")).unwrap(),
+            rebuilt
+                .location("Query", Some("bestProducts"))
+                .unwrap()
+                .replace("router.graphql", "moved.graphql")
+        );
+    }
+}
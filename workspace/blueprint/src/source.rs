@@ -0,0 +1,517 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, PoisonError, RwLock};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread::{self, JoinHandle};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+use valid::{Valid, Validator};
+
+use crate::{Blueprint, Index, Introspection, Subgraph};
+
+/// Where the router reads its supergraph from. A source is polled for
+/// changes, and only yields a Blueprint when what it reads has changed since
+/// the last poll.
+pub trait Source: Send {
+    /// Reads the source, building a new Blueprint if it has changed since the
+    /// last poll and `None` if it hasn't.
+    fn poll(&mut self) -> Valid<Option<Blueprint>, String>;
+}
+
+/// A supergraph in a single file.
+pub struct FileSource {
+    path: PathBuf,
+    last: Option<String>,
+}
+
+impl FileSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileSource { path: path.into(), last: None }
+    }
+}
+
+impl Source for FileSource {
+    fn poll(&mut self) -> Valid<Option<Blueprint>, String> {
+        let source = self.path.display().to_string();
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) if self.last.as_ref() == Some(&text) => Valid::succeed(None),
+            Ok(text) => {
+                let blueprint = Blueprint::parse_source(&source, &text).some();
+                self.last = Some(text);
+                blueprint
+            }
+            Err(error) => Valid::fail(format!("couldn't read `{}`: {}", source, error)),
+        }
+    }
+}
+
+/// A directory of subgraph schemas, one `<name>.graphql` file per subgraph,
+/// composed into a supergraph. The url of each subgraph is configured with
+/// [DirectorySource::url].
+pub struct DirectorySource {
+    path: PathBuf,
+    urls: BTreeMap<String, String>,
+    last: Option<BTreeMap<String, String>>,
+}
+
+impl DirectorySource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        DirectorySource { path: path.into(), urls: BTreeMap::new(), last: None }
+    }
+
+    /// Sets the url the router sends the fetches of subgraph `name` to.
+    pub fn url(mut self, name: impl AsRef<str>, url: impl AsRef<str>) -> Self {
+        self.urls
+            .insert(name.as_ref().to_string(), url.as_ref().to_string());
+        // The next poll composes again, even if no schema has changed.
+        self.last = None;
+        self
+    }
+
+    fn read(&self) -> std::io::Result<BTreeMap<String, String>> {
+        let mut schemas = BTreeMap::new();
+        for entry in std::fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "graphql")
+            {
+                if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                    schemas.insert(name.to_string(), std::fs::read_to_string(&path)?);
+                }
+            }
+        }
+        Ok(schemas)
+    }
+}
+
+impl Source for DirectorySource {
+    fn poll(&mut self) -> Valid<Option<Blueprint>, String> {
+        let schemas = match self.read() {
+            Ok(schemas) if self.last.as_ref() == Some(&schemas) => return Valid::succeed(None),
+            Ok(schemas) => schemas,
+            Err(error) => {
+                return Valid::fail(format!(
+                    "couldn't read `{}`: {}",
+                    self.path.display(),
+                    error
+                ))
+            }
+        };
+
+        let blueprint = Valid::from_iter(&schemas, |(name, sdl)| {
+            Valid::from_option(
+                self.urls.get(name),
+                format!("no url is configured for subgraph `{}`", name),
+            )
+            .map(|url| Subgraph::new(name, url, sdl))
+        })
+        .and_then(|subgraphs| {
+            if subgraphs.is_empty() {
+                Valid::fail(format!("no subgraph schemas in `{}`", self.path.display()))
+            } else {
+                Blueprint::compose(&subgraphs)
+            }
+        })
+        .some();
+        self.last = Some(schemas);
+        blueprint
+    }
+}
+
+/// A supergraph served over HTTP, e.g. by a schema registry, fetched with a
+/// GET on every poll.
+///
+/// Polling blocks on the request, so it must not happen on the threads of an
+/// async runtime. Not available on wasm, which can't block on a request.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct HttpSource {
+    url: String,
+    client: reqwest::blocking::Client,
+    last: Option<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HttpSource {
+    pub fn new(url: impl AsRef<str>) -> Self {
+        HttpSource {
+            url: url.as_ref().to_string(),
+            client: reqwest::blocking::Client::new(),
+            last: None,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Source for HttpSource {
+    fn poll(&mut self) -> Valid<Option<Blueprint>, String> {
+        let text = self
+            .client
+            .get(&self.url)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text());
+        match text {
+            Ok(text) if self.last.as_ref() == Some(&text) => Valid::succeed(None),
+            Ok(text) => {
+                let blueprint = Blueprint::parse_source(&self.url, &text).some();
+                self.last = Some(text);
+                blueprint
+            }
+            Err(error) => Valid::fail(format!("couldn't fetch `{}`: {}", self.url, error)),
+        }
+    }
+}
+
+/// A validated revision of the supergraph, with the indexes of the supergraph
+/// and of its API schema and the introspection of the latter, all built once
+/// when the revision is loaded.
+///
+/// `plans` is what else is kept for the revision and dropped along with it,
+/// as the `query_plan::PlanCache` of the plans made with its indexes.
+#[derive(Debug)]
+pub struct Revision<P = ()> {
+    /// Counts the revisions loaded, starting at 1.
+    pub number: usize,
+    pub blueprint: Blueprint,
+    pub index: Arc<Index>,
+    /// Index of the API schema, what clients can see of the supergraph.
+    pub api: Arc<Index>,
+    pub introspection: Introspection,
+    pub plans: P,
+}
+
+impl<P: Default> Revision<P> {
+    fn new(number: usize, blueprint: Blueprint) -> Valid<Self, String> {
+        blueprint
+            .check_satisfiability()
+            .and_then(|_| blueprint.to_api_schema())
+            .map(|api| Revision {
+                number,
+                index: Arc::new(blueprint.to_index()),
+                api: Arc::new(api.to_index()),
+                introspection: Introspection::new(api),
+                blueprint,
+                plans: P::default(),
+            })
+    }
+}
+
+/// The supergraph the router serves, replaced as its source changes.
+///
+/// Requests take the [Revision] current when they start and keep it until
+/// they finish, so a reload never changes the schema under a request in
+/// flight. A new revision is only swapped in once it is valid.
+#[derive(Debug)]
+pub struct Supergraph<P = ()> {
+    current: RwLock<Arc<Revision<P>>>,
+}
+
+impl<P: Default> Supergraph<P> {
+    /// Loads the first revision of the supergraph from `source`.
+    pub fn load(source: &mut dyn Source) -> Valid<Self, String> {
+        source
+            .poll()
+            .and_then(|blueprint| {
+                Valid::from_option(blueprint, "the source has no supergraph".to_string())
+            })
+            .and_then(|blueprint| Revision::new(1, blueprint))
+            .map(|revision| Supergraph { current: RwLock::new(Arc::new(revision)) })
+    }
+
+    /// The revision of the supergraph new requests are served with.
+    pub fn current(&self) -> Arc<Revision<P>> {
+        // A revision is swapped in whole, so a panic while holding the lock
+        // can't leave one half written.
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Polls `source` and swaps in the new revision if it has changed and is
+    /// valid. Succeeds with whether a new revision was swapped in; on failure
+    /// the current revision stays.
+    pub fn reload(&self, source: &mut dyn Source) -> Valid<bool, String> {
+        source.poll().and_then(|blueprint| match blueprint {
+            None => Valid::succeed(false),
+            Some(blueprint) => {
+                Revision::new(0, blueprint).map(|mut revision| {
+                    // Numbered under the lock, so that concurrent reloads
+                    // don't both take the number after the current one.
+                    let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+                    revision.number = current.number + 1;
+                    *current = Arc::new(revision);
+                    true
+                })
+            }
+        })
+    }
+
+    /// Polls `source` every `interval` on a thread of its own, until the
+    /// returned [Watcher] is dropped. Errors are logged, and leave the current
+    /// revision in place. Not available on wasm, which has no threads.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn watch(self: &Arc<Self>, mut source: impl Source + 'static, interval: Duration) -> Watcher
+    where
+        P: Send + Sync + 'static,
+    {
+        let supergraph = self.clone();
+        let (stop, stopped) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                match supergraph.reload(&mut source).to_result() {
+                    Ok(true) => log::info!(
+                        "loaded revision {} of the supergraph",
+                        supergraph.current().number
+                    ),
+                    Ok(false) => {}
+                    Err(error) => log::error!(
+                        "keeping revision {} of the supergraph: {}",
+                        supergraph.current().number,
+                        error
+                    ),
+                }
+            }
+        });
+        Watcher { stop: Some(stop), thread: Some(thread) }
+    }
+}
+
+/// Reloads a [Supergraph] as its source changes, until dropped.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Watcher {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    use super::*;
+    use crate::Definition;
+
+    const ROUTER: &str = include_str!("fixtures/router.graphql");
+
+    fn query_fields<P>(revision: &Revision<P>) -> Vec<String> {
+        match revision
+            .index
+            .get_type("Query")
+            .map(|definition| definition.as_ref())
+        {
+            Some(Definition::Object(query)) => query
+                .fields
+                .iter()
+                .map(|field| field.name.to_string())
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn with_field(supergraph: &str) -> String {
+        supergraph.replace(
+            "  topProducts: [Product!]! @join__field(graph: PRODUCTS)\n",
+            "  topProducts: [Product!]! @join__field(graph: PRODUCTS)\n  \
+             bestProduct: Product @join__field(graph: PRODUCTS)\n",
+        )
+    }
+
+    // Yields the same supergraph at every poll, as a changed one.
+    struct Always;
+
+    impl Source for Always {
+        fn poll(&mut self) -> Valid<Option<Blueprint>, String> {
+            Blueprint::parse(ROUTER).some()
+        }
+    }
+
+    #[test]
+    fn test_concurrent_reloads() {
+        let supergraph: Supergraph = Supergraph::load(&mut Always).to_result().unwrap();
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| supergraph.reload(&mut Always).to_result().unwrap());
+            }
+        });
+
+        assert_eq!(supergraph.current().number, 5);
+    }
+
+    #[test]
+    fn test_poisoned() {
+        let supergraph: Supergraph = Supergraph::load(&mut Always).to_result().unwrap();
+
+        let _ = thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let _current = supergraph.current.write().unwrap();
+                    panic!("poisons the lock");
+                })
+                .join()
+        });
+
+        assert_eq!(supergraph.current().number, 1);
+        assert!(supergraph.reload(&mut Always).to_result().unwrap());
+        assert_eq!(supergraph.current().number, 2);
+    }
+
+    #[test]
+    fn test_file_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("supergraph.graphql");
+        std::fs::write(&path, ROUTER).unwrap();
+
+        let mut source = FileSource::new(&path);
+        let supergraph: Supergraph = Supergraph::load(&mut source).to_result().unwrap();
+        let in_flight = supergraph.current();
+        assert_eq!(in_flight.number, 1);
+        assert!(!supergraph.reload(&mut source).to_result().unwrap());
+
+        std::fs::write(&path, with_field(ROUTER)).unwrap();
+        assert!(supergraph.reload(&mut source).to_result().unwrap());
+
+        let current = supergraph.current();
+        assert_eq!(current.number, 2);
+        assert_eq!(query_fields(&current), ["me", "topProducts", "bestProduct"]);
+        assert_eq!(query_fields(&in_flight), ["me", "topProducts"]);
+    }
+
+    #[test]
+    fn test_invalid_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("supergraph.graphql");
+        std::fs::write(&path, ROUTER).unwrap();
+
+        let mut source = FileSource::new(&path);
+        let supergraph: Supergraph = Supergraph::load(&mut source).to_result().unwrap();
+
+        std::fs::write(
+            &path,
+            ROUTER.replace(
+                "me: User! @join__field(graph: ACCOUNTS)",
+                "me: User! @join__field(graph: ACCOUNTS, requires: \"id\")",
+            ),
+        )
+        .unwrap();
+        assert!(supergraph.reload(&mut source).to_result().is_err());
+        // The failed revision isn't reported again until the file changes.
+        assert!(!supergraph.reload(&mut source).to_result().unwrap());
+
+        let current = supergraph.current();
+        assert_eq!(current.number, 1);
+        assert_eq!(query_fields(&current), ["me", "topProducts"]);
+    }
+
+    #[test]
+    fn test_directory_source() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, sdl) in [
+            (
+                "accounts",
+                include_str!("fixtures/subgraphs/accounts.graphql"),
+            ),
+            (
+                "products",
+                include_str!("fixtures/subgraphs/products.graphql"),
+            ),
+            (
+                "reviews",
+                include_str!("fixtures/subgraphs/reviews.graphql"),
+            ),
+        ] {
+            std::fs::write(dir.path().join(format!("{}.graphql", name)), sdl).unwrap();
+        }
+        std::fs::write(dir.path().join("README.md"), "Not a subgraph").unwrap();
+
+        let mut source = DirectorySource::new(dir.path())
+            .url("accounts", "http://accounts:4001")
+            .url("products", "http://products:4002");
+        let error = Supergraph::<()>::load(&mut source).to_result().unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("no url is configured for subgraph `reviews`"));
+
+        let mut source = source.url("reviews", "http://reviews:4003");
+        let supergraph: Supergraph = Supergraph::load(&mut source).to_result().unwrap();
+        assert_eq!(query_fields(&supergraph.current()), ["me", "topProducts"]);
+
+        std::fs::write(
+            dir.path().join("products.graphql"),
+            include_str!("fixtures/subgraphs/products.graphql").replace(
+                "  topProducts: [Product!]!\n",
+                "  topProducts: [Product!]!\n  bestProduct: Product\n",
+            ),
+        )
+        .unwrap();
+        assert!(supergraph.reload(&mut source).to_result().unwrap());
+        assert_eq!(
+            query_fields(&supergraph.current()),
+            ["me", "topProducts", "bestProduct"]
+        );
+    }
+
+    // Serves the schema behind the mutex to every GET.
+    fn serve(schema: Arc<Mutex<String>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/supergraph", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let body = schema.lock().unwrap().clone();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_http_watch() {
+        let schema = Arc::new(Mutex::new(ROUTER.to_string()));
+        let url = serve(schema.clone());
+
+        let mut source = HttpSource::new(&url);
+        let supergraph: Arc<Supergraph> =
+            Arc::new(Supergraph::load(&mut source).to_result().unwrap());
+        let _watcher = supergraph.watch(source, Duration::from_millis(10));
+
+        *schema.lock().unwrap() = with_field(ROUTER);
+        let start = Instant::now();
+        while supergraph.current().number == 1 {
+            assert!(start.elapsed() < Duration::from_secs(10), "no reload");
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            query_fields(&supergraph.current()),
+            ["me", "topProducts", "bestProduct"]
+        );
+    }
+}
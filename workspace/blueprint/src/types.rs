@@ -0,0 +1,177 @@
+use std::str::FromStr;
+
+use crate::build::map_type;
+use crate::Type;
+
+impl Type {
+    pub fn named(name: impl Into<String>) -> Self {
+        Type::Named { name: name.into(), required: false }
+    }
+
+    pub fn list(of_type: Type) -> Self {
+        Type::List { of_type: Box::new(of_type), non_null: false }
+    }
+
+    /// The non-null variant of the type, `T!` for `T`.
+    pub fn to_required(&self) -> Type {
+        match self {
+            Type::Named { name, .. } => Type::Named { name: name.clone(), required: true },
+            Type::List { of_type, .. } => Type::List { of_type: of_type.clone(), non_null: true },
+        }
+    }
+
+    /// The nullable variant of the type, `T` for `T!`.
+    pub fn to_nullable(&self) -> Type {
+        match self {
+            Type::Named { name, .. } => Type::Named { name: name.clone(), required: false },
+            Type::List { of_type, .. } => Type::List { of_type: of_type.clone(), non_null: false },
+        }
+    }
+
+    pub fn is_list(&self) -> bool {
+        matches!(self, Type::List { .. })
+    }
+
+    /// The type of the items of a list type.
+    pub fn item_type(&self) -> Option<&Type> {
+        match self {
+            Type::Named { .. } => None,
+            Type::List { of_type, .. } => Some(of_type),
+        }
+    }
+
+    /// How many lists the named type is wrapped in, 2 for `[[Int]!]`.
+    pub fn list_depth(&self) -> usize {
+        match self {
+            Type::Named { .. } => 0,
+            Type::List { of_type, .. } => 1 + of_type.list_depth(),
+        }
+    }
+
+    /// Whether a value of this type can be used where `location` is
+    /// expected, the spec's
+    /// [AreTypesCompatible](https://spec.graphql.org/October2021/#AreTypesCompatible()).
+    pub fn is_compatible_with(&self, location: &Type) -> bool {
+        match (self, location) {
+            (_, location) if location.is_required() => {
+                self.is_required()
+                    && self
+                        .to_nullable()
+                        .is_compatible_with(&location.to_nullable())
+            }
+            (variable, _) if variable.is_required() => {
+                variable.to_nullable().is_compatible_with(location)
+            }
+            (Type::List { of_type, .. }, Type::List { of_type: location, .. }) => {
+                of_type.is_compatible_with(location)
+            }
+            (Type::Named { name, .. }, Type::Named { name: location, .. }) => name == location,
+            _ => false,
+        }
+    }
+
+    /// Whether a variable of this type can be used where `location` is
+    /// expected, the spec's
+    /// [IsVariableUsageAllowed](https://spec.graphql.org/October2021/#IsVariableUsageAllowed()).
+    /// `has_default` tells whether the variable has a default value other
+    /// than null or the location has a default value, either of which lets
+    /// a nullable variable be used for a non-null location.
+    pub fn is_usage_allowed(&self, location: &Type, has_default: bool) -> bool {
+        if location.is_required() && !self.is_required() {
+            has_default && self.is_compatible_with(&location.to_nullable())
+        } else {
+            self.is_compatible_with(location)
+        }
+    }
+}
+
+/// Reads a type the way it is written in a document, `[Int!]!`.
+impl FromStr for Type {
+    type Err = String;
+
+    fn from_str(of_type: &str) -> Result<Self, Self::Err> {
+        parse(of_type.trim()).ok_or_else(|| format!("`{}` is not a valid type", of_type))
+    }
+}
+
+impl From<&async_graphql_parser::types::Type> for Type {
+    fn from(of_type: &async_graphql_parser::types::Type) -> Self {
+        map_type(of_type)
+    }
+}
+
+fn parse(of_type: &str) -> Option<Type> {
+    if let Some(nullable) = of_type.strip_suffix('!') {
+        return parse(nullable.trim_end())
+            .filter(|nullable| !nullable.is_required())
+            .map(|nullable| nullable.to_required());
+    }
+
+    if let Some(item) = of_type
+        .strip_prefix('[')
+        .and_then(|item| item.strip_suffix(']'))
+    {
+        return parse(item.trim()).map(Type::list);
+    }
+
+    let mut chars = of_type.chars();
+    let is_name = chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric());
+    is_name.then(|| Type::named(of_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn of_type(of_type: &str) -> Type {
+        of_type.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_print() {
+        for printed in ["Int", "Int!", "[Int]", "[Int!]!", "[[String]!]"] {
+            assert_eq!(of_type(printed).to_string(), printed);
+        }
+
+        assert_eq!(of_type("[[String]!]").list_depth(), 2);
+        assert_eq!(of_type("[Int!]!").to_nullable().to_string(), "[Int!]");
+        assert_eq!(of_type("[Int!]").item_type(), Some(&of_type("Int!")));
+        assert!("[Int".parse::<Type>().is_err());
+        assert!("Int!!".parse::<Type>().is_err());
+        assert!("".parse::<Type>().is_err());
+    }
+
+    #[test]
+    fn test_is_compatible_with() {
+        let compatible = |variable: &str, location: &str| {
+            of_type(variable).is_compatible_with(&of_type(location))
+        };
+
+        assert!(compatible("Int", "Int"));
+        assert!(compatible("Int!", "Int"));
+        assert!(!compatible("Int", "Int!"));
+        assert!(!compatible("Int", "String"));
+        assert!(compatible("[Int!]!", "[Int]"));
+        assert!(!compatible("[Int]", "[Int!]"));
+        assert!(!compatible("[Int]", "Int"));
+        assert!(!compatible("Int", "[Int]"));
+        assert!(!compatible("[[Int]]", "[Int]"));
+    }
+
+    #[test]
+    fn test_is_usage_allowed() {
+        let allowed = |variable: &str, location: &str, has_default: bool| {
+            of_type(variable).is_usage_allowed(&of_type(location), has_default)
+        };
+
+        assert!(allowed("Int!", "Int!", false));
+        assert!(!allowed("Int", "Int!", false));
+        assert!(allowed("Int", "Int!", true));
+        assert!(!allowed("String", "Int!", true));
+        assert!(allowed("[Int]", "[Int]!", true));
+        assert!(!allowed("[Int]", "[Int!]!", true));
+    }
+}
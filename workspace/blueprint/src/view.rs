@@ -0,0 +1,645 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::build::map_type;
+use crate::{
+    Blueprint, Definition, Directive, EnumTypeDefinition, FieldDefinition, Graph,
+    InputFieldDefinition, InputObjectTypeDefinition, InterfaceTypeDefinition, JoinFieldParsed,
+    Links, ObjectTypeDefinition, ScalarTypeDefinition, SchemaDefinition, Selection, SelectionSet,
+    Type, UnionTypeDefinition,
+};
+
+/// What one subgraph of the supergraph holds: the types it has, the keys it
+/// resolves them by and how it gets their fields.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphView {
+    pub graph: Graph,
+    /// Name of the subgraph, from `@join__graph`.
+    pub name: Option<String>,
+    pub url: Option<String>,
+    types: IndexMap<String, GraphType>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphType {
+    pub name: String,
+    pub keys: Vec<Key>,
+    pub extension: bool,
+    pub is_interface_object: bool,
+    fields: IndexMap<String, GraphField>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Key {
+    pub fields: SelectionSet,
+    /// Whether the graph resolves the entity by this key through
+    /// `_entities`, or only refers to it.
+    pub resolvable: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphField {
+    pub name: String,
+    /// Type of the field in the subgraph, which can be nullable where the
+    /// supergraph's isn't.
+    pub of_type: Type,
+    pub resolution: Resolution,
+    pub requires: Option<SelectionSet>,
+    pub provides: Option<SelectionSet>,
+    /// Subgraph the field was taken over from with `@override`.
+    pub overrides: Option<String>,
+    /// Whether other graphs resolve the field too.
+    pub shared: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Resolution {
+    /// The graph resolves the field.
+    Owned,
+    /// The graph only resolves the field under the fields that `@provides`
+    /// it.
+    Provided,
+    /// The graph only holds the field, to be given it by others for
+    /// `@requires` or `@key`.
+    External,
+}
+
+impl GraphView {
+    pub fn get_type(&self, type_name: &str) -> Option<&GraphType> {
+        self.types.get(type_name)
+    }
+
+    pub fn types(&self) -> impl Iterator<Item = &GraphType> {
+        self.types.values()
+    }
+
+    pub fn get_field(&self, type_name: &str, field_name: &str) -> Option<&GraphField> {
+        self.types.get(type_name)?.fields.get(field_name)
+    }
+
+    /// Whether the graph resolves a field on its own.
+    pub fn can_resolve(&self, type_name: &str, field_name: &str) -> bool {
+        self.get_field(type_name, field_name)
+            .is_some_and(|field| field.resolution == Resolution::Owned)
+    }
+}
+
+impl GraphType {
+    pub fn get_field(&self, field_name: &str) -> Option<&GraphField> {
+        self.fields.get(field_name)
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = &GraphField> {
+        self.fields.values()
+    }
+
+    /// Whether the graph resolves the type as an entity.
+    pub fn is_entity(&self) -> bool {
+        self.keys.iter().any(|key| key.resolvable)
+    }
+
+    fn is_key(&self, field_name: &str) -> bool {
+        self.keys
+            .iter()
+            .any(|key| key.fields.fields().any(|field| field.name == field_name))
+    }
+}
+
+/// Splits the supergraph into what each of its graphs holds.
+pub(crate) fn views(blueprint: &Blueprint) -> BTreeMap<Graph, GraphView> {
+    let mut views = BTreeMap::<Graph, GraphView>::new();
+    let definitions = blueprint
+        .definitions
+        .iter()
+        .filter(|definition| !blueprint.links.owns(definition.name()));
+
+    for definition in definitions {
+        for join_type in definition.join_types() {
+            let view = views
+                .entry(join_type.graph.clone())
+                .or_insert_with(|| GraphView {
+                    graph: join_type.graph.clone(),
+                    name: None,
+                    url: None,
+                    types: IndexMap::new(),
+                });
+            let graph_type = view
+                .types
+                .entry(definition.name().to_string())
+                .or_insert_with(|| GraphType {
+                    name: definition.name().to_string(),
+                    keys: Vec::new(),
+                    extension: false,
+                    is_interface_object: false,
+                    fields: IndexMap::new(),
+                });
+
+            if let Some(key) = &join_type.key {
+                graph_type
+                    .keys
+                    .push(Key { fields: key.clone(), resolvable: join_type.resolvable });
+            }
+            graph_type.extension |= join_type.extension;
+            graph_type.is_interface_object |= join_type.is_interface_object;
+        }
+
+        match definition.as_ref() {
+            Definition::Object(ObjectTypeDefinition { name, fields, .. })
+            | Definition::Interface(InterfaceTypeDefinition { name, fields, .. }) => {
+                for field in fields {
+                    hold(
+                        &mut views,
+                        name,
+                        &field.name,
+                        &field.of_type,
+                        &field.join_fields,
+                    );
+                }
+            }
+            Definition::InputObject(def) => {
+                for field in &def.fields {
+                    hold(
+                        &mut views,
+                        &def.name,
+                        &field.name,
+                        &field.of_type,
+                        &field.join_fields,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    provide(&mut views);
+    share(&mut views);
+    name(&mut views, blueprint);
+
+    views
+}
+
+// Puts a field in the graphs of its `@join__field`s or, without any, in
+// every graph of its type.
+fn hold(
+    views: &mut BTreeMap<Graph, GraphView>,
+    type_name: &str,
+    field_name: &str,
+    of_type: &Type,
+    join_fields: &[JoinFieldParsed],
+) {
+    let field = |join_field: Option<&JoinFieldParsed>| {
+        let external = join_field.is_some_and(|join_field| {
+            join_field.external == Some(true) || join_field.used_overridden == Some(true)
+        });
+
+        GraphField {
+            name: field_name.to_string(),
+            of_type: join_field
+                .and_then(|join_field| join_field.r#type.as_deref())
+                .and_then(async_graphql_parser::types::Type::new)
+                .map(|of_type| map_type(&of_type))
+                .unwrap_or_else(|| of_type.clone()),
+            resolution: if external {
+                Resolution::External
+            } else {
+                Resolution::Owned
+            },
+            requires: join_field.and_then(|join_field| join_field.requires.clone()),
+            provides: join_field.and_then(|join_field| join_field.provides.clone()),
+            overrides: join_field.and_then(|join_field| join_field.r#override.clone()),
+            shared: false,
+        }
+    };
+
+    if join_fields.is_empty() {
+        for view in views.values_mut() {
+            if let Some(graph_type) = view.types.get_mut(type_name) {
+                graph_type
+                    .fields
+                    .insert(field_name.to_string(), field(None));
+            }
+        }
+        return;
+    }
+
+    for join_field in join_fields {
+        let Some(graph_type) = join_field
+            .graph
+            .as_ref()
+            .and_then(|graph| views.get_mut(graph))
+            .and_then(|view| view.types.get_mut(type_name))
+        else {
+            continue;
+        };
+        graph_type
+            .fields
+            .insert(field_name.to_string(), field(Some(join_field)));
+    }
+}
+
+// External fields selected by a `@provides` of the same graph are resolved
+// under it.
+fn provide(views: &mut BTreeMap<Graph, GraphView>) {
+    for view in views.values_mut() {
+        let provided = view
+            .types
+            .values()
+            .flat_map(|graph_type| graph_type.fields.values())
+            .filter(|field| field.resolution == Resolution::Owned)
+            .filter_map(|field| Some((field.of_type.as_type_str(), field.provides.as_ref()?)))
+            .flat_map(|(type_name, provides)| provided(view, &type_name, provides))
+            .collect::<Vec<_>>();
+
+        for (type_name, field_name) in provided {
+            let field = view
+                .types
+                .get_mut(&type_name)
+                .and_then(|graph_type| graph_type.fields.get_mut(&field_name));
+            if let Some(field) = field {
+                if field.resolution == Resolution::External {
+                    field.resolution = Resolution::Provided;
+                }
+            }
+        }
+    }
+}
+
+fn provided(view: &GraphView, type_name: &str, selection: &SelectionSet) -> Vec<(String, String)> {
+    selection
+        .0
+        .iter()
+        .flat_map(|selection| match selection {
+            Selection::Field(field) => {
+                let nested = view
+                    .get_field(type_name, &field.name)
+                    .map(|graph_field| {
+                        provided(view, graph_field.of_type.name(), &field.selections)
+                    })
+                    .unwrap_or_default();
+
+                std::iter::once((type_name.to_string(), field.name.clone()))
+                    .chain(nested)
+                    .collect()
+            }
+            Selection::InlineFragment(fragment) => provided(
+                view,
+                fragment.type_condition.as_deref().unwrap_or(type_name),
+                &fragment.selections,
+            ),
+        })
+        .collect()
+}
+
+fn share(views: &mut BTreeMap<Graph, GraphView>) {
+    let mut owners = BTreeMap::<(String, String), usize>::new();
+    for graph_type in views.values().flat_map(|view| view.types.values()) {
+        for field in graph_type
+            .fields
+            .values()
+            .filter(|field| field.resolution == Resolution::Owned)
+        {
+            *owners
+                .entry((graph_type.name.clone(), field.name.clone()))
+                .or_default() += 1;
+        }
+    }
+
+    for graph_type in views.values_mut().flat_map(|view| view.types.values_mut()) {
+        for field in graph_type.fields.values_mut() {
+            let key = (graph_type.name.clone(), field.name.clone());
+            field.shared = field.resolution == Resolution::Owned
+                && owners.get(&key).is_some_and(|owners| *owners > 1);
+        }
+    }
+}
+
+fn name(views: &mut BTreeMap<Graph, GraphView>, blueprint: &Blueprint) {
+    let graph_enum = blueprint.links.type_name("join", "Graph");
+    let graph_directive = blueprint.links.directive("join", "graph");
+    let Some(Definition::Enum(graphs)) = blueprint
+        .definitions
+        .iter()
+        .find(|definition| definition.name() == graph_enum)
+        .map(AsRef::as_ref)
+    else {
+        return;
+    };
+
+    for value in &graphs.enum_values {
+        let Some(view) = views.get_mut(&Graph::new(&value.name)) else {
+            continue;
+        };
+        let Some(directive) = value
+            .directives
+            .iter()
+            .find(|directive| directive.name == graph_directive)
+        else {
+            continue;
+        };
+        let argument = |name: &str| {
+            directive
+                .arguments
+                .get(name)
+                .and_then(|value| value.as_str())
+                .map(str::to_string)
+        };
+
+        view.name = argument("name");
+        view.url = argument("url");
+    }
+}
+
+const FEDERATION: &str = "https://specs.apollo.dev/federation/v2.3";
+
+/// The federation 2 schema of a subgraph, as the supergraph tells it.
+pub(crate) fn sdl<'a>(
+    view: &GraphView,
+    definitions: impl Iterator<Item = &'a Definition>,
+    schema: &SchemaDefinition,
+) -> String {
+    let imports = [
+        "@key",
+        "@external",
+        "@requires",
+        "@provides",
+        "@shareable",
+        "@override",
+        "@interfaceObject",
+    ];
+    let root = |root: &Option<String>| root.clone().filter(|root| view.types.contains_key(root));
+
+    let subgraph = Blueprint {
+        definitions: definitions
+            .filter_map(|definition| Some((definition, view.types.get(definition.name())?)))
+            .map(|(definition, graph_type)| {
+                Arc::new(subgraph_definition(view, definition, graph_type))
+            })
+            .collect(),
+        schema: SchemaDefinition {
+            query: root(&schema.query),
+            mutation: root(&schema.mutation),
+            subscription: root(&schema.subscription),
+            directives: vec![Directive::new(
+                "link",
+                json!({ "url": FEDERATION, "import": imports }),
+            )],
+        },
+        directives: Vec::new(),
+        join_graphs: Vec::new(),
+        links: Links::default(),
+        source: None,
+    };
+
+    subgraph.to_string()
+}
+
+fn subgraph_definition(
+    view: &GraphView,
+    definition: &Definition,
+    graph_type: &GraphType,
+) -> Definition {
+    let mut directives = graph_type
+        .keys
+        .iter()
+        .map(|key| {
+            let arguments = if key.resolvable {
+                json!({ "fields": key.fields.to_string() })
+            } else {
+                json!({ "fields": key.fields.to_string(), "resolvable": false })
+            };
+            Directive::new("key", arguments)
+        })
+        .collect::<Vec<_>>();
+    if graph_type.is_interface_object {
+        directives.push(Directive::new("interfaceObject", json!({})));
+    }
+
+    let implements = |implements: &BTreeSet<String>, join_implements: &[crate::JoinImplements]| {
+        if join_implements.is_empty() {
+            implements
+                .iter()
+                .filter(|interface| view.types.contains_key(*interface))
+                .cloned()
+                .collect()
+        } else {
+            join_implements
+                .iter()
+                .filter(|join_implements| join_implements.graph == view.graph)
+                .map(|join_implements| join_implements.interface.clone())
+                .collect()
+        }
+    };
+
+    match definition {
+        Definition::Object(def) => Definition::Object(ObjectTypeDefinition {
+            name: def.name.clone(),
+            fields: subgraph_fields(graph_type, &def.fields),
+            directives,
+            description: def.description.clone(),
+            implements: implements(&def.implements, &def.join_implements),
+            join_types: Vec::new(),
+            join_implements: Vec::new(),
+            pos: None,
+        }),
+        Definition::Interface(def) => Definition::Interface(InterfaceTypeDefinition {
+            name: def.name.clone(),
+            fields: subgraph_fields(graph_type, &def.fields),
+            directives,
+            description: def.description.clone(),
+            implements: implements(&def.implements, &def.join_implements),
+            join_types: Vec::new(),
+            join_implements: Vec::new(),
+            pos: None,
+        }),
+        Definition::InputObject(def) => Definition::InputObject(InputObjectTypeDefinition {
+            name: def.name.clone(),
+            fields: def
+                .fields
+                .iter()
+                .filter_map(|field| {
+                    let graph_field = graph_type.get_field(&field.name)?;
+                    Some(InputFieldDefinition {
+                        of_type: graph_field.of_type.clone(),
+                        ..subgraph_input(field)
+                    })
+                })
+                .collect(),
+            directives: Vec::new(),
+            description: def.description.clone(),
+            join_types: Vec::new(),
+            pos: None,
+        }),
+        Definition::Scalar(def) => Definition::Scalar(ScalarTypeDefinition {
+            name: def.name.clone(),
+            directives: Vec::new(),
+            description: def.description.clone(),
+            join_types: Vec::new(),
+            pos: None,
+        }),
+        Definition::Enum(def) => Definition::Enum(EnumTypeDefinition {
+            name: def.name.clone(),
+            directives: Vec::new(),
+            description: def.description.clone(),
+            enum_values: def
+                .enum_values
+                .iter()
+                .filter(|value| {
+                    value.join_enums.is_empty()
+                        || value
+                            .join_enums
+                            .iter()
+                            .any(|join_enum| join_enum.graph == view.graph)
+                })
+                .map(|value| crate::EnumValueDefinition {
+                    directives: Vec::new(),
+                    join_enums: Vec::new(),
+                    ..value.clone()
+                })
+                .collect(),
+            join_types: Vec::new(),
+            pos: None,
+        }),
+        Definition::Union(def) => Definition::Union(UnionTypeDefinition {
+            name: def.name.clone(),
+            directives: Vec::new(),
+            description: def.description.clone(),
+            types: if def.join_unions.is_empty() {
+                def.types
+                    .iter()
+                    .filter(|member| view.types.contains_key(*member))
+                    .cloned()
+                    .collect()
+            } else {
+                def.join_unions
+                    .iter()
+                    .filter(|join_union| join_union.graph == view.graph)
+                    .map(|join_union| join_union.member.clone())
+                    .collect()
+            },
+            join_types: Vec::new(),
+            join_unions: Vec::new(),
+            pos: None,
+        }),
+    }
+}
+
+fn subgraph_fields(graph_type: &GraphType, fields: &[FieldDefinition]) -> Vec<FieldDefinition> {
+    fields
+        .iter()
+        .filter_map(|field| Some((field, graph_type.get_field(&field.name)?)))
+        .map(|(field, graph_field)| {
+            let mut directives = Vec::new();
+            if graph_field.resolution != Resolution::Owned {
+                directives.push(Directive::new("external", json!({})));
+            }
+            if let Some(requires) = &graph_field.requires {
+                directives.push(Directive::new(
+                    "requires",
+                    json!({ "fields": requires.to_string() }),
+                ));
+            }
+            if let Some(provides) = &graph_field.provides {
+                directives.push(Directive::new(
+                    "provides",
+                    json!({ "fields": provides.to_string() }),
+                ));
+            }
+            if let Some(from) = &graph_field.overrides {
+                directives.push(Directive::new("override", json!({ "from": from })));
+            }
+            // Key fields are shareable without saying so.
+            if graph_field.shared && !graph_type.is_key(&field.name) {
+                directives.push(Directive::new("shareable", json!({})));
+            }
+
+            FieldDefinition {
+                name: field.name.clone(),
+                args: field.args.iter().map(subgraph_input).collect(),
+                of_type: graph_field.of_type.clone(),
+                directives,
+                description: field.description.clone(),
+                join_fields: Vec::new(),
+                pos: None,
+            }
+        })
+        .collect()
+}
+
+fn subgraph_input(input: &InputFieldDefinition) -> InputFieldDefinition {
+    InputFieldDefinition {
+        directives: Vec::new(),
+        join_fields: Vec::new(),
+        pos: None,
+        ..input.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use valid::Validator;
+
+    use super::*;
+    use crate::Index;
+
+    fn index() -> Index {
+        Blueprint::parse(include_str!("fixtures/router.graphql"))
+            .to_result()
+            .unwrap()
+            .to_index()
+    }
+
+    #[test]
+    fn test_graph_view() {
+        let index = index();
+        let reviews = index.graph(&Graph::new("REVIEWS")).unwrap();
+        let resolution = |type_name, field_name| {
+            reviews
+                .get_field(type_name, field_name)
+                .map(|field| field.resolution)
+        };
+
+        assert_eq!(reviews.name.as_deref(), Some("reviews"));
+        assert!(reviews.get_type("User").unwrap().is_entity());
+        assert_eq!(
+            reviews.get_type("User").unwrap().keys[0].fields.to_string(),
+            "id"
+        );
+        assert_eq!(resolution("User", "id"), Some(Resolution::Owned));
+        assert_eq!(resolution("User", "username"), None);
+        assert_eq!(
+            resolution("User", "joinedTimestamp"),
+            Some(Resolution::External)
+        );
+        assert_eq!(resolution("Product", "price"), Some(Resolution::Provided));
+        assert_eq!(
+            reviews
+                .get_field("User", "reviewCount")
+                .unwrap()
+                .overrides
+                .as_deref(),
+            Some("accounts")
+        );
+        assert!(reviews.get_field("Picture", "url").unwrap().shared);
+
+        assert_eq!(
+            index.graphs_resolving("User", "id"),
+            [Graph::new("ACCOUNTS"), Graph::new("REVIEWS")]
+        );
+        assert_eq!(
+            index.graphs_resolving("User", "joinedTimestamp"),
+            [Graph::new("ACCOUNTS")]
+        );
+    }
+
+    #[test]
+    fn test_subgraph_sdl() {
+        let index = index();
+        let sdl = index.subgraph_sdl(&Graph::new("REVIEWS")).unwrap();
+
+        insta::assert_snapshot!(sdl);
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use blueprint::Blueprint;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use query_plan::{validate, Enrich, OperationPlan};
+use valid::{Transform, Validator};
+
+const SUPERGRAPH: &str = include_str!("../../blueprint/src/fixtures/router.graphql");
+
+// A wide and deep query over every graph of the fixture supergraph.
+const QUERY: &str = r#"
+query {
+  me {
+    id username reviewCount joinedTimestamp trustworthiness
+    profilePicture { url width height }
+    reviews {
+      id body
+      pictures { url width height }
+      author { id username reviews { id body } }
+      product {
+        upc name price
+        reviews { id body author { id username reviewCount } }
+      }
+    }
+  }
+  topProducts {
+    upc name price
+    reviews {
+      id body
+      pictures { url width height }
+      author { id username joinedTimestamp trustworthiness }
+      product { upc name price reviews { id body } }
+    }
+  }
+}
+"#;
+
+fn bench_index(c: &mut Criterion) {
+    let blueprint = Blueprint::parse(SUPERGRAPH).to_result().unwrap();
+
+    c.bench_function("index", |b| b.iter(|| blueprint.to_index()));
+}
+
+fn bench_enrich(c: &mut Criterion) {
+    let blueprint = Blueprint::parse(SUPERGRAPH).to_result().unwrap();
+    let api = blueprint.to_api_schema().to_result().unwrap();
+    let enrich = Enrich::new(Arc::new(blueprint.to_index()), Arc::new(api.to_index()));
+    let document = async_graphql_parser::parse_query(QUERY).unwrap();
+    let plan = OperationPlan::try_new(&document, None).unwrap().plan;
+
+    c.bench_function("enrich", |b| {
+        b.iter_batched(
+            || plan.clone(),
+            |plan| enrich.transform(plan).to_result().unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+// What a request pays for a query that isn't cached yet: parsing, validating
+// and planning it, then enriching the plan.
+fn bench_plan(c: &mut Criterion) {
+    let blueprint = Blueprint::parse(SUPERGRAPH).to_result().unwrap();
+    let api = blueprint.to_api_schema().to_result().unwrap();
+    let enrich = Enrich::new(Arc::new(blueprint.to_index()), Arc::new(api.to_index()));
+
+    c.bench_function("plan", |b| {
+        b.iter(|| {
+            let document = async_graphql_parser::parse_query(QUERY).unwrap();
+            validate(enrich.api(), &document).to_result().unwrap();
+            let plan = OperationPlan::try_new(&document, None).unwrap().plan;
+            enrich.transform(plan).to_result().unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, bench_index, bench_enrich, bench_plan);
+criterion_main!(benches);
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use async_graphql_value::Value;
+use indexmap::IndexMap;
+use valid::Validator;
+
+use crate::error::Error;
+use crate::{validate, Enrich, Labels, OperationPlan};
+
+// A query and the name of its operation.
+type Operation = (String, Option<String>);
+
+// An operation and the labels its plan was made with, of those it checked.
+type Key = (Operation, Labels);
+
+/// Plans of the operations served with one revision of a supergraph. The
+/// labels choose the graphs of the fields under progressive `@override`s, so
+/// an operation is planned once for each outcome of the labels its fields
+/// check, and once only if it selects none of those fields.
+///
+/// The cache holds at most `capacity` plans, dropping the least recently used
+/// one to make room. Queries are planned without holding the lock, so two
+/// requests missing the same plan at once both plan it; the first one to
+/// finish is kept and handed to both.
+///
+/// A cache is only valid for the indexes its plans were enriched with, so
+/// each [blueprint::Revision] of a supergraph keeps its own, as its `plans`.
+#[derive(Debug)]
+pub struct PlanCache {
+    capacity: usize,
+    plans: Mutex<Plans>,
+}
+
+#[derive(Debug, Default)]
+struct Plans {
+    // From the least to the most recently used.
+    map: IndexMap<Key, Arc<OperationPlan<Value>>>,
+    // The labels the plans of an operation check, with how many of its plans
+    // are held.
+    checked: HashMap<Operation, (Labels, usize)>,
+}
+
+impl Plans {
+    fn get(&mut self, operation: &Operation, labels: &Labels) -> Option<Arc<OperationPlan<Value>>> {
+        let (checked, _) = self.checked.get(operation)?;
+        let key = (operation.clone(), labels.within(checked));
+        self.used(&key)
+    }
+
+    // Marks the plan of `key` as the most recently used one.
+    fn used(&mut self, key: &Key) -> Option<Arc<OperationPlan<Value>>> {
+        let index = self.map.get_index_of(key)?;
+        let last = self.map.len() - 1;
+        self.map.move_index(index, last);
+        self.map.get_index(last).map(|(_, plan)| plan.clone())
+    }
+
+    fn insert(
+        &mut self,
+        operation: Operation,
+        labels: &Labels,
+        checked: Labels,
+        plan: Arc<OperationPlan<Value>>,
+        capacity: usize,
+    ) -> Arc<OperationPlan<Value>> {
+        let key = (operation.clone(), labels.within(&checked));
+        if let Some(plan) = self.used(&key) {
+            return plan;
+        }
+        if self.map.len() >= capacity {
+            if let Some(((oldest, _), _)) = self.map.shift_remove_index(0) {
+                if let Some((_, plans)) = self.checked.get_mut(&oldest) {
+                    *plans -= 1;
+                    if *plans == 0 {
+                        self.checked.remove(&oldest);
+                    }
+                }
+            }
+        }
+        self.checked.entry(operation).or_insert((checked, 0)).1 += 1;
+        self.map.insert(key, plan.clone());
+        plan
+    }
+}
+
+impl Default for PlanCache {
+    fn default() -> Self {
+        Self::with_capacity(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl PlanCache {
+    /// How many plans [PlanCache::new] holds.
+    pub const DEFAULT_CAPACITY: usize = 1024;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        PlanCache { capacity: capacity.max(1), plans: Mutex::default() }
+    }
+
+    /// The plan of the operation named `operation_name` in `query` under
+    /// `labels`, validated and enriched with `enrich` the first time it is
+    /// asked for.
+    pub fn plan(
+        &self,
+        query: &str,
+        operation_name: Option<&str>,
+        labels: &Labels,
+        enrich: Enrich<Value>,
+    ) -> Result<Arc<OperationPlan<Value>>, Error> {
+        let operation = (query.to_string(), operation_name.map(str::to_string));
+        if let Some(plan) = self.lock().get(&operation, labels) {
+            return Ok(plan);
+        }
+
+        let document = async_graphql_parser::parse_query(query)?;
+        validate(enrich.api(), &document).to_result()?;
+        let enrich = enrich.labels(labels.clone());
+        let plan = enrich
+            .operation(OperationPlan::try_new(&document, operation_name)?)
+            .to_result()?;
+        Ok(self.lock().insert(
+            operation,
+            labels,
+            enrich.checked(),
+            Arc::new(plan),
+            self.capacity,
+        ))
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The plans are only ever replaced whole, so a panic while holding the
+    // lock can't leave them half written.
+    fn lock(&self) -> MutexGuard<'_, Plans> {
+        self.plans.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use blueprint::{Blueprint, Source, Supergraph};
+    use valid::Valid;
+
+    use super::*;
+    use crate::labels::tests::progressive;
+    use crate::QueryPlan;
+
+    #[test]
+    fn test_plans_by_labels() {
+        let blueprint = Blueprint::parse(&progressive()).to_result().unwrap();
+        let index = Arc::new(blueprint.to_index());
+        let api = Arc::new(blueprint.to_api_schema().to_result().unwrap().to_index());
+        let enrich = || Enrich::new(index.clone(), api.clone());
+        let decide = |custom: &[&str]| Labels::decide_with(&index, custom, || 50.0);
+        let graph = |plan: &OperationPlan<Value>| {
+            let QueryPlan::Fetch(fetch) = &plan.plan else {
+                panic!("expected a fetch")
+            };
+            let username = &fetch.selection_set[0].selections[0];
+            username
+                .join_field
+                .iter()
+                .filter_map(|join_field| join_field.graph.as_ref())
+                .map(|graph| graph.as_str().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let cache = PlanCache::new();
+        let query = "query { me { username } }";
+        let stable = cache.plan(query, None, &decide(&[]), enrich()).unwrap();
+        let beta = cache
+            .plan(query, None, &decide(&["beta"]), enrich())
+            .unwrap();
+        let beta_username = beta.clone();
+        let again = cache.plan(query, None, &decide(&[]), enrich()).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert!(Arc::ptr_eq(&stable, &again));
+        assert_eq!(graph(&stable), ["ACCOUNTS"]);
+        assert_eq!(graph(&beta), ["REVIEWS"]);
+
+        // A query selecting no field under a label is planned once for all.
+        let query = "query { me { id } }";
+        let stable = cache.plan(query, None, &decide(&[]), enrich()).unwrap();
+        let beta = cache
+            .plan(query, None, &decide(&["beta"]), enrich())
+            .unwrap();
+        assert_eq!(cache.len(), 3);
+        assert!(Arc::ptr_eq(&stable, &beta));
+
+        // Nor does one depend on the labels it doesn't check.
+        let query = "query { me { username } }";
+        let percent = Labels::decide_with(&index, ["beta"], || 0.0);
+        let both = cache.plan(query, None, &percent, enrich()).unwrap();
+        assert_eq!(cache.len(), 3);
+        assert!(Arc::ptr_eq(&both, &beta_username));
+    }
+
+    #[test]
+    fn test_invalid_query() {
+        let blueprint = Blueprint::parse(&progressive()).to_result().unwrap();
+        let index = Arc::new(blueprint.to_index());
+        let api = Arc::new(blueprint.to_api_schema().to_result().unwrap().to_index());
+
+        let cache = PlanCache::new();
+        let plan = cache.plan(
+            "query { me { username { id } } }",
+            None,
+            &Labels::default(),
+            Enrich::new(index, api),
+        );
+
+        assert!(plan.is_err());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let blueprint = Blueprint::parse(&progressive()).to_result().unwrap();
+        let index = Arc::new(blueprint.to_index());
+        let api = Arc::new(blueprint.to_api_schema().to_result().unwrap().to_index());
+        let enrich = || Enrich::new(index.clone(), api.clone());
+        let labels = Labels::default();
+
+        let cache = PlanCache::with_capacity(2);
+        let plan = |query: &str| cache.plan(query, None, &labels, enrich()).unwrap();
+        let me = plan("query { me { id } }");
+        plan("query { me { username } }");
+        // Used again, so the least recently used plan is now the one above.
+        assert!(Arc::ptr_eq(&me, &plan("query { me { id } }")));
+        plan("query { me { id username } }");
+
+        assert_eq!(cache.len(), 2);
+        assert!(Arc::ptr_eq(&me, &plan("query { me { id } }")));
+        assert_eq!(cache.len(), 2);
+    }
+
+    // Yields the supergraph at every poll, as a changed one.
+    struct Progressive;
+
+    impl Source for Progressive {
+        fn poll(&mut self) -> Valid<Option<Blueprint>, String> {
+            Blueprint::parse(&progressive()).some()
+        }
+    }
+
+    #[test]
+    fn test_revision_plans() {
+        let supergraph = Supergraph::<PlanCache>::load(&mut Progressive)
+            .to_result()
+            .unwrap();
+        let revision = supergraph.current();
+        let enrich = Enrich::new(revision.index.clone(), revision.api.clone());
+        revision
+            .plans
+            .plan("query { me { id } }", None, &Labels::default(), enrich)
+            .unwrap();
+
+        // The next revision plans with its own indexes, from scratch.
+        assert!(supergraph.reload(&mut Progressive).to_result().unwrap());
+        assert!(supergraph.current().plans.is_empty());
+        assert_eq!(revision.plans.len(), 1);
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use blueprint::{Index, JoinFieldParsed, OverrideLabel};
+
+/// The override labels set for a request. They decide, for each progressive
+/// `@override`, whether the overriding or the overridden graph resolves the
+/// field.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Labels(BTreeSet<String>);
+
+impl Labels {
+    /// Decides the labels of the supergraph for a request: custom labels are
+    /// set when the context of the request sets them, percentages by chance.
+    pub fn decide<A: AsRef<str>>(index: &Index, custom: impl IntoIterator<Item = A>) -> Self {
+        Self::decide_with(index, custom, || fastrand::f64() * 100.0)
+    }
+
+    /// Like [Labels::decide], with `roll` drawing a number from 0 to 100 for
+    /// each percentage, which is set when the number is below it.
+    pub fn decide_with<A: AsRef<str>>(
+        index: &Index,
+        custom: impl IntoIterator<Item = A>,
+        mut roll: impl FnMut() -> f64,
+    ) -> Self {
+        let custom = custom
+            .into_iter()
+            .map(|label| label.as_ref().to_string())
+            .collect::<BTreeSet<_>>();
+
+        Labels(
+            index
+                .override_labels()
+                .iter()
+                .filter(|label| match label {
+                    OverrideLabel::Percent(percent) => roll() < *percent,
+                    OverrideLabel::Custom(label) => custom.contains(label),
+                })
+                .map(ToString::to_string)
+                .collect(),
+        )
+    }
+
+    pub fn is_set(&self, label: &OverrideLabel) -> bool {
+        self.0.contains(&label.to_string())
+    }
+
+    /// The `@join__field`s of a field that apply under these labels: with its
+    /// label set a progressive `@override` is resolved by the overriding
+    /// graph, otherwise by the overridden one. The labels looked at are added
+    /// to `checked`.
+    pub(crate) fn select(
+        &self,
+        join_fields: &Arc<[JoinFieldParsed]>,
+        checked: &mut Labels,
+    ) -> Arc<[JoinFieldParsed]> {
+        if join_fields
+            .iter()
+            .all(|join_field| join_field.override_label.is_none())
+        {
+            return join_fields.clone();
+        }
+
+        join_fields
+            .iter()
+            .filter(|join_field| match &join_field.override_label {
+                Some(label) => {
+                    checked.0.insert(label.to_string());
+                    self.is_set(label) == join_field.r#override.is_some()
+                }
+                None => true,
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Those of these labels that are in `checked`, all that a plan made
+    /// under them depends on when planning it only looked at `checked`.
+    pub(crate) fn within(&self, checked: &Labels) -> Labels {
+        Labels(self.0.intersection(&checked.0).cloned().collect())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use blueprint::Blueprint;
+    use valid::Validator;
+
+    use super::*;
+
+    pub(crate) fn progressive() -> String {
+        include_str!("../../blueprint/src/fixtures/router.graphql")
+            .replace(
+                "  override: String\n",
+                "  override: String\n  overrideLabel: String\n",
+            )
+            .replace(
+                "  username: String! @join__field(graph: ACCOUNTS)\n",
+                "  username: String!\n    @join__field(graph: ACCOUNTS, overrideLabel: \
+                 \"beta\")\n    @join__field(graph: REVIEWS, override: \"accounts\", \
+                 overrideLabel: \"beta\")\n",
+            )
+            .replace(
+                "  reviewCount: Int! @join__field(graph: REVIEWS, override: \"accounts\")\n",
+                "  reviewCount: Int!\n    @join__field(graph: ACCOUNTS, overrideLabel: \
+                 \"percent(20)\")\n    @join__field(graph: REVIEWS, override: \"accounts\", \
+                 overrideLabel: \"percent(20)\")\n",
+            )
+    }
+
+    fn index() -> Index {
+        Blueprint::parse(&progressive())
+            .to_result()
+            .unwrap()
+            .to_index()
+    }
+
+    #[test]
+    fn test_decide() {
+        let index = index();
+        let beta = OverrideLabel::Custom("beta".to_string());
+        let percent = OverrideLabel::Percent(20.0);
+
+        let labels = Labels::decide_with(&index, ["beta", "unknown"], || 19.9);
+        assert!(labels.is_set(&beta) && labels.is_set(&percent));
+        assert_eq!(labels.0.len(), 2);
+
+        let labels = Labels::decide_with(&index, Vec::<String>::new(), || 20.0);
+        assert_eq!(labels, Labels::default());
+    }
+
+    #[test]
+    fn test_select() {
+        let index = index();
+        let mut checked = Labels::default();
+        let mut graphs = |labels: &Labels, field_name| match index.get_field("User", field_name) {
+            Some(blueprint::QueryField::Field(field)) => labels
+                .select(field.join_fields(), &mut checked)
+                .iter()
+                .filter_map(|join_field| join_field.graph.as_ref())
+                .map(|graph| graph.as_str().to_string())
+                .collect::<Vec<_>>(),
+            _ => panic!("expected `User.{}`", field_name),
+        };
+
+        let beta = Labels::decide_with(&index, ["beta"], || 50.0);
+        assert_eq!(graphs(&beta, "username"), ["REVIEWS"]);
+        assert_eq!(graphs(&beta, "reviewCount"), ["ACCOUNTS"]);
+
+        let percent = Labels::decide_with(&index, Vec::<String>::new(), || 10.0);
+        assert_eq!(graphs(&percent, "username"), ["ACCOUNTS"]);
+        assert_eq!(graphs(&percent, "reviewCount"), ["REVIEWS"]);
+        assert_eq!(graphs(&percent, "profilePicture"), ["ACCOUNTS"]);
+
+        // Only the labels of progressive `@override`s are looked at.
+        assert_eq!(checked, Labels::decide_with(&index, ["beta"], || 0.0));
+    }
+}
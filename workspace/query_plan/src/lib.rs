@@ -1,5 +1,14 @@
+mod cache;
 pub mod error;
+mod labels;
 mod model;
 mod transform;
+mod validation;
+mod variables;
 
+pub use cache::PlanCache;
+pub use labels::Labels;
 pub use model::v0::*;
+pub use transform::Enrich;
+pub use validation::{validate, validate_directives};
+pub use variables::Variables;
@@ -1,2 +1,4 @@
 pub mod v0;
+// Nothing plans with the v1 model yet.
+#[allow(dead_code)]
 pub mod v1;
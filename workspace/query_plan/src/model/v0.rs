@@ -1,9 +1,12 @@
+use std::collections::HashMap;
 use std::ops::Deref;
+use std::sync::Arc;
 
 use async_graphql::Positioned;
 use async_graphql_parser::types::{self as Q};
-use blueprint::{Graph, JoinFieldParsed};
+use blueprint::{Graph, Introspection, JoinFieldParsed, Name, Pos, Type};
 use derive_setters::Setters;
+use valid::{Valid, Validator};
 
 use crate::error::Error;
 
@@ -16,6 +19,19 @@ pub enum QueryPlan<Value> {
         select: Lens,
         plan: Box<QueryPlan<Value>>,
     },
+    /// Answers the introspection fields of the query root from the API
+    /// schema, without fetching from any subgraph.
+    Introspect(SelectionSet<Value>),
+    /// Executes `if_clause` when the boolean `variable` of the request is
+    /// true and `else_clause` otherwise, for root fields under `@include` or
+    /// `@skip` on a variable. Either clause may be left out. Conditions below
+    /// the root stay on their fields, for the subgraphs to check against the
+    /// variables they are sent.
+    Condition {
+        variable: String,
+        if_clause: Option<Box<QueryPlan<Value>>>,
+        else_clause: Option<Box<QueryPlan<Value>>>,
+    },
 }
 
 #[derive(Debug, Clone, Setters)]
@@ -33,8 +49,7 @@ pub struct Fetch<Value> {
 #[derive(Debug, Clone)]
 pub struct VariableDefinition<Value> {
     pub name: String,
-    pub type_name: TypeName,
-    pub nullable: bool,
+    pub of_type: Type,
     pub directives: Vec<Directive<Value>>,
     pub default_value: Option<Value>,
 }
@@ -43,38 +58,80 @@ impl QueryPlan<async_graphql_value::Value> {
     pub fn fetch(fetch: Fetch<async_graphql_value::Value>) -> Self {
         QueryPlan::Fetch(fetch)
     }
+
+    /// A plan that runs nothing and resolves to an empty object, the plan of
+    /// an operation whose root fields are all left out by a constant `@skip`
+    /// or `@include`.
+    pub fn empty() -> Self {
+        QueryPlan::Parallel(Vec::new())
+    }
+
+    // Runs `self` only under `conditions`, the first being the outermost.
+    fn conditional(self, conditions: &[Condition]) -> Self {
+        conditions.iter().rev().fold(self, |plan, condition| {
+            let plan = Some(Box::new(plan));
+            let (if_clause, else_clause) = match condition.include {
+                true => (plan, None),
+                false => (None, plan),
+            };
+            QueryPlan::Condition { variable: condition.variable.clone(), if_clause, else_clause }
+        })
+    }
 }
 
 impl QueryPlan<async_graphql_value::Value> {
-    // Tries to create a new Query Plan from a GraphQL query and a Blueprint Index.
-    pub fn try_new(query: &str) -> Result<Self, Error> {
-        let doc = async_graphql_parser::parse_query(query)?;
-        let mut parallel = Vec::new();
-
-        // TODO: handle fragments
-        for (name, Positioned { node: op, .. }) in doc.operations.iter() {
-            let name = name.map(|n| n.to_string());
-            let selection_set = SelectionSet::from(&op.selection_set.node);
-            let type_name = TypeName::new(op.ty.to_string());
-            let directives = extract_directives(op.directives.clone());
-            let variables = extract_variables(op.variable_definitions.clone());
-
+    // Plans a single operation of a document with `fragments`.
+    fn operation(
+        name: Option<&async_graphql_value::Name>,
+        op: &Q::OperationDefinition,
+        fragments: &HashMap<async_graphql_value::Name, Positioned<Q::FragmentDefinition>>,
+    ) -> Result<Self, Error> {
+        let name = name.map(|n| n.to_string());
+        let selection_set = SelectionSet::from_document(&op.selection_set.node, fragments)
+            .to_result()?
+            .fold_conditions();
+        let type_name = TypeName::new(op.ty.to_string());
+        let directives = extract_directives(op.directives.clone());
+        let variables = extract_variables(op.variable_definitions.clone());
+
+        // Introspection is answered by the gateway, only the rest of a
+        // query is fetched.
+        let (introspection, selection_set) = match op.ty {
+            Q::OperationType::Query => {
+                selection_set.partition(|field| Introspection::is_introspection_field(&field.name))
+            }
+            _ => (SelectionSet::default(), selection_set),
+        };
+        // Root fields under a condition on a variable are answered in a plan
+        // of their own, which runs only when the condition holds.
+        let introspects =
+            introspection
+                .by_conditions()
+                .into_iter()
+                .map(|(conditions, selection_set)| {
+                    QueryPlan::Introspect(selection_set).conditional(&conditions)
+                });
+        let groups = selection_set.by_conditions();
+        let fetches = groups.into_iter().map(|(conditions, selection_set)| {
             let fetch = Fetch {
-                name,
-                type_name,
+                name: name.clone(),
+                type_name: type_name.clone(),
                 arguments: Vec::new(),
-                variables,
-                directives,
+                variables: variables.clone(),
+                directives: directives.clone(),
                 selection_set,
                 representations: None,
                 service: None,
             };
-
-            let fetch_op = QueryPlan::fetch(fetch);
-            parallel.push(fetch_op);
-        }
-
-        Ok(QueryPlan::Parallel(parallel))
+            QueryPlan::fetch(fetch).conditional(&conditions)
+        });
+
+        let mut plans = introspects.chain(fetches).collect::<Vec<_>>();
+        Ok(match plans.len() {
+            0 => QueryPlan::empty(),
+            1 => plans.remove(0),
+            _ => QueryPlan::Parallel(plans),
+        })
     }
 
     // Sequentially executes one plan after the other
@@ -86,12 +143,83 @@ impl QueryPlan<async_graphql_value::Value> {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationType {
+    Query,
+    Mutation,
+    Subscription,
+}
+
+impl From<Q::OperationType> for OperationType {
+    fn from(ty: Q::OperationType) -> Self {
+        match ty {
+            Q::OperationType::Query => OperationType::Query,
+            Q::OperationType::Mutation => OperationType::Mutation,
+            Q::OperationType::Subscription => OperationType::Subscription,
+        }
+    }
+}
+
+impl std::fmt::Display for OperationType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            OperationType::Query => "query",
+            OperationType::Mutation => "mutation",
+            OperationType::Subscription => "subscription",
+        })
+    }
+}
+
+/// The plan of the one operation of a document a request executes.
+#[derive(Debug, Clone)]
+pub struct OperationPlan<Value> {
+    pub name: Option<String>,
+    pub operation_type: OperationType,
+    /// The variables of the operation, which requests are coerced to with
+    /// [crate::Variables::coerce].
+    pub variables: Vec<VariableDefinition<Value>>,
+    pub plan: QueryPlan<Value>,
+}
+
+impl OperationPlan<async_graphql_value::Value> {
+    /// Plans the operation of `document` named `operation_name`. The name can
+    /// only be left out when the document has a single operation.
+    pub fn try_new(
+        document: &Q::ExecutableDocument,
+        operation_name: Option<&str>,
+    ) -> Result<Self, Error> {
+        let mut operations = document.operations.iter();
+        let operation = match operation_name {
+            Some(operation_name) => Valid::from_option(
+                operations.find(|(name, _)| name.is_some_and(|name| name == operation_name)),
+                format!("Unknown operation named `{}`", operation_name),
+            ),
+            None => match (operations.next(), operations.next()) {
+                (Some(operation), None) => Valid::succeed(operation),
+                (None, _) => Valid::fail("The document has no operations".to_string()),
+                (Some(_), Some(_)) => Valid::fail(
+                    "An operation name is required, as the document has several operations"
+                        .to_string(),
+                ),
+            },
+        };
+        let (name, op) = operation.to_result()?;
+
+        Ok(OperationPlan {
+            name: name.map(|name| name.to_string()),
+            operation_type: op.node.ty.into(),
+            variables: extract_variables(op.node.variable_definitions.clone()),
+            plan: QueryPlan::operation(name, &op.node, &document.fragments)?,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
-pub struct TypeName(String);
+pub struct TypeName(Name);
 
 impl TypeName {
-    pub fn new(name: String) -> Self {
-        TypeName(name)
+    pub fn new(name: impl Into<Name>) -> Self {
+        TypeName(name.into())
     }
 
     pub fn as_str(&self) -> &str {
@@ -99,9 +227,15 @@ impl TypeName {
     }
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct SelectionSet<Value>(Vec<Field<Value>>);
 
+impl<Value> Default for SelectionSet<Value> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
 impl<A> Deref for SelectionSet<A> {
     type Target = Vec<Field<A>>;
 
@@ -122,6 +256,12 @@ impl<Value> SelectionSet<Value> {
     pub fn into_vec(self) -> Vec<Field<Value>> {
         self.0
     }
+
+    /// Splits the fields into those matching `f` and the others.
+    pub fn partition(self, f: impl Fn(&Field<Value>) -> bool) -> (Self, Self) {
+        let (matching, others) = self.0.into_iter().partition(|field| f(field));
+        (Self(matching), Self(others))
+    }
 }
 
 #[derive(Debug, Clone, Setters)]
@@ -132,15 +272,21 @@ pub struct Field<Value> {
     pub arguments: Vec<Argument<Value>>,
     pub directives: Vec<Directive<Value>>,
 
+    /// The type the field is selected on when it comes from a fragment with a
+    /// type condition, as `name` in `... on User { name }`.
+    pub type_condition: Option<TypeName>,
+
     /// When set to true the field is considered to be used internally for
     /// querying sub-graphs and should not be exposed to the user.
     pub is_hidden: bool,
 
     /// Possible Graphs from where the field can be queried from.
-    pub graph: Vec<Graph>,
+    #[setters(into)]
+    pub graph: Arc<[Graph]>,
 
     /// Internal readonly information from the Blueprint Index.
-    pub join_field: Vec<JoinFieldParsed>,
+    #[setters(into)]
+    pub join_field: Arc<[JoinFieldParsed]>,
 
     /// The type of the field.
     pub field_type: Option<TypeName>,
@@ -157,9 +303,10 @@ impl<A> Field<A> {
             selections,
             arguments: Vec::new(),
             directives: Vec::new(),
+            type_condition: None,
             is_hidden: false,
-            graph: Vec::new(),
-            join_field: Vec::new(),
+            graph: Arc::from([]),
+            join_field: Arc::from([]),
             parent_type: None,
             field_type: None,
         }
@@ -268,44 +415,196 @@ impl Lens {
 }
 
 // Correctly implement and add tests
-impl From<&Q::SelectionSet> for SelectionSet<async_graphql_value::Value> {
-    fn from(node: &Q::SelectionSet) -> SelectionSet<async_graphql_value::Value> {
-        let mut selection_set = Vec::new();
-        for selection in node.items.iter() {
-            let inner_selection = &selection.node;
-            match inner_selection {
-                Q::Selection::Field(Positioned { node, .. }) => {
-                    let field_name = node.name.node.as_str().to_string();
-
-                    let alias = node
-                        .alias
-                        .as_ref()
-                        .map(|alias| alias.clone().into_inner().to_string());
-
-                    let arguments = extract_arguments(node.arguments.clone());
-
-                    let directives = extract_directives(node.directives.clone());
-
-                    let field =
-                        Field::new(field_name, SelectionSet::from(&node.selection_set.node))
-                            .alias(alias)
-                            .arguments(arguments)
-                            .directives(directives);
+impl SelectionSet<async_graphql_value::Value> {
+    /// The fields of a selection set of a document with `fragments`, with the
+    /// fragments it spreads expanded. Fails on unknown fragments and on
+    /// fragments spreading themselves.
+    pub fn from_document(
+        node: &Q::SelectionSet,
+        fragments: &HashMap<async_graphql_value::Name, Positioned<Q::FragmentDefinition>>,
+    ) -> Valid<Self, String> {
+        Fragments(fragments)
+            .expand(node, None, &[], &mut Vec::new())
+            .map(SelectionSet)
+    }
 
-                    selection_set.push(field);
-                }
-                Q::Selection::InlineFragment(_) => {
-                    todo!()
-                }
-                Q::Selection::FragmentSpread(_) => {
-                    todo!()
-                }
+    // Drops the fields `@skip` or `@include` leave out whatever the variables,
+    // and the directives that keep a field whatever they are.
+    fn fold_conditions(self) -> Self {
+        let fields = self.0.into_iter().filter_map(|mut field| {
+            let mut is_kept = true;
+            field
+                .directives
+                .retain(|directive| match Condition::of(directive) {
+                    Some(Err(include)) => {
+                        is_kept &= include;
+                        false
+                    }
+                    _ => true,
+                });
+            is_kept.then(|| {
+                let selections = std::mem::take(&mut field.selections).fold_conditions();
+                field.selections(selections)
+            })
+        });
+        SelectionSet(fields.collect())
+    }
+
+    // Groups the fields by the conditions on variables they are selected
+    // under, in the order the groups first appear. The conditions are taken
+    // off the fields, as the plan checks them. Only the fields of this
+    // selection set are grouped, the conditions of the fields they select
+    // are left for the subgraphs.
+    fn by_conditions(self) -> Vec<(Vec<Condition>, Self)> {
+        let mut groups: Vec<(Vec<Condition>, Self)> = Vec::new();
+        for mut field in self.0 {
+            let mut conditions = Vec::new();
+            field
+                .directives
+                .retain(|directive| match Condition::of(directive) {
+                    Some(Ok(condition)) => {
+                        conditions.push(condition);
+                        false
+                    }
+                    _ => true,
+                });
+            conditions.sort();
+            conditions.dedup();
+
+            match groups.iter_mut().find(|(other, _)| *other == conditions) {
+                Some((_, selection_set)) => selection_set.push(field),
+                None => groups.push((conditions, SelectionSet(vec![field]))),
+            }
+        }
+        groups
+    }
+}
+
+// An `@include` or `@skip` on a variable, holding when the variable is
+// `include`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Condition {
+    variable: String,
+    include: bool,
+}
+
+impl Condition {
+    // The condition of an `@include` or `@skip`, or whether it includes the
+    // field when it is given a constant.
+    fn of(directive: &Directive<async_graphql_value::Value>) -> Option<Result<Self, bool>> {
+        let include = match directive.name.as_str() {
+            "include" => true,
+            "skip" => false,
+            _ => return None,
+        };
+        let argument = directive
+            .arguments
+            .iter()
+            .find(|argument| argument.name == "if")?;
+        match &argument.value {
+            async_graphql_value::Value::Variable(variable) => {
+                Some(Ok(Condition { variable: variable.to_string(), include }))
             }
+            async_graphql_value::Value::Boolean(value) => Some(Err(*value == include)),
+            _ => None,
         }
-        SelectionSet(selection_set)
     }
 }
 
+// Expands the fragments of a document into the fields they select.
+struct Fragments<'a>(&'a HashMap<async_graphql_value::Name, Positioned<Q::FragmentDefinition>>);
+
+impl<'a> Fragments<'a> {
+    // Expands `node` as selected under a fragment with `condition` and
+    // `directives`, which apply to each of its fields. `spreading` holds the
+    // fragments being expanded, to find those spreading themselves.
+    fn expand(
+        &self,
+        node: &'a Q::SelectionSet,
+        condition: Option<&TypeName>,
+        directives: &[Directive<async_graphql_value::Value>],
+        spreading: &mut Vec<&'a str>,
+    ) -> Valid<Vec<Field<async_graphql_value::Value>>, String> {
+        Valid::from_iter(node.items.iter(), |selection| match &selection.node {
+            Q::Selection::Field(Positioned { node, .. }) => self
+                .expand(&node.selection_set.node, None, &[], spreading)
+                .map(|selections| {
+                    let alias = node.alias.as_ref().map(|alias| alias.node.to_string());
+                    let mut field_directives = extract_directives(node.directives.clone());
+                    field_directives.extend_from_slice(directives);
+
+                    vec![
+                        Field::new(node.name.node.to_string(), SelectionSet(selections))
+                            .alias(alias)
+                            .arguments(extract_arguments(node.arguments.clone()))
+                            .directives(field_directives)
+                            .type_condition(condition.cloned()),
+                    ]
+                }),
+            Q::Selection::InlineFragment(Positioned { node: fragment, .. }) => {
+                let condition = fragment
+                    .type_condition
+                    .as_ref()
+                    .map(|condition| TypeName::new(condition.node.on.node.as_str()))
+                    .or_else(|| condition.cloned());
+                let directives = fragment_directives(directives, &fragment.directives);
+
+                self.expand(
+                    &fragment.selection_set.node,
+                    condition.as_ref(),
+                    &directives,
+                    spreading,
+                )
+            }
+            Q::Selection::FragmentSpread(Positioned { node: spread, pos }) => {
+                let name = spread.fragment_name.node.as_str();
+                let at = |message: String| format!("{}: {}", Pos::from(*pos), message);
+
+                if let Some(start) = spreading.iter().position(|spread| *spread == name) {
+                    let cycle = spreading[start..]
+                        .iter()
+                        .chain([&name])
+                        .copied()
+                        .collect::<Vec<_>>();
+                    return Valid::fail(at(format!(
+                        "Fragment `{}` spreads itself: {}",
+                        name,
+                        cycle.join(" -> ")
+                    )));
+                }
+                let Some((name, Positioned { node: fragment, .. })) = self.0.get_key_value(name)
+                else {
+                    return Valid::fail(at(format!("Unknown fragment `{}`", name)));
+                };
+
+                let condition = TypeName::new(fragment.type_condition.node.on.node.as_str());
+                let directives = fragment_directives(directives, &spread.directives);
+
+                spreading.push(name.as_str());
+                let fields = self.expand(
+                    &fragment.selection_set.node,
+                    Some(&condition),
+                    &directives,
+                    spreading,
+                );
+                spreading.pop();
+                fields
+            }
+        })
+        .map(|fields| fields.into_iter().flatten().collect())
+    }
+}
+
+// The directives of a fragment, after those of the fragments it is in.
+fn fragment_directives(
+    directives: &[Directive<async_graphql_value::Value>],
+    fragment: &[Positioned<Q::Directive>],
+) -> Vec<Directive<async_graphql_value::Value>> {
+    let mut directives = directives.to_vec();
+    directives.extend(extract_directives(fragment.to_vec()));
+    directives
+}
+
 fn extract_directives(
     directives: Vec<Positioned<Q::Directive>>,
 ) -> Vec<Directive<async_graphql_value::Value>> {
@@ -344,13 +643,13 @@ fn extract_variables(
         .map(
             |Positioned { node: variable_node, .. }| VariableDefinition {
                 name: variable_node.name.node.to_string(),
-                type_name: TypeName::new(variable_node.var_type.node.base.to_string()),
-                nullable: variable_node.var_type.node.nullable,
+                of_type: Type::from(&variable_node.var_type.node),
                 directives: extract_directives(variable_node.directives.clone()),
+                // Only a default written in the operation, a nullable
+                // variable left out of a request stays absent.
                 default_value: variable_node
-                    .default_value()
-                    .cloned()
-                    .map(|cv| cv.into_value()),
+                    .default_value
+                    .map(|default| default.node.into_value()),
             },
         )
         .collect()
@@ -360,13 +659,224 @@ fn extract_variables(
 mod test {
     use insta::assert_debug_snapshot;
 
+    use super::{Fetch, Field, OperationPlan, OperationType, TypeName};
+    use crate::error::Error;
     use crate::QueryPlan;
 
+    // Plans the single operation of `query`.
+    fn try_plan(query: &str) -> Result<QueryPlan<async_graphql_value::Value>, Error> {
+        let document = async_graphql_parser::parse_query(query)?;
+        OperationPlan::try_new(&document, None).map(|operation| operation.plan)
+    }
+
+    fn plan(query: &str) -> QueryPlan<async_graphql_value::Value> {
+        try_plan(query).unwrap()
+    }
+
     #[test]
     fn test() {
         let query = "query { topProducts { name reviews { score } reviews { description } } }";
-        let actual: QueryPlan<_> = QueryPlan::try_new(query).unwrap();
-        assert_debug_snapshot!(actual);
+        assert_debug_snapshot!(plan(query));
+    }
+
+    #[test]
+    fn test_introspection() {
+        let QueryPlan::Introspect(selection_set) = plan(
+            "query { __typename __schema { types { name } } t: __type(name: \"User\") { name } }",
+        ) else {
+            panic!("expected introspection to be answered locally")
+        };
+        assert_eq!(selection_set.len(), 3);
+
+        let QueryPlan::Parallel(plans) =
+            plan("query { __schema { queryType { name } } me { id __typename } }")
+        else {
+            panic!("expected introspection next to a fetch")
+        };
+        assert!(
+            matches!(&plans[..], [QueryPlan::Introspect(introspection), QueryPlan::Fetch(fetch)]
+            if introspection[0].name == "__schema" && fetch.selection_set[0].name == "me")
+        );
+
+        assert!(matches!(
+            plan("mutation { __typename logVisit { id } }"),
+            QueryPlan::Fetch(_)
+        ));
+    }
+
+    #[test]
+    fn test_fragments() {
+        let query = r#"
+            query ($product: Boolean!, $hide: Boolean!) {
+                me { ...User }
+                search {
+                    __typename
+                    ... on Product @include(if: $product) { upc }
+                    ... { ...User }
+                }
+            }
+
+            fragment User on User {
+                id
+                ... @skip(if: $hide) { username }
+            }
+        "#;
+        let QueryPlan::Fetch(fetch) = plan(query) else {
+            panic!("expected a fetch")
+        };
+        let fields = |field: &Field<_>| {
+            field
+                .selections
+                .iter()
+                .map(|field| {
+                    let condition = field.type_condition.as_ref().map(TypeName::as_str);
+                    let directives = field
+                        .directives
+                        .iter()
+                        .map(|directive| format!("@{}", directive.name))
+                        .collect::<String>();
+                    format!("{}{} {:?}", field.name, directives, condition)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            fields(&fetch.selection_set[0]),
+            ["id Some(\"User\")", "username@skip Some(\"User\")"]
+        );
+        assert_eq!(
+            fields(&fetch.selection_set[1]),
+            [
+                "__typename None",
+                "upc@include Some(\"Product\")",
+                "id Some(\"User\")",
+                "username@skip Some(\"User\")"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fragment_errors() {
+        let query = r#"
+            query {
+                me { ...A }
+                search { ...Unknown }
+            }
+
+            fragment A on User { ...B }
+            fragment B on User { friends { ...A } }
+        "#;
+        let Err(Error::Plan(errors)) = try_plan(query) else {
+            panic!("expected the fragments to be rejected")
+        };
+
+        insta::assert_snapshot!(errors);
+    }
+
+    #[test]
+    fn test_conditions() {
+        let query = r#"
+            query ($reviews: Boolean!, $hide: Boolean = false, $schema: Boolean!) {
+                __typename
+                __schema @include(if: $schema) { queryType { name } }
+                me @skip(if: true) { id }
+                topProducts @include(if: true) {
+                    name @skip(if: false)
+                    upc @include(if: false)
+                    reviews @include(if: $reviews) { body }
+                }
+                ... @include(if: $reviews) { me { id } }
+                search @skip(if: $hide) @include(if: $reviews) { __typename }
+            }
+        "#;
+        // The root fields of each plan, under the conditions leading to it.
+        fn fields(plan: &QueryPlan<async_graphql_value::Value>, conditions: &str) -> Vec<String> {
+            match plan {
+                QueryPlan::Parallel(plans) => plans
+                    .iter()
+                    .flat_map(|plan| fields(plan, conditions))
+                    .collect(),
+                QueryPlan::Fetch(Fetch { selection_set, .. })
+                | QueryPlan::Introspect(selection_set) => selection_set
+                    .iter()
+                    .map(|field| format!("{}{}", conditions, field.name))
+                    .collect(),
+                QueryPlan::Condition { variable, if_clause, else_clause } => {
+                    let clause = |clause: &Option<Box<QueryPlan<_>>>, prefix| match clause {
+                        Some(plan) => {
+                            fields(plan, &format!("{}{}${} ", conditions, prefix, variable))
+                        }
+                        None => Vec::new(),
+                    };
+                    [clause(if_clause, ""), clause(else_clause, "!")].concat()
+                }
+                _ => panic!("unexpected plan"),
+            }
+        }
+
+        let conditional = plan(query);
+        assert_eq!(
+            fields(&conditional, ""),
+            [
+                "__typename",
+                "$schema __schema",
+                "topProducts",
+                "$reviews me",
+                "!$hide $reviews search"
+            ]
+        );
+
+        let QueryPlan::Parallel(plans) = &conditional else {
+            panic!("expected a parallel plan")
+        };
+        let QueryPlan::Fetch(fetch) = &plans[2] else {
+            panic!("expected a fetch")
+        };
+        let top_products = &fetch.selection_set[0];
+        assert!(top_products.directives.is_empty());
+        assert_eq!(
+            top_products
+                .selections
+                .iter()
+                .map(|field| format!("{} {}", field.name, field.directives.len()))
+                .collect::<Vec<_>>(),
+            // Only root fields are planned under conditions, `reviews` keeps
+            // its `@include` for the subgraph.
+            ["name 0", "reviews 1"]
+        );
+
+        assert!(matches!(
+            plan("query { me @skip(if: true) { id } }"),
+            QueryPlan::Parallel(plans) if plans.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_operation_selection() {
+        let document = async_graphql_parser::parse_query(
+            "query getUser { me { id } } mutation logVisit { logVisit(tag: 123) { id } }",
+        )
+        .unwrap();
+        let plan = |operation_name| OperationPlan::try_new(&document, operation_name);
+
+        let operation = plan(Some("logVisit")).unwrap();
+        assert_eq!(operation.name.as_deref(), Some("logVisit"));
+        assert_eq!(operation.operation_type, OperationType::Mutation);
+        assert!(
+            matches!(&operation.plan, QueryPlan::Fetch(fetch) if fetch.selection_set[0].name == "logVisit")
+        );
+
+        let errors = [plan(None), plan(Some("getUsers"))].map(|plan| match plan {
+            Err(Error::Plan(errors)) => errors.to_string(),
+            plan => panic!("expected the operation to be rejected, got {:?}", plan),
+        });
+        assert!(errors[0].contains("An operation name is required"));
+        assert!(errors[1].contains("Unknown operation named `getUsers`"));
+
+        let document = async_graphql_parser::parse_query("{ __typename }").unwrap();
+        let operation = OperationPlan::try_new(&document, None).unwrap();
+        assert_eq!(operation.operation_type, OperationType::Query);
+        assert!(matches!(operation.plan, QueryPlan::Introspect(_)));
     }
 
     #[test]
@@ -407,7 +917,11 @@ mod test {
                 }
             }
         "#;
-        let actual: QueryPlan<_> = QueryPlan::try_new(query).unwrap();
-        assert_debug_snapshot!(actual);
+        let document = async_graphql_parser::parse_query(query).unwrap();
+        let plan = |name| OperationPlan::try_new(&document, Some(name)).unwrap().plan;
+
+        assert_debug_snapshot!("complex_query", plan("getData"));
+        assert_debug_snapshot!("complex_mutation", plan("logVisit"));
+        assert_debug_snapshot!("complex_subscription", plan("newMessages"));
     }
 }
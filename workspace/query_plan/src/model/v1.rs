@@ -1,4 +1,4 @@
-use std::fmt::{Debug};
+use std::fmt::Debug;
 
 use async_graphql_parser::types::{self as Q};
 
@@ -31,7 +31,7 @@ pub struct Node<A, T> {
 }
 
 impl<A, T> Node<A, T> {
-    pub fn try_from(sel: Q::SelectionSet) -> Result<Self, Error> {
+    pub fn try_from(_sel: Q::SelectionSet) -> Result<Self, Error> {
         todo!()
     }
 }
@@ -1,83 +1,145 @@
+use std::cell::RefCell;
 use std::marker::PhantomData;
-use std::rc::Rc;
+use std::sync::Arc;
 
-use blueprint::{Index, QueryField};
+use blueprint::{Definition, Index, Name, QueryField};
 use valid::{Transform, Valid, Validator};
 
-use crate::{Fetch, QueryPlan, SelectionSet, TypeName};
+use crate::{Fetch, Labels, OperationPlan, OperationType, QueryPlan, SelectionSet, TypeName};
 
 pub struct Enrich<Value> {
-    index: Rc<Index>,
+    index: Arc<Index>,
+    /// Index of the API schema, operations may only select what clients can
+    /// see.
+    api: Arc<Index>,
+    /// Override labels of the request, choosing the graphs of progressive
+    /// `@override`s.
+    labels: Labels,
+    /// The labels looked at while enriching, all a plan depends on.
+    checked: RefCell<Labels>,
     _marker: PhantomData<Value>,
 }
 
 impl<Value: Clone> Enrich<Value> {
-    pub fn new(index: Rc<Index>) -> Self {
-        Self { index, _marker: PhantomData }
+    pub fn new(index: Arc<Index>, api: Arc<Index>) -> Self {
+        Self {
+            index,
+            api,
+            labels: Labels::default(),
+            checked: RefCell::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn labels(self, labels: Labels) -> Self {
+        Self { labels, ..self }
+    }
+
+    /// The labels the plans enriched so far have looked at: a plan is the same
+    /// under any labels that agree on these.
+    pub fn checked(&self) -> Labels {
+        self.checked.borrow().clone()
+    }
+
+    /// The index operations are validated against before they are planned.
+    pub fn api(&self) -> &Index {
+        &self.api
     }
 
     fn iter_sel(
         &self,
         selection: SelectionSet<Value>,
-        parent_type: &str,
+        parent_type: &Name,
     ) -> Valid<SelectionSet<Value>, String> {
-        // this field belongs to container_type, so we if want to get this field
-        let type_def = match self.index.get_object_type_definition(parent_type) {
-            Some(type_def) => type_def,
-            None => {
-                return Valid::fail(format!(
-                    "type definition not found for type '{}' ",
-                    parent_type
-                ));
+        Valid::from_iter(selection.into_vec(), |mut field| {
+            // A field selected through a fragment is selected on the type of
+            // its condition.
+            let parent_type = match &field.type_condition {
+                Some(condition) => match self.index.type_name(condition.as_str()) {
+                    Some(condition) if self.applies(parent_type, condition) => condition,
+                    Some(condition) => {
+                        return self.fail(
+                            parent_type,
+                            format!(
+                                "type condition '{}' can never apply to type '{}'",
+                                condition, parent_type
+                            ),
+                        )
+                    }
+                    None => {
+                        return self.fail(
+                            parent_type,
+                            format!("unknown type '{}' in a type condition", condition.as_str()),
+                        )
+                    }
+                },
+                None => parent_type,
+            };
+
+            // Every graph answers `__typename` of the values it resolves.
+            if field.name == "__typename" {
+                field.parent_type = Some(TypeName::new(parent_type.clone()));
+                return Valid::succeed(field);
             }
-        };
 
-        Valid::from_iter(selection.into_vec(), |field| {
-            let field_def = match self.index.get_field(parent_type, &field.name) {
-                Some(QueryField::Field((def, _))) => def,
+            match self.index.get_type(parent_type).map(AsRef::as_ref) {
+                Some(Definition::Object(_) | Definition::Interface(_)) => {}
+                // A union has no fields but `__typename`, the ones of its
+                // members are selected through fragments on them.
+                Some(Definition::Union(_)) => {
+                    return self.fail(
+                        parent_type,
+                        format!(
+                            "field '{}' can not be selected on union '{}' but through a \
+                             fragment",
+                            field.name, parent_type
+                        ),
+                    );
+                }
                 _ => {
-                    return Valid::fail(format!(
-                        "field definition not found for field '{}' in type '{}' ",
-                        field.name, parent_type
-                    ));
+                    return self.fail(
+                        parent_type,
+                        format!("type definition not found for type '{}' ", parent_type),
+                    );
                 }
-            };
+            }
 
-            let mut field = if field_def.join_fields.is_empty() {
-                // if field doesn't have @join__field directive, then
-                // we need to figure out from where this field can be queried
-
-                // 1. this field can be queried form the @join__type -> wherein the key is same
-                //    as this field.
-                // 2. this field can be queried from the @join__type directive's graph where key
-                //    is none.
-                let graphs = type_def
-                    .join_types
-                    .iter()
-                    .filter_map(|jt| {
-                        if jt.key.is_none() || jt.key.as_ref().map_or(false, |_k| false) {
-                            // TODO: fix that
-                            Some(jt.graph.clone())
-                        } else {
-                            None
-                        }
-                    })
-                    .collect::<Vec<_>>();
+            let field_def = match (
+                self.api.get_field(parent_type, &field.name),
+                self.index.get_field(parent_type, &field.name),
+            ) {
+                (Some(QueryField::Field(_)), Some(QueryField::Field(def))) => def,
+                _ => {
+                    return self.fail(
+                        parent_type,
+                        format!(
+                            "field definition not found for field '{}' in type '{}' ",
+                            field.name, parent_type
+                        ),
+                    );
+                }
+            };
 
-                field.graph(graphs)
+            // Everything taken from the Index is shared with it, so enriching
+            // a field doesn't allocate unless labels choose its graphs.
+            if field_def.join_fields().is_empty() {
+                // Without @join__field the field is resolved by the graphs
+                // holding its type.
+                field.graph = field_def.graphs().clone();
             } else {
-                field.join_field(field_def.join_fields.clone())
-            };
+                field.join_field = self
+                    .labels
+                    .select(field_def.join_fields(), &mut self.checked.borrow_mut());
+            }
 
-            let type_name = field_def.of_type.as_type_str();
+            let type_name = field_def.type_name();
 
-            field = field
-                .field_type(Some(TypeName::new(type_name.to_string())))
-                .parent_type(Some(TypeName::new(parent_type.to_string())));
+            field.field_type = Some(TypeName::new(type_name.clone()));
+            field.parent_type = Some(TypeName::new(parent_type.clone()));
 
             if !field.selections.is_empty() {
-                let selection = field.selections.clone();
-                self.iter_sel(selection, &type_name)
+                let selection = std::mem::take(&mut field.selections);
+                self.iter_sel(selection, type_name)
                     .map(|selection_set| field.selections(selection_set))
             } else {
                 Valid::succeed(field)
@@ -86,10 +148,52 @@ impl<Value: Clone> Enrich<Value> {
         .map(|fields| SelectionSet::new(fields))
     }
 
+    /// Enriches the plan of an operation, starting from the root type of its
+    /// operation type.
+    pub fn operation(
+        &self,
+        operation: OperationPlan<Value>,
+    ) -> Valid<OperationPlan<Value>, String> {
+        self.root(operation.operation_type)
+            .and_then(|root_type| self.iter(operation.plan, root_type))
+            .map(|plan| OperationPlan { plan, ..operation })
+    }
+
+    fn root(&self, operation_type: OperationType) -> Valid<&Name, String> {
+        let root = match operation_type {
+            OperationType::Query => self.index.get_query(),
+            OperationType::Mutation => self.index.get_mutation(),
+            OperationType::Subscription => self.index.get_subscription(),
+        };
+        Valid::from_option(
+            root.and_then(|root| self.index.type_name(root)),
+            format!("Root operation for `{}` is not defined", operation_type),
+        )
+    }
+
+    // Whether a fragment on `condition` can apply to a value of `parent_type`,
+    // that is whether some object type is of both.
+    fn applies(&self, parent_type: &str, condition: &str) -> bool {
+        self.index.is_possible_type(parent_type, condition)
+            || self
+                .index
+                .possible_types(condition)
+                .iter()
+                .any(|object| self.index.is_possible_type(parent_type, object))
+    }
+
+    // Fails with the location of `type_name` in the supergraph, if known.
+    fn fail<A>(&self, type_name: &str, message: String) -> Valid<A, String> {
+        match self.index.location(type_name, None) {
+            Some(location) => Valid::fail(format!("{}: {}", location, message)),
+            None => Valid::fail(message),
+        }
+    }
+
     fn iter(
         &self,
         query: QueryPlan<Value>,
-        container_type: &str,
+        container_type: &Name,
     ) -> Valid<QueryPlan<Value>, String> {
         match query {
             QueryPlan::Fetch(Fetch {
@@ -119,6 +223,9 @@ impl<Value: Clone> Enrich<Value> {
                 .iter(*plan, container_type)
                 .map(|plan| QueryPlan::Flatten { select, plan: Box::new(plan) }),
 
+            // Introspection selects from the API schema, not from the graphs.
+            QueryPlan::Introspect(_) => Valid::succeed(query),
+
             QueryPlan::Parallel(plans) => {
                 Valid::from_iter(plans, |plan| self.iter(plan, container_type))
                     .map(|plans| QueryPlan::Parallel(plans))
@@ -128,6 +235,22 @@ impl<Value: Clone> Enrich<Value> {
                 Valid::from_iter(plans, |plan| self.iter(plan, container_type))
                     .map(|plans| QueryPlan::Sequence(plans))
             }
+
+            QueryPlan::Condition { variable, if_clause, else_clause } => {
+                let clause = |clause: Option<Box<QueryPlan<Value>>>| match clause {
+                    Some(plan) => self
+                        .iter(*plan, container_type)
+                        .map(|plan| Some(Box::new(plan))),
+                    None => Valid::succeed(None),
+                };
+                clause(if_clause)
+                    .zip(clause(else_clause))
+                    .map(|(if_clause, else_clause)| QueryPlan::Condition {
+                        variable,
+                        if_clause,
+                        else_clause,
+                    })
+            }
         }
     }
 }
@@ -137,38 +260,268 @@ impl<Value: Clone> Transform for Enrich<Value> {
     type Error = String;
 
     fn transform(&self, value: Self::Value) -> valid::Valid<Self::Value, Self::Error> {
-        Valid::from_option(
-            self.index.get_query(),
-            "Root operation for `query` is not defined".to_string(),
-        )
-        .and_then(|root_type| self.iter(value, root_type))
+        self.root(OperationType::Query)
+            .and_then(|root_type| self.iter(value, root_type))
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::sync::Arc;
+
     use blueprint::Blueprint;
 
     use super::*;
 
-    fn setup(graphql: &str) -> Index {
-        let document = async_graphql_parser::parse_schema(graphql).unwrap();
-        Blueprint::parse_doc(document).to_index()
+    fn setup(graphql: &str) -> Enrich<async_graphql_value::Value> {
+        let blueprint = Blueprint::parse_source("router.graphql", graphql)
+            .to_result()
+            .unwrap();
+        let api = blueprint.to_api_schema().to_result().unwrap();
+
+        Enrich::new(Arc::new(blueprint.to_index()), Arc::new(api.to_index()))
+    }
+
+    // Plans the single operation of `query`.
+    fn query_plan(query: &str) -> QueryPlan<async_graphql_value::Value> {
+        let document = async_graphql_parser::parse_query(query).unwrap();
+        OperationPlan::try_new(&document, None).unwrap().plan
     }
 
     #[test]
     fn test_enricher_supergraph_1() {
         let query = "query { topProducts { productName: name reviews { body } reviews { id } } }";
-        let index = setup(include_str!(
+        let enrich = setup(include_str!(
+            "../../../blueprint/src/fixtures/router.graphql"
+        ));
+        let qp = query_plan(query);
+
+        let enriched_selection_set = enrich.transform(qp).to_result().unwrap();
+
+        insta::assert_debug_snapshot!(enriched_selection_set)
+    }
+
+    #[test]
+    fn test_enricher_shares_index() {
+        let query = "query { topProducts { name reviews { body } } }";
+        let enrich = setup(include_str!(
+            "../../../blueprint/src/fixtures/router.graphql"
+        ));
+        let qp = query_plan(query);
+
+        let QueryPlan::Fetch(fetch) = enrich.transform(qp).to_result().unwrap() else {
+            panic!("expected a fetch")
+        };
+        let name = &fetch.selection_set[0].selections[0];
+        let body = &fetch.selection_set[0].selections[1].selections[0];
+        let field = |type_name, field_name| match enrich.index.get_field(type_name, field_name) {
+            Some(QueryField::Field(field)) => field,
+            _ => panic!("expected `{}.{}`", type_name, field_name),
+        };
+
+        assert!(Arc::ptr_eq(
+            &name.join_field,
+            field("Product", "name").join_fields()
+        ));
+        assert!(Arc::ptr_eq(&body.graph, field("Review", "body").graphs()));
+    }
+
+    #[test]
+    fn test_enricher_introspection() {
+        let query = "query { __schema { queryType { name } } topProducts { __typename name } }";
+        let enrich = setup(include_str!(
+            "../../../blueprint/src/fixtures/router.graphql"
+        ));
+        let qp = query_plan(query);
+
+        let QueryPlan::Parallel(plans) = enrich.transform(qp).to_result().unwrap() else {
+            panic!("expected introspection next to a fetch")
+        };
+        let (QueryPlan::Introspect(introspection), QueryPlan::Fetch(fetch)) =
+            (&plans[0], &plans[1])
+        else {
+            panic!("expected introspection next to a fetch")
+        };
+        let typename = &fetch.selection_set[0].selections[0];
+
+        assert!(introspection[0].field_type.is_none());
+        assert_eq!(typename.name, "__typename");
+        assert_eq!(
+            typename.parent_type.as_ref().map(TypeName::as_str),
+            Some("Product")
+        );
+    }
+
+    #[test]
+    fn test_enricher_fragments() {
+        let query = "query { me { ...Profile } topProducts { ... on Product { name } } } \
+                     fragment Profile on User { username }";
+        let enrich = setup(include_str!(
+            "../../../blueprint/src/fixtures/router.graphql"
+        ));
+        let qp = query_plan(query);
+
+        let QueryPlan::Fetch(fetch) = enrich.transform(qp).to_result().unwrap() else {
+            panic!("expected a fetch")
+        };
+        let username = &fetch.selection_set[0].selections[0];
+        let name = &fetch.selection_set[1].selections[0];
+
+        assert_eq!(
+            username.parent_type.as_ref().map(TypeName::as_str),
+            Some("User")
+        );
+        assert_eq!(
+            name.field_type.as_ref().map(TypeName::as_str),
+            Some("String")
+        );
+
+        let qp = query_plan("query { me { ... on Account { id } } }");
+        let errors = enrich.transform(qp).to_result().unwrap_err();
+        assert!(errors
+            .to_string()
+            .contains("unknown type 'Account' in a type condition"));
+    }
+
+    // The parent types of the fields `field` selects, with the types of the
+    // fields.
+    fn selected(field: &crate::Field<async_graphql_value::Value>) -> Vec<String> {
+        field
+            .selections
+            .iter()
+            .map(|field| {
+                let type_name = |type_name: &Option<TypeName>| {
+                    type_name
+                        .as_ref()
+                        .map_or("-".to_string(), |type_name| type_name.as_str().to_string())
+                };
+                format!(
+                    "{}.{}: {}",
+                    type_name(&field.parent_type),
+                    field.name,
+                    type_name(&field.field_type)
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_enricher_interface() {
+        let enrich = setup(include_str!(
+            "../../../blueprint/src/fixtures/abstract_types.graphql"
+        ));
+        let qp = query_plan(r#"query { node(id: "1") { id __typename ... on Product { upc } } }"#);
+
+        let QueryPlan::Fetch(fetch) = enrich.transform(qp).to_result().unwrap() else {
+            panic!("expected a fetch")
+        };
+
+        assert_eq!(
+            selected(&fetch.selection_set[0]),
+            ["Node.id: ID", "Node.__typename: -", "Product.upc: String"]
+        );
+    }
+
+    #[test]
+    fn test_enricher_union() {
+        let enrich = setup(include_str!(
+            "../../../blueprint/src/fixtures/abstract_types.graphql"
+        ));
+        let qp = query_plan(
+            r#"query { search(text: "rust") { __typename ... on Book { title } ... on Node { id } } }"#,
+        );
+
+        let QueryPlan::Fetch(fetch) = enrich.transform(qp).to_result().unwrap() else {
+            panic!("expected a fetch")
+        };
+
+        assert_eq!(
+            selected(&fetch.selection_set[0]),
+            ["Result.__typename: -", "Book.title: String", "Node.id: ID"]
+        );
+    }
+
+    #[test]
+    fn test_enricher_abstract_errors() {
+        let enrich = setup(include_str!(
+            "../../../blueprint/src/fixtures/abstract_types.graphql"
+        ));
+        let errors = |query| {
+            enrich
+                .transform(query_plan(query))
+                .to_result()
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert!(
+            errors(r#"query { search(text: "rust") { title } }"#).contains(
+                "field 'title' can not be selected on union 'Result' but through a fragment"
+            )
+        );
+        assert!(
+            errors(r#"query { node(id: "1") { ... on Query { __typename } } }"#)
+                .contains("type condition 'Query' can never apply to type 'Node'")
+        );
+    }
+
+    #[test]
+    fn test_enricher_operation_root() {
+        let enrich = setup(include_str!(
             "../../../blueprint/src/fixtures/router.graphql"
         ));
-        let qp = QueryPlan::try_new(query).unwrap();
+        let plan = |query| {
+            let document = async_graphql_parser::parse_query(query).unwrap();
+            OperationPlan::try_new(&document, None).unwrap()
+        };
 
-        let enriched_selection_set = Enrich::new(Rc::new(index))
-            .transform(qp)
+        let operation = enrich
+            .operation(plan("query { me { id } }"))
             .to_result()
             .unwrap();
+        let QueryPlan::Fetch(fetch) = &operation.plan else {
+            panic!("expected a fetch")
+        };
+        assert_eq!(
+            fetch.selection_set[0]
+                .parent_type
+                .as_ref()
+                .map(TypeName::as_str),
+            Some("Query")
+        );
 
-        insta::assert_debug_snapshot!(enriched_selection_set)
+        let errors = enrich
+            .operation(plan("mutation { me { id } }"))
+            .to_result()
+            .unwrap_err();
+        assert!(errors
+            .to_string()
+            .contains("Root operation for `mutation` is not defined"));
+    }
+
+    #[test]
+    fn test_enricher_unknown_field() {
+        let query = "query { topProducts { name rating } }";
+        let enrich = setup(include_str!(
+            "../../../blueprint/src/fixtures/router.graphql"
+        ));
+        let qp = query_plan(query);
+
+        let errors = enrich.transform(qp).to_result().unwrap_err();
+
+        insta::assert_snapshot!(errors)
+    }
+
+    #[test]
+    fn test_enricher_inaccessible_field() {
+        let query = "query { me { profilePicture { url altText } } }";
+        let enrich = setup(include_str!(
+            "../../../blueprint/src/fixtures/router.graphql"
+        ));
+        let qp = query_plan(query);
+
+        let errors = enrich.transform(qp).to_result().unwrap_err();
+
+        insta::assert_snapshot!(errors)
     }
 }
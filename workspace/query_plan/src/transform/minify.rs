@@ -39,10 +39,23 @@ impl<A> Transform for Minify<A> {
                         .map(|vec| QueryPlan::Sequence(vec))
                 }
             }
-            QueryPlan::Fetch { .. } => Valid::succeed(input),
+            QueryPlan::Fetch { .. } | QueryPlan::Introspect(_) => Valid::succeed(input),
             QueryPlan::Flatten { select, plan } => self
                 .transform(*plan)
                 .map(|plan| QueryPlan::Flatten { select, plan: Box::new(plan) }),
+            QueryPlan::Condition { variable, if_clause, else_clause } => {
+                let clause = |clause: Option<Box<QueryPlan<A>>>| match clause {
+                    Some(plan) => self.transform(*plan).map(|plan| Some(Box::new(plan))),
+                    None => Valid::succeed(None),
+                };
+                clause(if_clause)
+                    .zip(clause(else_clause))
+                    .map(|(if_clause, else_clause)| QueryPlan::Condition {
+                        variable,
+                        if_clause,
+                        else_clause,
+                    })
+            }
         }
     }
 }
@@ -1,6 +1,8 @@
 mod enrich;
 mod minify;
 mod preset;
+// Nothing prunes plans with it yet.
+#[allow(dead_code)]
 mod pruner;
 
 pub use enrich::Enrich;
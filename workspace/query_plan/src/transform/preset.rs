@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use blueprint::Index;
 use valid::Transform;
@@ -7,14 +7,15 @@ use super::{Enrich, Minify};
 use crate::QueryPlan;
 
 pub struct Preset<A> {
-    index: Rc<Index>,
+    index: Arc<Index>,
+    api: Arc<Index>,
     _marker: std::marker::PhantomData<A>,
 }
 
 impl<A> Preset<A> {
     #[allow(dead_code)]
-    pub fn new(index: Rc<Index>) -> Self {
-        Self { index, _marker: std::marker::PhantomData }
+    pub fn new(index: Arc<Index>, api: Arc<Index>) -> Self {
+        Self { index, api, _marker: std::marker::PhantomData }
     }
 }
 
@@ -26,7 +27,7 @@ impl<A: Clone> Transform for Preset<A> {
     fn transform(&self, input: Self::Value) -> valid::Valid<Self::Value, String> {
         Minify::new()
             .map_err(|e| e.to_string())
-            .pipe(Enrich::new(self.index.clone()))
+            .pipe(Enrich::new(self.index.clone(), self.api.clone()))
             .transform(input)
     }
 }
@@ -30,9 +30,11 @@ impl<T: Clone + Default> Transform for Pruner<T> {
 impl<T: Clone + Default> Pruner<T> {
     /// Prunes a field and its subfields in a two-step process:
     /// 1. Removes subgraphs not present in the `required_subgraphs` set.
-    /// 2. If there's a common subgraph with the parent, further prunes based on `parent_subgraphs`.
+    /// 2. If there's a common subgraph with the parent, further prunes based on
+    ///    `parent_subgraphs`.
     ///
-    /// This function recursively applies the pruning process to all nested fields.
+    /// This function recursively applies the pruning process to all nested
+    /// fields.
     fn prune_field(
         field: Field<T>,
         required_subgraphs: &HashSet<String>,
@@ -50,17 +52,18 @@ impl<T: Clone + Default> Pruner<T> {
 
         let pruned_join_field = field
             .join_field
-            .into_iter()
+            .iter()
             .filter(|sub| {
-                sub.graph.as_ref().map_or(true, |g| {
+                sub.graph.as_ref().is_none_or(|g| {
                     let contains = required_subgraphs.contains(g.as_str());
                     if is_common {
-                        contains && parent_subgraphs.map_or(true, |ps| ps.contains(g.as_str()))
+                        contains && parent_subgraphs.is_none_or(|ps| ps.contains(g.as_str()))
                     } else {
                         contains
                     }
                 })
             })
+            .cloned()
             .collect();
 
         let pruned_fields = field
@@ -157,11 +160,16 @@ impl<T: Clone + Default> Pruner<T> {
 #[cfg(test)]
 mod test {
     use blueprint::{Graph, JoinField, JoinFieldParsed};
-    use valid::{Transform, Validator};
+    use valid::{Transform, ValidateFrom, Validator};
 
+    use super::Pruner;
     use crate::{Field, SelectionSet};
 
-    use super::Pruner;
+    fn join_field(graph: &Graph) -> JoinFieldParsed {
+        JoinFieldParsed::validate_from(JoinField::new(graph.clone()))
+            .to_result()
+            .unwrap()
+    }
 
     /// topProducts {   [Product]
     ///     name        [Product]
@@ -169,16 +177,18 @@ mod test {
     ///         body    [Reviews, Unknown]
     ///     }
     /// }
-    /// with set cover we can figure out that all fields in graph can be easily resolved by
-    /// `Product` and `Reviews` subgraphs only. so we can easily prune out the `Unknow` subgraph.
+    /// with set cover we can figure out that all fields in graph can be easily
+    /// resolved by `Product` and `Reviews` subgraphs only. so we can easily
+    /// prune out the `Unknow` subgraph.
     ///
     /// We need another step of pruning.
     ///
-    /// set cover pruns out subgraphs by looking at global picture but there's one more step that we can do
-    /// reduce the subgraphs, i mean we can prune based on parent's subgraphs.
-    /// eg. reviews [Reviews]
+    /// set cover pruns out subgraphs by looking at global picture but there's
+    /// one more step that we can do reduce the subgraphs, i mean we can
+    /// prune based on parent's subgraphs. eg. reviews [Reviews]
     ///         body    [Reviews, Unknown]  -> so we can remove the `Unknown`
-    ///         test    [Something]         -> there's no common so keep the `Something` as is.
+    ///         test    [Something]         -> there's no common so keep the
+    /// `Something` as is.
     ///
     ///
     /// eg.
@@ -196,29 +206,22 @@ mod test {
         let unknown_subgraph = Graph::new("Unknown");
         let something_subgraph = Graph::new("Something");
 
-        let name: Field<String> =
-            Field::new("name".into(), SelectionSet::default()).join_field(vec![
-                JoinFieldParsed::from(JoinField::new(product_subgraph.clone())),
-            ]);
-        let test: Field<String> =
-            Field::new("test".into(), SelectionSet::default()).join_field(vec![
-                JoinFieldParsed::from(JoinField::new(something_subgraph.clone())),
-            ]);
+        let name: Field<String> = Field::new("name".into(), SelectionSet::default())
+            .join_field(vec![join_field(&product_subgraph)]);
+        let test: Field<String> = Field::new("test".into(), SelectionSet::default())
+            .join_field(vec![join_field(&something_subgraph)]);
         let body: Field<String> =
             Field::new("body".into(), SelectionSet::default()).join_field(vec![
-                JoinFieldParsed::from(JoinField::new(reviews_subgraph.clone())),
-                JoinFieldParsed::from(JoinField::new(unknown_subgraph.clone())),
+                join_field(&reviews_subgraph),
+                join_field(&unknown_subgraph),
             ]);
-        let reviews = Field::new("reviews".into(), SelectionSet::new(vec![body])).join_field(vec![
-            JoinFieldParsed::from(JoinField::new(reviews_subgraph.clone())),
-        ]);
+        let reviews = Field::new("reviews".into(), SelectionSet::new(vec![body]))
+            .join_field(vec![join_field(&reviews_subgraph)]);
         let base_field = Field::new(
             "topProducts".into(),
             SelectionSet::new(vec![name, reviews, test]),
         )
-        .join_field(vec![JoinFieldParsed::from(JoinField::new(
-            product_subgraph.clone(),
-        ))]);
+        .join_field(vec![join_field(&product_subgraph)]);
 
         let pruned_selection_set = Pruner::new().transform(base_field).to_result().unwrap();
         insta::assert_debug_snapshot!(pruned_selection_set);
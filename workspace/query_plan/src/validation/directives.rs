@@ -0,0 +1,195 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+use async_graphql_parser::types::{
+    Directive, ExecutableDocument, OperationDefinition, OperationType, Selection, SelectionSet,
+    VariableDefinition,
+};
+use async_graphql_parser::Positioned;
+use blueprint::{DirectiveLocation, Index, Pos};
+use valid::{Valid, Validator};
+
+use super::values::check_arguments;
+
+/// Checks the directives of an operation document against the ones the
+/// schema defines: that they are defined, used where they are allowed, not
+/// repeated unless `repeatable` and given arguments of the right types.
+pub fn validate_directives(index: &Index, document: &ExecutableDocument) -> Valid<(), String> {
+    let validation = Directives { index, document, fragments: RefCell::default() };
+
+    Valid::from_iter(super::operations(document), |(_, operation)| {
+        validation.operation(&operation.node)
+    })
+    .unit()
+}
+
+struct Directives<'a> {
+    index: &'a Index,
+    document: &'a ExecutableDocument,
+    // Fragments are checked once, with the variables of the first operation
+    // spreading them.
+    fragments: RefCell<BTreeSet<String>>,
+}
+
+type Variables = [Positioned<VariableDefinition>];
+
+impl Directives<'_> {
+    fn operation(&self, operation: &OperationDefinition) -> Valid<(), String> {
+        let location = match operation.ty {
+            OperationType::Query => DirectiveLocation::Query,
+            OperationType::Mutation => DirectiveLocation::Mutation,
+            OperationType::Subscription => DirectiveLocation::Subscription,
+        };
+        let variables = operation.variable_definitions.as_slice();
+
+        self.directives(&operation.directives, location, variables)
+            .and(Valid::from_iter(variables, |variable| {
+                self.directives(
+                    &variable.node.directives,
+                    DirectiveLocation::VariableDefinition,
+                    variables,
+                )
+            }))
+            .and(self.selection_set(&operation.selection_set.node, variables))
+    }
+
+    fn selection_set(
+        &self,
+        selection_set: &SelectionSet,
+        variables: &Variables,
+    ) -> Valid<(), String> {
+        Valid::from_iter(&selection_set.items, |selection| match &selection.node {
+            Selection::Field(field) => self
+                .directives(&field.node.directives, DirectiveLocation::Field, variables)
+                .and(self.selection_set(&field.node.selection_set.node, variables)),
+            Selection::FragmentSpread(spread) => {
+                let name = &spread.node.fragment_name.node;
+                let directives = self.directives(
+                    &spread.node.directives,
+                    DirectiveLocation::FragmentSpread,
+                    variables,
+                );
+                let fragment = match self.document.fragments.get(name) {
+                    Some(fragment) if self.fragments.borrow_mut().insert(name.to_string()) => self
+                        .directives(
+                            &fragment.node.directives,
+                            DirectiveLocation::FragmentDefinition,
+                            variables,
+                        )
+                        .and(self.selection_set(&fragment.node.selection_set.node, variables)),
+                    _ => Valid::succeed(()),
+                };
+                directives.and(fragment)
+            }
+            Selection::InlineFragment(fragment) => self
+                .directives(
+                    &fragment.node.directives,
+                    DirectiveLocation::InlineFragment,
+                    variables,
+                )
+                .and(self.selection_set(&fragment.node.selection_set.node, variables)),
+        })
+        .unit()
+    }
+
+    fn directives(
+        &self,
+        directives: &[Positioned<Directive>],
+        location: DirectiveLocation,
+        variables: &Variables,
+    ) -> Valid<(), String> {
+        Valid::from_iter(directives.iter().enumerate(), |(i, directive)| {
+            let name = directive.node.name.node.as_str();
+            let at = |message: String| format!("{}: {}", Pos::from(directive.pos), message);
+
+            let Some(definition) = self.index.get_directive(name) else {
+                return Valid::fail(at(format!("Directive `@{}` is not defined", name)));
+            };
+            if !definition.locations.contains(&location) {
+                return Valid::fail(at(format!(
+                    "Directive `@{}` can not be used on {}",
+                    name, location
+                )));
+            }
+            let is_repeated = directives[..i]
+                .iter()
+                .any(|other| other.node.name.node == name);
+            if is_repeated && !definition.repeatable {
+                return Valid::fail(at(format!("Directive `@{}` can not be repeated", name)));
+            }
+
+            check_arguments(
+                self.index,
+                &directive.node.arguments,
+                &definition.arguments,
+                &format!("@{}", name),
+                directive.pos.into(),
+                variables,
+            )
+            .trace(&format!("@{}", name))
+        })
+        .unit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blueprint::Blueprint;
+
+    use super::*;
+
+    fn validate(query: &str) -> Valid<(), String> {
+        let index = Blueprint::parse(include_str!(
+            "../../../blueprint/src/fixtures/router.graphql"
+        ))
+        .and_then(|blueprint| blueprint.to_api_schema())
+        .to_result()
+        .unwrap()
+        .to_index();
+        let document = async_graphql_parser::parse_query(query).unwrap();
+
+        validate_directives(&index, &document)
+    }
+
+    #[test]
+    fn test_directives() {
+        let query = r#"
+            query ($skip: Boolean!, $maybe: Boolean = false) {
+                me @include(if: true) {
+                    name @lowercase @skip(if: $skip)
+                    ... @include(if: $maybe) { id }
+                    ...Reviews @skip(if: false)
+                }
+            }
+
+            fragment Reviews on User {
+                reviews { body @include(if: $skip) }
+            }
+        "#;
+
+        validate(query).to_result().unwrap();
+    }
+
+    #[test]
+    fn test_directive_errors() {
+        let query = r#"
+            query ($flag: Boolean, $name: String) @lowercase {
+                me @unknown {
+                    name @lowercase @lowercase
+                    id @skip(if: $flag) @include(if: $name)
+                    ...Reviews @skip
+                    ... @include(if: "yes", unless: true) { id }
+                    ... @include(if: true, if: false) { id }
+                }
+            }
+
+            fragment Reviews on User @skip(if: $missing) {
+                reviews { body }
+            }
+        "#;
+
+        let errors = validate(query).to_result().unwrap_err();
+
+        insta::assert_snapshot!(errors);
+    }
+}
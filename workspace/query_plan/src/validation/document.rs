@@ -0,0 +1,313 @@
+use std::collections::BTreeSet;
+
+use async_graphql_parser::types::{
+    Directive, ExecutableDocument, OperationDefinition, OperationType, Selection, SelectionSet,
+};
+use async_graphql_parser::Positioned;
+use async_graphql_value::Value;
+use blueprint::{Definition, Index, Pos, Type};
+use valid::{Valid, Validator};
+
+use super::{is_composite, operations, spreads, BUILTIN_SCALARS};
+
+/// Checks the rules of a document as a whole: that fragments are on
+/// composite types, used and free of cycles, that variables are unique,
+/// of input types and used, and that subscriptions select a single field.
+pub(crate) fn check(index: &Index, document: &ExecutableDocument) -> Valid<(), String> {
+    let mut fragments = document.fragments.iter().collect::<Vec<_>>();
+    fragments.sort_by_key(|(_, fragment)| (fragment.pos.line, fragment.pos.column));
+
+    let used = operations(document)
+        .into_iter()
+        .flat_map(|(_, operation)| {
+            let mut used = BTreeSet::new();
+            spread_fragments(document, &operation.node.selection_set.node, &mut used);
+            used
+        })
+        .collect::<BTreeSet<_>>();
+
+    let fragments = Valid::from_iter(fragments.iter().enumerate(), |(i, (name, fragment))| {
+        let at = |message: String| format!("{}: {}", Pos::from(fragment.pos), message);
+        let on = fragment.node.type_condition.node.on.node.as_str();
+
+        let type_condition = if index.get_type(on).is_none() {
+            Valid::fail(at(format!(
+                "Fragment `{}` is on `{}`, which is not defined",
+                name, on
+            )))
+        } else if !is_composite(index, on) {
+            Valid::fail(at(format!(
+                "Fragment `{}` is on `{}`, which has no fields",
+                name, on
+            )))
+        } else {
+            Valid::succeed(())
+        };
+        let is_used = if used.contains(name.as_str()) {
+            Valid::succeed(())
+        } else {
+            Valid::fail(at(format!("Fragment `{}` is never used", name)))
+        };
+        // A cycle is reported from its first fragment only.
+        let earlier = fragments[..i]
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<BTreeSet<_>>();
+        let cycle = match cycle(document, name.as_str(), &earlier) {
+            Some(path) => Valid::fail(at(format!(
+                "Fragment `{}` spreads itself: {}",
+                name,
+                path.join(" -> ")
+            ))),
+            None => Valid::succeed(()),
+        };
+
+        type_condition.and(is_used).and(cycle)
+    });
+
+    let operations = Valid::from_iter(operations(document), |(name, operation)| {
+        let at = |message: String| format!("{}: {}", Pos::from(operation.pos), message);
+
+        check_variables(index, document, &operation.node)
+            .and(check_subscription(document, &operation.node, name).map_err(&at))
+    });
+
+    fragments.and(operations).unit()
+}
+
+// The fragments spread from a selection set, through other fragments too.
+fn spread_fragments<'a>(
+    document: &'a ExecutableDocument,
+    selection_set: &'a SelectionSet,
+    used: &mut BTreeSet<&'a str>,
+) {
+    for name in spreads(selection_set) {
+        if used.insert(name) {
+            if let Some(fragment) = document.fragments.get(name) {
+                spread_fragments(document, &fragment.node.selection_set.node, used);
+            }
+        }
+    }
+}
+
+// The path from `name` back to itself, through fragments not in `skip`.
+fn cycle<'a>(
+    document: &'a ExecutableDocument,
+    name: &'a str,
+    skip: &BTreeSet<&str>,
+) -> Option<Vec<&'a str>> {
+    fn visit<'a>(
+        document: &'a ExecutableDocument,
+        path: &mut Vec<&'a str>,
+        visited: &mut BTreeSet<&'a str>,
+        skip: &BTreeSet<&str>,
+    ) -> bool {
+        let Some(fragment) = document.fragments.get(*path.last().unwrap()) else {
+            return false;
+        };
+        for spread in spreads(&fragment.node.selection_set.node) {
+            if spread == path[0] {
+                path.push(spread);
+                return true;
+            }
+            if skip.contains(spread) || !visited.insert(spread) {
+                continue;
+            }
+            path.push(spread);
+            if visit(document, path, visited, skip) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+
+    let mut path = vec![name];
+    visit(document, &mut path, &mut BTreeSet::new(), skip).then_some(path)
+}
+
+fn check_variables(
+    index: &Index,
+    document: &ExecutableDocument,
+    operation: &OperationDefinition,
+) -> Valid<(), String> {
+    let mut usages = BTreeSet::new();
+    directive_variables(&operation.directives, &mut usages);
+    selection_variables(
+        document,
+        &operation.selection_set.node,
+        &mut usages,
+        &mut BTreeSet::new(),
+    );
+
+    let variables = &operation.variable_definitions;
+    Valid::from_iter(variables.iter().enumerate(), |(i, variable)| {
+        let at = |message: String| format!("{}: {}", Pos::from(variable.pos), message);
+        let name = variable.node.name.node.as_str();
+        let of_type = Type::from(&variable.node.var_type.node);
+
+        let is_unique = variables[..i]
+            .iter()
+            .all(|other| other.node.name.node != name);
+        let is_unique = if is_unique {
+            Valid::succeed(())
+        } else {
+            Valid::fail(at(format!(
+                "Variable `${}` is defined more than once",
+                name
+            )))
+        };
+        let is_input = BUILTIN_SCALARS.contains(&of_type.name())
+            || matches!(
+                index.get_type(of_type.name()).map(AsRef::as_ref),
+                Some(Definition::Scalar(_) | Definition::Enum(_) | Definition::InputObject(_))
+            );
+        let is_input = if is_input {
+            Valid::succeed(())
+        } else {
+            Valid::fail(at(format!(
+                "Variable `${}` is of type `{}`, which is not an input type",
+                name, of_type
+            )))
+        };
+        let is_used = if usages.contains(name) {
+            Valid::succeed(())
+        } else {
+            Valid::fail(at(format!("Variable `${}` is never used", name)))
+        };
+
+        is_unique.and(is_input).and(is_used)
+    })
+    .unit()
+}
+
+// The variables used in a selection set, through the fragments it spreads.
+fn selection_variables<'a>(
+    document: &'a ExecutableDocument,
+    selection_set: &'a SelectionSet,
+    usages: &mut BTreeSet<&'a str>,
+    fragments: &mut BTreeSet<&'a str>,
+) {
+    for selection in &selection_set.items {
+        directive_variables(selection.node.directives(), usages);
+        match &selection.node {
+            Selection::Field(field) => {
+                for (_, value) in &field.node.arguments {
+                    value_variables(&value.node, usages);
+                }
+                selection_variables(document, &field.node.selection_set.node, usages, fragments);
+            }
+            Selection::FragmentSpread(spread) => {
+                let name = spread.node.fragment_name.node.as_str();
+                if let Some(fragment) = document.fragments.get(name) {
+                    if fragments.insert(name) {
+                        directive_variables(&fragment.node.directives, usages);
+                        selection_variables(
+                            document,
+                            &fragment.node.selection_set.node,
+                            usages,
+                            fragments,
+                        );
+                    }
+                }
+            }
+            Selection::InlineFragment(fragment) => selection_variables(
+                document,
+                &fragment.node.selection_set.node,
+                usages,
+                fragments,
+            ),
+        }
+    }
+}
+
+fn directive_variables<'a>(
+    directives: &'a [Positioned<Directive>],
+    usages: &mut BTreeSet<&'a str>,
+) {
+    for directive in directives {
+        for (_, value) in &directive.node.arguments {
+            value_variables(&value.node, usages);
+        }
+    }
+}
+
+fn value_variables<'a>(value: &'a Value, usages: &mut BTreeSet<&'a str>) {
+    match value {
+        Value::Variable(name) => {
+            usages.insert(name.as_str());
+        }
+        Value::List(items) => items.iter().for_each(|item| value_variables(item, usages)),
+        Value::Object(fields) => fields
+            .values()
+            .for_each(|field| value_variables(field, usages)),
+        _ => {}
+    }
+}
+
+// A subscription selects a single field of the root, fragments included.
+fn check_subscription(
+    document: &ExecutableDocument,
+    operation: &OperationDefinition,
+    name: Option<&str>,
+) -> Valid<(), String> {
+    if operation.ty != OperationType::Subscription {
+        return Valid::succeed(());
+    }
+
+    let mut fields = BTreeSet::new();
+    root_fields(
+        document,
+        &operation.selection_set.node,
+        &mut fields,
+        &mut BTreeSet::new(),
+    );
+    if fields.len() > 1 {
+        let name = name.map(|name| format!(" `{}`", name)).unwrap_or_default();
+        Valid::fail(format!(
+            "Subscription{} must select a single field, found {}",
+            name,
+            fields
+                .iter()
+                .map(|field| format!("`{}`", field))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    } else {
+        Valid::succeed(())
+    }
+}
+
+fn root_fields<'a>(
+    document: &'a ExecutableDocument,
+    selection_set: &'a SelectionSet,
+    fields: &mut BTreeSet<&'a str>,
+    fragments: &mut BTreeSet<&'a str>,
+) {
+    for selection in &selection_set.items {
+        match &selection.node {
+            Selection::Field(field) => {
+                fields.insert(field.node.response_key().node.as_str());
+            }
+            Selection::FragmentSpread(spread) => {
+                let name = spread.node.fragment_name.node.as_str();
+                if let Some(fragment) = document.fragments.get(name) {
+                    if fragments.insert(name) {
+                        root_fields(
+                            document,
+                            &fragment.node.selection_set.node,
+                            fields,
+                            fragments,
+                        );
+                    }
+                }
+            }
+            Selection::InlineFragment(fragment) => root_fields(
+                document,
+                &fragment.node.selection_set.node,
+                fields,
+                fragments,
+            ),
+        }
+    }
+}
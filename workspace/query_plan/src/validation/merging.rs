@@ -0,0 +1,234 @@
+use std::collections::BTreeSet;
+
+use async_graphql_parser::types::{
+    ExecutableDocument, Field, OperationType, Selection, SelectionSet,
+};
+use async_graphql_parser::Positioned;
+use blueprint::{FieldDefinition, Index, Pos, Type};
+use indexmap::IndexMap;
+use valid::{Valid, Validator};
+
+use super::{field_definition, is_composite, is_leaf, is_object, operations};
+
+/// Checks that the fields a selection set selects under one response key can
+/// be merged into one, the spec's
+/// [Field Selection Merging](https://spec.graphql.org/October2021/#sec-Field-Selection-Merging).
+pub(crate) fn check(index: &Index, document: &ExecutableDocument) -> Valid<(), String> {
+    let merging = Merging { index, document };
+
+    let mut fragments = document.fragments.iter().collect::<Vec<_>>();
+    fragments.sort_by_key(|(_, fragment)| (fragment.pos.line, fragment.pos.column));
+
+    let operations = Valid::from_iter(operations(document), |(_, operation)| {
+        let root = match operation.node.ty {
+            OperationType::Query => index.get_query(),
+            OperationType::Mutation => index.get_mutation(),
+            OperationType::Subscription => index.get_subscription(),
+        };
+        match root {
+            Some(root) => merging.visit(&operation.node.selection_set.node, root),
+            None => Valid::succeed(()),
+        }
+    });
+    let fragments = Valid::from_iter(fragments, |(_, fragment)| {
+        let on = fragment.node.type_condition.node.on.node.as_str();
+        if is_composite(index, on) {
+            merging.visit(&fragment.node.selection_set.node, on)
+        } else {
+            Valid::succeed(())
+        }
+    });
+
+    operations.and(fragments).unit()
+}
+
+struct Merging<'a> {
+    index: &'a Index,
+    document: &'a ExecutableDocument,
+}
+
+// A field selected within `parent`, with its definition when there is one.
+struct Selected<'a> {
+    parent: &'a str,
+    field: &'a Positioned<Field>,
+    definition: Option<&'a FieldDefinition>,
+}
+
+type Selections<'a> = IndexMap<&'a str, Vec<Selected<'a>>>;
+
+impl<'a> Merging<'a> {
+    // Checks a selection set, then the ones of its fields.
+    fn visit(&self, selection_set: &'a SelectionSet, parent: &'a str) -> Valid<(), String> {
+        let mut selections = Selections::new();
+        self.collect(selection_set, parent, &mut selections, &mut BTreeSet::new());
+
+        let merging = Valid::from_iter(selections.values(), |fields| {
+            Valid::from_iter(fields.iter().enumerate(), |(i, b)| {
+                Valid::from_iter(&fields[..i], |a| self.conflict(a, b, false))
+            })
+        });
+        let nested = self.nested(selection_set, parent);
+
+        merging.and(nested).unit()
+    }
+
+    fn nested(&self, selection_set: &'a SelectionSet, parent: &'a str) -> Valid<(), String> {
+        Valid::from_iter(&selection_set.items, |selection| match &selection.node {
+            Selection::Field(field) => {
+                let selection_set = &field.node.selection_set.node;
+                match field_definition(self.index, parent, &field.node.name.node) {
+                    Some(definition)
+                        if !selection_set.items.is_empty()
+                            && is_composite(self.index, definition.of_type.name()) =>
+                    {
+                        self.visit(selection_set, definition.of_type.name())
+                    }
+                    _ => Valid::succeed(()),
+                }
+            }
+            Selection::FragmentSpread(_) => Valid::succeed(()),
+            Selection::InlineFragment(fragment) => {
+                let on = match &fragment.node.type_condition {
+                    Some(condition) => condition.node.on.node.as_str(),
+                    None => parent,
+                };
+                if is_composite(self.index, on) {
+                    self.nested(&fragment.node.selection_set.node, on)
+                } else {
+                    Valid::succeed(())
+                }
+            }
+        })
+        .unit()
+    }
+
+    // The fields of a selection set by response key, fragments expanded.
+    fn collect(
+        &self,
+        selection_set: &'a SelectionSet,
+        parent: &'a str,
+        selections: &mut Selections<'a>,
+        fragments: &mut BTreeSet<&'a str>,
+    ) {
+        for selection in &selection_set.items {
+            match &selection.node {
+                Selection::Field(field) => {
+                    selections
+                        .entry(field.node.response_key().node.as_str())
+                        .or_default()
+                        .push(Selected {
+                            parent,
+                            field,
+                            definition: field_definition(self.index, parent, &field.node.name.node),
+                        });
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.node.fragment_name.node.as_str();
+                    let Some(fragment) = self.document.fragments.get(name) else {
+                        continue;
+                    };
+                    let on = fragment.node.type_condition.node.on.node.as_str();
+                    if fragments.insert(name) && is_composite(self.index, on) {
+                        self.collect(&fragment.node.selection_set.node, on, selections, fragments);
+                    }
+                }
+                Selection::InlineFragment(fragment) => {
+                    let on = match &fragment.node.type_condition {
+                        Some(condition) => condition.node.on.node.as_str(),
+                        None => parent,
+                    };
+                    if is_composite(self.index, on) {
+                        self.collect(&fragment.node.selection_set.node, on, selections, fragments);
+                    }
+                }
+            }
+        }
+    }
+
+    // Whether two fields under one response key can be merged. Fields of
+    // different object types are never both selected, so they only need to
+    // return values of the same shape.
+    fn conflict(&self, a: &Selected<'a>, b: &Selected<'a>, exclusive: bool) -> Valid<(), String> {
+        let key = a.field.node.response_key().node.as_str();
+        let fail = |reason: String| {
+            Valid::fail(format!(
+                "{}: Field `{}` conflicts with `{}` at {}, as {}",
+                Pos::from(b.field.pos),
+                key,
+                key,
+                Pos::from(a.field.pos),
+                reason
+            ))
+        };
+        let exclusive = exclusive
+            || (a.parent != b.parent
+                && is_object(self.index, a.parent)
+                && is_object(self.index, b.parent));
+
+        if !exclusive {
+            let (x, y) = (&a.field.node.name.node, &b.field.node.name.node);
+            if x != y {
+                return fail(format!("they select different fields `{}` and `{}`", x, y));
+            }
+            if !same_arguments(&a.field.node, &b.field.node) {
+                return fail("they have different arguments".to_string());
+            }
+        }
+
+        let (Some(x), Some(y)) = (a.definition, b.definition) else {
+            return Valid::succeed(());
+        };
+        if !self.same_shape(&x.of_type, &y.of_type) {
+            return fail(format!("they return `{}` and `{}`", x.of_type, y.of_type));
+        }
+
+        // The fields both select under one key merge too.
+        let (x, y) = (x.of_type.name(), y.of_type.name());
+        if !is_composite(self.index, x) || !is_composite(self.index, y) {
+            return Valid::succeed(());
+        }
+        let mut left = Selections::new();
+        self.collect(
+            &a.field.node.selection_set.node,
+            x,
+            &mut left,
+            &mut BTreeSet::new(),
+        );
+        let mut right = Selections::new();
+        self.collect(
+            &b.field.node.selection_set.node,
+            y,
+            &mut right,
+            &mut BTreeSet::new(),
+        );
+
+        Valid::from_iter(left, |(key, left)| match right.get(key) {
+            Some(right) => Valid::from_iter(&left, |a| {
+                Valid::from_iter(right, |b| self.conflict(a, b, exclusive))
+            })
+            .unit(),
+            None => Valid::succeed(()),
+        })
+        .unit()
+    }
+
+    fn same_shape(&self, a: &Type, b: &Type) -> bool {
+        match (a, b) {
+            (Type::Named { name: a, required: x }, Type::Named { name: b, required: y }) => {
+                x == y && (a == b || !(is_leaf(self.index, a) || is_leaf(self.index, b)))
+            }
+            (Type::List { of_type: a, non_null: x }, Type::List { of_type: b, non_null: y }) => {
+                x == y && self.same_shape(a, b)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn same_arguments(a: &Field, b: &Field) -> bool {
+    a.arguments.len() == b.arguments.len()
+        && a.arguments.iter().all(|(name, value)| {
+            b.get_argument(&name.node)
+                .is_some_and(|other| other.node == value.node)
+        })
+}
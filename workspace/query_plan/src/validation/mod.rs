@@ -0,0 +1,268 @@
+mod directives;
+mod document;
+mod merging;
+mod selections;
+mod values;
+
+use std::sync::LazyLock;
+
+use async_graphql_parser::types::{
+    ExecutableDocument, OperationDefinition, Selection, SelectionSet,
+};
+use async_graphql_parser::Positioned;
+use blueprint::{Definition, FieldDefinition, Index, InputFieldDefinition, Type};
+pub use directives::validate_directives;
+use valid::{Valid, Validator};
+
+/// Validates an operation document against the schema clients see, the API
+/// schema, by the rules of the spec's
+/// [Validation](https://spec.graphql.org/October2021/#sec-Validation). Every
+/// violation is reported, prefixed with its position in the document.
+///
+/// Selections of `__schema` and `__type` are only checked when the index has
+/// the introspection types, as the one of [blueprint::Introspection::index].
+pub fn validate(index: &Index, document: &ExecutableDocument) -> Valid<(), String> {
+    let validation = document::check(index, document)
+        .and(selections::check(index, document))
+        .and(merging::check(index, document))
+        .and(validate_directives(index, document));
+
+    // A fragment is checked in every operation spreading it, report what is
+    // wrong with it once.
+    match validation.to_result() {
+        Ok(()) => Valid::succeed(()),
+        Err(error) => {
+            let mut causes = Vec::new();
+            for cause in error.as_vec() {
+                if !causes.contains(cause) {
+                    causes.push(cause.clone());
+                }
+            }
+            Valid::from_vec_cause(causes)
+        }
+    }
+}
+
+const BUILTIN_SCALARS: &[&str] = &["String", "Int", "Float", "Boolean", "ID"];
+
+// The fields every type has, then those only the query root has.
+static META_FIELDS: LazyLock<[FieldDefinition; 3]> = LazyLock::new(|| {
+    let field = |name: &str, args: Vec<InputFieldDefinition>, of_type: Type| FieldDefinition {
+        name: name.into(),
+        args,
+        of_type,
+        directives: Vec::new(),
+        description: None,
+        join_fields: Vec::new(),
+        pos: None,
+    };
+    let name = InputFieldDefinition {
+        name: "name".into(),
+        of_type: Type::named("String").to_required(),
+        default_value: None,
+        directives: Vec::new(),
+        description: None,
+        join_fields: Vec::new(),
+        pos: None,
+    };
+
+    [
+        field(
+            "__typename",
+            Vec::new(),
+            Type::named("String").to_required(),
+        ),
+        field(
+            "__schema",
+            Vec::new(),
+            Type::named("__Schema").to_required(),
+        ),
+        field("__type", vec![name], Type::named("__Type")),
+    ]
+});
+
+// The definition of a field selected on `type_name`, meta-fields included.
+fn field_definition<'a>(
+    index: &'a Index,
+    type_name: &str,
+    name: &str,
+) -> Option<&'a FieldDefinition> {
+    let is_root = index.get_query() == Some(type_name);
+    let meta = match name {
+        "__typename" => META_FIELDS.first(),
+        "__schema" | "__type" if is_root => META_FIELDS.iter().find(|field| field.name == name),
+        _ => None,
+    };
+    if let Some(meta) = meta {
+        return is_composite(index, type_name).then_some(meta);
+    }
+
+    match index.get_type(type_name).map(AsRef::as_ref) {
+        Some(Definition::Object(_) | Definition::Interface(_)) => {
+            match index.get_field(type_name, name)? {
+                blueprint::QueryField::Field(field) => Some(field.definition),
+                blueprint::QueryField::InputField(_) => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_leaf(index: &Index, type_name: &str) -> bool {
+    BUILTIN_SCALARS.contains(&type_name)
+        || matches!(
+            index.get_type(type_name).map(AsRef::as_ref),
+            Some(Definition::Scalar(_) | Definition::Enum(_))
+        )
+}
+
+fn is_composite(index: &Index, type_name: &str) -> bool {
+    matches!(
+        index.get_type(type_name).map(AsRef::as_ref),
+        Some(Definition::Object(_) | Definition::Interface(_) | Definition::Union(_))
+    )
+}
+
+fn is_object(index: &Index, type_name: &str) -> bool {
+    matches!(
+        index.get_type(type_name).map(AsRef::as_ref),
+        Some(Definition::Object(_))
+    )
+}
+
+// The fragments a selection set spreads, directly or in inline fragments.
+fn spreads(selection_set: &SelectionSet) -> Vec<&str> {
+    let mut spreads = Vec::new();
+    for selection in &selection_set.items {
+        match &selection.node {
+            Selection::Field(field) => {
+                spreads.extend(self::spreads(&field.node.selection_set.node))
+            }
+            Selection::FragmentSpread(spread) => {
+                spreads.push(spread.node.fragment_name.node.as_str())
+            }
+            Selection::InlineFragment(fragment) => {
+                spreads.extend(self::spreads(&fragment.node.selection_set.node))
+            }
+        }
+    }
+    spreads
+}
+
+// The operations of a document in the order they are written.
+fn operations(
+    document: &ExecutableDocument,
+) -> Vec<(Option<&str>, &Positioned<OperationDefinition>)> {
+    let mut operations = document
+        .operations
+        .iter()
+        .map(|(name, operation)| (name.map(|name| name.as_str()), operation))
+        .collect::<Vec<_>>();
+    operations.sort_by_key(|(_, operation)| (operation.pos.line, operation.pos.column));
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use blueprint::Blueprint;
+
+    use super::*;
+
+    fn validate(query: &str) -> Valid<(), String> {
+        let introspection = Blueprint::parse(include_str!(
+            "../../../blueprint/src/fixtures/router.graphql"
+        ))
+        .and_then(|blueprint| blueprint.introspection())
+        .to_result()
+        .unwrap();
+        let document = async_graphql_parser::parse_query(query).unwrap();
+
+        super::validate(introspection.index(), &document)
+    }
+
+    #[test]
+    fn test_valid() {
+        let query = r#"
+            query Me($name: String!, $skip: Boolean = false) {
+                __typename
+                __type(name: $name) { name fields { name } }
+                me {
+                    ...User
+                    ... on User { id username @skip(if: $skip) }
+                    reviews { body product { upc } }
+                }
+                topProducts { ...Product price }
+            }
+
+            query Products {
+                topProducts { ...Product }
+            }
+
+            fragment User on User {
+                id
+                profilePicture { url }
+            }
+
+            fragment Product on Product {
+                upc
+                name
+                reviews { author { ...User } }
+            }
+        "#;
+
+        validate(query).to_result().unwrap();
+    }
+
+    #[test]
+    fn test_variable_defaults() {
+        let query = r#"
+            query ($skip: Boolean = "no", $name: String! = null, $include: Boolean = true) {
+                me @skip(if: $skip) @include(if: $include) { username }
+                __type(name: $name) { name }
+            }
+        "#;
+
+        let errors = validate(query).to_result().unwrap_err();
+
+        insta::assert_snapshot!(errors);
+    }
+
+    #[test]
+    fn test_errors() {
+        let query = r#"
+            query Me($id: ID, $id: ID, $unused: Int, $user: User) {
+                me {
+                    name
+                    id(first: 1)
+                    profilePicture
+                    trustworthiness { name }
+                    ...Product
+                    ...Missing
+                    ... on Review { body }
+                }
+                __type { name }
+                topProducts { upc: name upc }
+                alias: me { id }
+                alias: topProducts { upc }
+            }
+
+            fragment Product on Product {
+                upc
+                reviews { ...Review }
+            }
+
+            fragment Review on Review {
+                body
+                product { ...Product }
+            }
+
+            fragment Unused on Trustworthiness {
+                __typename
+            }
+        "#;
+
+        let errors = validate(query).to_result().unwrap_err();
+
+        insta::assert_snapshot!(errors);
+    }
+}
@@ -0,0 +1,174 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+use async_graphql_parser::types::{
+    ExecutableDocument, Field, OperationDefinition, OperationType, Selection, SelectionSet,
+    VariableDefinition,
+};
+use async_graphql_parser::Positioned;
+use blueprint::{Index, Pos};
+use valid::{Valid, Validator};
+
+use super::values::{check_arguments, check_default};
+use super::{field_definition, is_composite, is_leaf, is_object, operations};
+
+/// Checks the selections of every operation against the types they are made
+/// on: that fields are defined and given the arguments they take, that leaves
+/// select nothing and other fields select something, and that fragments are
+/// spread where they can apply. The default values of the variables are
+/// checked along with the values of the arguments.
+pub(crate) fn check(index: &Index, document: &ExecutableDocument) -> Valid<(), String> {
+    Valid::from_iter(operations(document), |(_, operation)| {
+        let selections = Selections {
+            index,
+            document,
+            variables: &operation.node.variable_definitions,
+            fragments: RefCell::default(),
+        };
+        selections.operation(operation)
+    })
+    .unit()
+}
+
+struct Selections<'a> {
+    index: &'a Index,
+    document: &'a ExecutableDocument,
+    variables: &'a [Positioned<VariableDefinition>],
+    // Fragments are checked once for each operation, with its variables.
+    fragments: RefCell<BTreeSet<String>>,
+}
+
+impl Selections<'_> {
+    fn operation(&self, operation: &Positioned<OperationDefinition>) -> Valid<(), String> {
+        let defaults = Valid::from_iter(self.variables, |variable| {
+            check_default(self.index, variable)
+        });
+        let root = match operation.node.ty {
+            OperationType::Query => self.index.get_query(),
+            OperationType::Mutation => self.index.get_mutation(),
+            OperationType::Subscription => self.index.get_subscription(),
+        };
+        let selections = match root {
+            Some(root) => self.selection_set(&operation.node.selection_set.node, root),
+            None => Valid::fail(format!(
+                "{}: The schema has no {} type",
+                Pos::from(operation.pos),
+                crate::OperationType::from(operation.node.ty)
+            )),
+        };
+
+        defaults.and(selections)
+    }
+
+    fn selection_set(&self, selection_set: &SelectionSet, type_name: &str) -> Valid<(), String> {
+        Valid::from_iter(&selection_set.items, |selection| match &selection.node {
+            Selection::Field(field) => self.field(field, type_name),
+            Selection::FragmentSpread(spread) => {
+                let name = spread.node.fragment_name.node.as_str();
+                let at = |message: String| format!("{}: {}", Pos::from(spread.pos), message);
+                let Some(fragment) = self.document.fragments.get(name) else {
+                    return Valid::fail(at(format!("Fragment `{}` is not defined", name)));
+                };
+                let on = fragment.node.type_condition.node.on.node.as_str();
+
+                let is_possible = self.is_possible(type_name, on).map_err(&at);
+                let fragment = if self.fragments.borrow_mut().insert(name.to_string())
+                    && is_composite(self.index, on)
+                {
+                    self.selection_set(&fragment.node.selection_set.node, on)
+                } else {
+                    Valid::succeed(())
+                };
+                is_possible.and(fragment)
+            }
+            Selection::InlineFragment(fragment) => {
+                let at = |message: String| format!("{}: {}", Pos::from(fragment.pos), message);
+                let Some(condition) = &fragment.node.type_condition else {
+                    return self.selection_set(&fragment.node.selection_set.node, type_name);
+                };
+                let on = condition.node.on.node.as_str();
+
+                if self.index.get_type(on).is_none() {
+                    Valid::fail(at(format!("Type `{}` is not defined", on)))
+                } else if !is_composite(self.index, on) {
+                    Valid::fail(at(format!(
+                        "Fragment can not be on `{}`, which has no fields",
+                        on
+                    )))
+                } else {
+                    self.is_possible(type_name, on)
+                        .map_err(&at)
+                        .and(self.selection_set(&fragment.node.selection_set.node, on))
+                }
+            }
+        })
+        .unit()
+    }
+
+    fn field(&self, field: &Positioned<Field>, type_name: &str) -> Valid<(), String> {
+        let name = field.node.name.node.as_str();
+        let at = |message: String| format!("{}: {}", Pos::from(field.pos), message);
+        let Some(definition) = field_definition(self.index, type_name, name) else {
+            return Valid::fail(at(format!(
+                "Field `{}` is not defined on `{}`",
+                name, type_name
+            )));
+        };
+
+        let arguments = check_arguments(
+            self.index,
+            &field.node.arguments,
+            &definition.args,
+            &format!("{}.{}", type_name, name),
+            field.pos.into(),
+            self.variables,
+        );
+
+        let of_type = definition.of_type.name();
+        let selection_set = &field.node.selection_set.node;
+        let selections = if is_leaf(self.index, of_type) {
+            if selection_set.items.is_empty() {
+                Valid::succeed(())
+            } else {
+                Valid::fail(at(format!(
+                    "Field `{}` is of type `{}`, which has no fields to select",
+                    name, definition.of_type
+                )))
+            }
+        } else if is_composite(self.index, of_type) {
+            if selection_set.items.is_empty() {
+                Valid::fail(at(format!(
+                    "Field `{}` is of type `{}`, which must select fields",
+                    name, definition.of_type
+                )))
+            } else {
+                self.selection_set(selection_set, of_type)
+            }
+        } else {
+            // A type the index lacks, as the introspection types of the API
+            // schema: there is nothing to check it against.
+            Valid::succeed(())
+        };
+
+        arguments.and(selections)
+    }
+
+    // A fragment on `on` spread within `type_name` applies to some object.
+    fn is_possible(&self, type_name: &str, on: &str) -> Valid<(), String> {
+        let possible = |type_name: &str| {
+            if is_object(self.index, type_name) {
+                BTreeSet::from([type_name.to_string()])
+            } else {
+                self.index.possible_types(type_name).clone()
+            }
+        };
+        if !is_composite(self.index, on) || !possible(type_name).is_disjoint(&possible(on)) {
+            Valid::succeed(())
+        } else {
+            Valid::fail(format!(
+                "Fragment on `{}` can never apply within `{}`",
+                on, type_name
+            ))
+        }
+    }
+}
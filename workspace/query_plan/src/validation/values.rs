@@ -0,0 +1,219 @@
+use async_graphql_parser::types::VariableDefinition;
+use async_graphql_parser::Positioned;
+use async_graphql_value::{Name, Value};
+use blueprint::{Definition, Index, InputFieldDefinition, Pos, Type};
+use valid::{Valid, Validator};
+
+/// Checks the arguments given to a field or a directive, `owner` as errors
+/// name it, against the ones it takes: that each is defined and given once,
+/// that the required ones are given and that their values are of their types.
+/// Missing arguments are reported at `pos`, the position of the field or the
+/// directive.
+pub(crate) fn check_arguments(
+    index: &Index,
+    arguments: &[(Positioned<Name>, Positioned<Value>)],
+    inputs: &[InputFieldDefinition],
+    owner: &str,
+    pos: Pos,
+    variables: &[Positioned<VariableDefinition>],
+) -> Valid<(), String> {
+    let unknown = Valid::from_iter(arguments.iter().enumerate(), |(i, (argument, _))| {
+        let at = |message: String| format!("{}: {}", Pos::from(argument.pos), message);
+        if arguments[..i]
+            .iter()
+            .any(|(other, _)| other.node == argument.node)
+        {
+            Valid::fail(at(format!(
+                "Argument `{}` is given more than once",
+                argument.node
+            )))
+        } else if inputs
+            .iter()
+            .any(|input| input.name == argument.node.as_str())
+        {
+            Valid::succeed(())
+        } else {
+            Valid::fail(at(format!(
+                "Argument `{}` is not defined on `{}`",
+                argument.node, owner
+            )))
+        }
+    });
+    let inputs = Valid::from_iter(inputs, |input| {
+        let value = arguments
+            .iter()
+            .find(|(argument, _)| argument.node == input.name.as_str());
+        match value {
+            Some((_, value)) => check_value(
+                index,
+                &value.node,
+                &input.of_type,
+                input.default_value.is_some(),
+                variables,
+            )
+            .trace(&input.name)
+            .map_err(&|message: String| format!("{}: {}", Pos::from(value.pos), message)),
+            None if input.of_type.is_required() && input.default_value.is_none() => {
+                Valid::fail(format!(
+                    "{}: Argument `{}` of `{}` is required",
+                    pos, input.name, owner
+                ))
+            }
+            None => Valid::succeed(()),
+        }
+    });
+
+    unknown.and(inputs).unit()
+}
+
+/// Checks the default value of a variable, if it has one, against the type of
+/// the variable.
+pub(crate) fn check_default(
+    index: &Index,
+    variable: &Positioned<VariableDefinition>,
+) -> Valid<(), String> {
+    let Some(value) = &variable.node.default_value else {
+        return Valid::succeed(());
+    };
+
+    check_value(
+        index,
+        &value.node.clone().into_value(),
+        &Type::from(&variable.node.var_type.node),
+        false,
+        &[],
+    )
+    .trace(&format!("${}", variable.node.name.node))
+    .map_err(&|message: String| format!("{}: {}", Pos::from(value.pos), message))
+}
+
+/// Checks a value written in an operation against the type of the argument
+/// it is given for, the spec's
+/// [Values of Correct Type](https://spec.graphql.org/October2021/#sec-Values-of-Correct-Type)
+/// and
+/// [All Variable Usages Are Allowed](https://spec.graphql.org/October2021/#sec-All-Variable-Usages-Are-Allowed).
+/// `has_default` tells whether the argument has a default value.
+pub(crate) fn check_value(
+    index: &Index,
+    value: &Value,
+    of_type: &Type,
+    has_default: bool,
+    variables: &[Positioned<VariableDefinition>],
+) -> Valid<(), String> {
+    if let Value::Variable(name) = value {
+        let Some(variable) = variables
+            .iter()
+            .find(|variable| variable.node.name.node == *name)
+        else {
+            return Valid::fail(format!("Variable `${}` is not defined", name));
+        };
+        let variable_type = Type::from(&variable.node.var_type.node);
+        let has_default = has_default
+            || variable
+                .node
+                .default_value
+                .as_ref()
+                .is_some_and(|value| value.node != async_graphql_value::ConstValue::Null);
+
+        return if variable_type.is_usage_allowed(of_type, has_default) {
+            Valid::succeed(())
+        } else {
+            Valid::fail(format!(
+                "Variable `${}` of type `{}` can not be used where `{}` is expected",
+                name, variable_type, of_type
+            ))
+        };
+    }
+
+    let mismatch = || {
+        Valid::fail(format!(
+            "Expected a value of type `{}`, found `{}`",
+            of_type, value
+        ))
+    };
+
+    match (of_type, value) {
+        (_, Value::Null) if of_type.is_required() => mismatch(),
+        (_, Value::Null) => Valid::succeed(()),
+        (Type::List { of_type: item, .. }, Value::List(items)) => {
+            Valid::from_iter(items, |value| {
+                check_value(index, value, item, false, variables)
+            })
+            .unit()
+        }
+        // A single value is coerced to a list of it.
+        (Type::List { of_type: item, .. }, value) => {
+            check_value(index, value, item, false, variables)
+        }
+        (Type::Named { name, .. }, value) => {
+            let is_valid = match (name.as_str(), value) {
+                ("Int", Value::Number(number)) => number
+                    .as_i64()
+                    .is_some_and(|number| i32::try_from(number).is_ok()),
+                ("Float", Value::Number(_)) => true,
+                ("String", Value::String(_)) => true,
+                ("Boolean", Value::Boolean(_)) => true,
+                ("ID", Value::String(_)) => true,
+                ("ID", Value::Number(number)) => number.is_i64(),
+                ("Int" | "Float" | "String" | "Boolean" | "ID", _) => false,
+                _ => {
+                    return match index.get_type(name).map(AsRef::as_ref) {
+                        Some(Definition::Enum(def)) => match value {
+                            Value::Enum(value)
+                                if def.enum_values.iter().any(|v| v.name == value.as_str()) =>
+                            {
+                                Valid::succeed(())
+                            }
+                            _ => mismatch(),
+                        },
+                        Some(Definition::InputObject(def)) => match value {
+                            Value::Object(fields) => {
+                                let unknown = Valid::from_iter(fields.keys(), |field| {
+                                    if def.fields.iter().any(|input| input.name == field.as_str()) {
+                                        Valid::succeed(())
+                                    } else {
+                                        Valid::fail(format!(
+                                            "Field `{}` is not defined on `{}`",
+                                            field, name
+                                        ))
+                                    }
+                                });
+                                let inputs = Valid::from_iter(&def.fields, |input| {
+                                    match fields.get(input.name.as_str()) {
+                                        Some(value) => check_value(
+                                            index,
+                                            value,
+                                            &input.of_type,
+                                            input.default_value.is_some(),
+                                            variables,
+                                        )
+                                        .trace(&input.name),
+                                        None if input.of_type.is_required()
+                                            && input.default_value.is_none() =>
+                                        {
+                                            Valid::fail(format!(
+                                                "Field `{}` of `{}` is required",
+                                                input.name, name
+                                            ))
+                                        }
+                                        None => Valid::succeed(()),
+                                    }
+                                });
+                                unknown.and(inputs).unit()
+                            }
+                            _ => mismatch(),
+                        },
+                        // Custom scalars take any value.
+                        _ => Valid::succeed(()),
+                    };
+                }
+            };
+
+            if is_valid {
+                Valid::succeed(())
+            } else {
+                mismatch()
+            }
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use async_graphql_value::{ConstValue, Name, Value};
+use blueprint::{Definition, Index, InputFieldDefinition, InputObjectTypeDefinition, Type};
+use indexmap::IndexMap;
+use serde_json::Value as Json;
+use valid::{Valid, Validator};
+
+use crate::VariableDefinition;
+
+/// The variables of a request coerced to the types the operation defines
+/// them with, by the spec's
+/// [Coercing Variable Values](https://spec.graphql.org/October2021/#sec-Coercing-Variable-Values).
+/// A variable is absent when the request leaves out a nullable variable
+/// without a default value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Variables(IndexMap<String, ConstValue>);
+
+impl Variables {
+    /// Coerces the JSON `variables` of a request to `definitions`, applying
+    /// their defaults. Errors are traced with the path to the value inside
+    /// the variable.
+    pub fn coerce(
+        index: &Index,
+        definitions: &[VariableDefinition<Value>],
+        variables: &serde_json::Map<String, Json>,
+    ) -> Valid<Self, String> {
+        Valid::from_iter(definitions, |definition| {
+            let name = &definition.name;
+            let of_type = &definition.of_type;
+            let value = match (variables.get(name), &definition.default_value) {
+                (Some(value), _) => coerce(index, value, of_type).map(Some),
+                // A default is written as GraphQL, which tells enum values
+                // from strings unlike the JSON of the request.
+                (None, Some(default)) => Valid::from_option(
+                    default.clone().into_const(),
+                    "A default value can not use variables".to_string(),
+                )
+                .and_then(|default| coerce_const(index, &default, of_type))
+                .map(Some),
+                (None, None) if of_type.is_required() => {
+                    Valid::fail(format!("Variable of type `{}` is required", of_type))
+                }
+                (None, None) => Valid::succeed(None),
+            };
+
+            value
+                .map(|value| value.map(|value| (name.clone(), value)))
+                .trace(&format!("${}", name))
+        })
+        .map(|variables| Variables(variables.into_iter().flatten().collect()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ConstValue> {
+        self.0.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ConstValue)> {
+        self.0.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The variables as JSON, the way they are sent to subgraphs.
+    pub fn to_json(&self) -> Json {
+        Json::Object(
+            self.0
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone().into_json().unwrap_or_default()))
+                .collect(),
+        )
+    }
+}
+
+fn coerce(index: &Index, value: &Json, of_type: &Type) -> Valid<ConstValue, String> {
+    let mismatch = || {
+        Valid::fail(format!(
+            "Expected a value of type `{}`, found `{}`",
+            of_type, value
+        ))
+    };
+
+    match (of_type, value) {
+        (_, Json::Null) if of_type.is_required() => mismatch(),
+        (_, Json::Null) => Valid::succeed(ConstValue::Null),
+        (Type::List { of_type: item, .. }, Json::Array(items)) => {
+            Valid::from_iter(items.iter().enumerate(), |(i, value)| {
+                coerce(index, value, item).trace(&i.to_string())
+            })
+            .map(ConstValue::List)
+        }
+        // A single value is coerced to a list of it.
+        (Type::List { of_type: item, .. }, value) => {
+            coerce(index, value, item).map(|value| ConstValue::List(vec![value]))
+        }
+        (Type::Named { name, .. }, value) => match (name.as_str(), value) {
+            ("Int", Json::Number(number)) => match number.as_i64() {
+                Some(int) if i32::try_from(int).is_ok() => {
+                    Valid::succeed(ConstValue::Number(number.clone()))
+                }
+                _ => mismatch(),
+            },
+            ("Float", Json::Number(number)) => {
+                match number.as_f64().and_then(serde_json::Number::from_f64) {
+                    Some(float) => Valid::succeed(ConstValue::Number(float)),
+                    None => mismatch(),
+                }
+            }
+            ("String", Json::String(string)) => Valid::succeed(ConstValue::String(string.clone())),
+            ("Boolean", Json::Bool(boolean)) => Valid::succeed(ConstValue::Boolean(*boolean)),
+            ("ID", Json::String(id)) => Valid::succeed(ConstValue::String(id.clone())),
+            ("ID", Json::Number(number)) if number.is_i64() => {
+                Valid::succeed(ConstValue::String(number.to_string()))
+            }
+            ("Int" | "Float" | "String" | "Boolean" | "ID", _) => mismatch(),
+            _ => match index.get_type(name).map(AsRef::as_ref) {
+                Some(Definition::Enum(_)) => match value {
+                    Json::String(value) if index.validate_enum_value(name, value) => {
+                        Valid::succeed(ConstValue::Enum(Name::new(value)))
+                    }
+                    _ => mismatch(),
+                },
+                Some(Definition::InputObject(definition)) => {
+                    let Json::Object(fields) = value else {
+                        return mismatch();
+                    };
+                    coerce_object(
+                        index,
+                        definition,
+                        fields.keys().map(String::as_str),
+                        |input| {
+                            fields
+                                .get(input.name.as_str())
+                                .map(|value| coerce(index, value, &input.of_type))
+                        },
+                    )
+                }
+                // Custom scalars take any value.
+                Some(Definition::Scalar(_)) => Valid::from_option(
+                    ConstValue::from_json(value.clone()).ok(),
+                    format!("Expected a value of type `{}`, found `{}`", of_type, value),
+                ),
+                Some(_) => Valid::fail(format!("`{}` is not an input type", name)),
+                None => Valid::fail(format!("Type `{}` is not defined", name)),
+            },
+        },
+    }
+}
+
+// Coerces a value written in the operation, as the default of a variable.
+// Enum values and strings are told apart: only an enum value is taken for an
+// enum, and only a string for a `String` or an `ID`.
+fn coerce_const(index: &Index, value: &ConstValue, of_type: &Type) -> Valid<ConstValue, String> {
+    let mismatch = || {
+        Valid::fail(format!(
+            "Expected a value of type `{}`, found `{}`",
+            of_type, value
+        ))
+    };
+
+    match (of_type, value) {
+        (_, ConstValue::Null) if of_type.is_required() => mismatch(),
+        (_, ConstValue::Null) => Valid::succeed(ConstValue::Null),
+        (Type::List { of_type: item, .. }, ConstValue::List(items)) => {
+            Valid::from_iter(items.iter().enumerate(), |(i, value)| {
+                coerce_const(index, value, item).trace(&i.to_string())
+            })
+            .map(ConstValue::List)
+        }
+        // A single value is coerced to a list of it.
+        (Type::List { of_type: item, .. }, value) => {
+            coerce_const(index, value, item).map(|value| ConstValue::List(vec![value]))
+        }
+        (Type::Named { name, .. }, value) => match (name.as_str(), value) {
+            ("String" | "ID", ConstValue::String(_)) => Valid::succeed(value.clone()),
+            ("String", _) | ("ID", ConstValue::Enum(_)) => mismatch(),
+            _ => match index.get_type(name).map(AsRef::as_ref) {
+                Some(Definition::Enum(_)) => match value {
+                    ConstValue::Enum(value) if index.validate_enum_value(name, value) => {
+                        Valid::succeed(ConstValue::Enum(value.clone()))
+                    }
+                    _ => mismatch(),
+                },
+                Some(Definition::InputObject(definition)) => {
+                    let ConstValue::Object(fields) = value else {
+                        return mismatch();
+                    };
+                    coerce_object(
+                        index,
+                        definition,
+                        fields.keys().map(Name::as_str),
+                        |input| {
+                            fields
+                                .get(input.name.as_str())
+                                .map(|value| coerce_const(index, value, &input.of_type))
+                        },
+                    )
+                }
+                // Numbers, booleans and custom scalars read the same in JSON.
+                _ => match value.clone().into_json() {
+                    Ok(json) => coerce(index, &json, of_type),
+                    Err(_) => mismatch(),
+                },
+            },
+        },
+    }
+}
+
+// Coerces the fields of an input object given as `fields`, with `coerce_field`
+// coercing the value given for a field if there is one. Fields left out take
+// their default.
+fn coerce_object<'a>(
+    index: &Index,
+    definition: &InputObjectTypeDefinition,
+    fields: impl IntoIterator<Item = &'a str>,
+    coerce_field: impl Fn(&InputFieldDefinition) -> Option<Valid<ConstValue, String>>,
+) -> Valid<ConstValue, String> {
+    let unknown = Valid::from_iter(fields, |field| {
+        if definition.fields.iter().any(|input| input.name == field) {
+            Valid::succeed(())
+        } else {
+            Valid::fail(format!(
+                "Field `{}` is not defined on `{}`",
+                field, definition.name
+            ))
+        }
+    });
+    let inputs = Valid::from_iter(&definition.fields, |input| {
+        let value = match (coerce_field(input), &input.default_value) {
+            (Some(value), _) => value.map(Some),
+            (None, Some(value)) => coerce(index, value, &input.of_type).map(Some),
+            (None, None) if input.of_type.is_required() => {
+                Valid::fail(format!("Field of type `{}` is required", input.of_type))
+            }
+            (None, None) => Valid::succeed(None),
+        };
+        value
+            .map(|value| value.map(|value| (Name::new(&input.name), value)))
+            .trace(&input.name)
+    });
+
+    unknown
+        .and(inputs)
+        .map(|fields| ConstValue::Object(fields.into_iter().flatten().collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use blueprint::Blueprint;
+    use serde_json::json;
+
+    use super::*;
+    use crate::OperationPlan;
+
+    // The fixture has no input types of its own, a few are added for the
+    // variables to be coerced to.
+    fn index() -> Index {
+        let schema = format!(
+            "{}\n{}",
+            include_str!("../../blueprint/src/fixtures/router.graphql"),
+            r#"
+            input ReviewInput {
+                body: String!
+                rating: Int = 5
+                tags: [String!]
+                trust: Trustworthiness
+                product: ProductInput
+            }
+
+            input ProductInput {
+                upc: ID!
+            }
+
+            enum Sort {
+                ASC
+                DESC
+            }
+            "#
+        );
+        Blueprint::parse(&schema).to_result().unwrap().to_index()
+    }
+
+    fn coerce(query: &str, variables: Json) -> Valid<Variables, String> {
+        let document = async_graphql_parser::parse_query(query).unwrap();
+        let plan = OperationPlan::try_new(&document, None).unwrap();
+        let Json::Object(variables) = variables else {
+            panic!("expected an object")
+        };
+
+        Variables::coerce(&index(), &plan.variables, &variables)
+    }
+
+    #[test]
+    fn test_coerce() {
+        let query = r#"
+            query (
+                $review: ReviewInput!
+                $ids: [ID!]!
+                $limit: Int = 10
+                $ratio: Float
+                $trust: Trustworthiness
+                $set: join__FieldSet
+                $missing: String
+                $defaultIds: [ID!] = 1
+                $defaultRatio: Float = 2
+                $sort: Sort = DESC
+                $text: String = "DESC"
+            ) { me { id } }
+        "#;
+        let variables = json!({
+            "review": { "body": "Good", "tags": "new", "product": { "upc": 1 } },
+            "ids": [1, "2"],
+            "ratio": 1,
+            "trust": "KINDA_TRUSTED",
+            "set": { "any": ["value"] },
+        });
+
+        let variables = coerce(query, variables).to_result().unwrap();
+
+        assert_eq!(variables.len(), 10);
+        assert_eq!(variables.get("missing"), None);
+        assert_eq!(variables.get("limit"), Some(&ConstValue::from(10)));
+        assert_eq!(
+            variables.get("trust"),
+            Some(&ConstValue::Enum(Name::new("KINDA_TRUSTED")))
+        );
+        assert_eq!(
+            variables.get("sort"),
+            Some(&ConstValue::Enum(Name::new("DESC")))
+        );
+        assert_eq!(
+            variables.get("text"),
+            Some(&ConstValue::String("DESC".to_string()))
+        );
+        assert_eq!(
+            variables.to_json(),
+            json!({
+                "review": {
+                    "body": "Good",
+                    "rating": 5,
+                    "tags": ["new"],
+                    "product": { "upc": "1" },
+                },
+                "ids": ["1", "2"],
+                "limit": 10,
+                "ratio": 1.0,
+                "trust": "KINDA_TRUSTED",
+                "set": { "any": ["value"] },
+                "defaultIds": ["1"],
+                "defaultRatio": 2.0,
+                "sort": "DESC",
+                "text": "DESC",
+            })
+        );
+    }
+
+    #[test]
+    fn test_coerce_errors() {
+        let query = r#"
+            query (
+                $review: ReviewInput!
+                $reviews: [ReviewInput!]
+                $id: ID!
+                $limit: Int
+                $trust: Trustworthiness
+                $user: User
+                $defaultLimit: Int = "10"
+                $s: String = DESC
+                $sort: Sort = "DESC"
+                $sorts: [Sort!] = [ASC, "DESC"]
+            ) { me { id } }
+        "#;
+        let variables = json!({
+            "review": { "rating": 1.5, "author": "me", "product": {} },
+            "reviews": [{ "body": "Good" }, { "body": "Bad", "tags": ["new", null] }],
+            "limit": 4_000_000_000_i64,
+            "trust": "TRUSTED",
+            "user": { "id": "1" },
+        });
+
+        let errors = coerce(query, variables).to_result().unwrap_err();
+
+        insta::assert_snapshot!(errors);
+    }
+}
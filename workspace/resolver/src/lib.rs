@@ -1,15 +1,25 @@
-use blueprint::{Blueprint, Graph};
+use async_graphql_value::{ConstValue, Value};
+use blueprint::{Blueprint, Graph, Introspection, Selection};
 use futures::future;
-use query_plan::{Fetch, QueryPlan, SelectionSet, TypeName};
+use indexmap::IndexMap;
+use query_plan::{Directive, Fetch, QueryPlan, SelectionSet, TypeName, Variables};
+use valid::Validator;
 
 // TODO: implement reference implementation
 pub trait ResolverContextTrait {
-    fn plan(&self) -> &QueryPlan<serde_json::Value>;
-    fn with_plan(&self, plan: QueryPlan<serde_json::Value>) -> Self;
+    fn plan(&self) -> &QueryPlan<Value>;
+    fn with_plan(&self, plan: QueryPlan<Value>) -> Self;
 
     fn blueprint(&self) -> &Blueprint;
     fn with_blueprint(&self, blueprint: Blueprint) -> Self;
 
+    /// Introspection of the Blueprint, built once per revision of the
+    /// supergraph as [blueprint::Revision::introspection].
+    fn introspection(&self) -> &Introspection;
+
+    /// The variables of the request, coerced to those of the operation.
+    fn variables(&self) -> &Variables;
+
     fn value(&self) -> &serde_json::Value;
     fn value_owned(self) -> serde_json::Value;
     fn with_value(&self, value: serde_json::Value) -> Self;
@@ -77,6 +87,26 @@ pub async fn resolve<Ctx: ResolverContextTrait + Clone>(
 
             res
         }
+        QueryPlan::Introspect(selection_set) => {
+            let selection_set = to_field_set(&selection_set, ctx.variables())?;
+
+            ctx.introspection()
+                .resolve(&selection_set)
+                .to_result()
+                .map_err(|error| anyhow::anyhow!("{}", error))?
+        }
+        QueryPlan::Condition { variable, if_clause, else_clause } => {
+            let clause = match ctx.variables().get(&variable) {
+                Some(ConstValue::Boolean(true)) => if_clause,
+                _ => else_clause,
+            };
+
+            match clause {
+                Some(plan) => resolve(ctx.with_plan(*plan)).await?,
+                // Nothing to merge into the value.
+                None => serde_json::Value::Object(Default::default()),
+            }
+        }
         QueryPlan::Flatten { select, plan } => {
             let path_value = select.get(ctx.value().clone());
 
@@ -113,6 +143,80 @@ fn merge(value: serde_json::Value, other_value: serde_json::Value) -> serde_json
     }
 }
 
+// Introspection is answered from the Blueprint, which takes selections the
+// way field sets are written: with the variables bound and without the fields
+// `@skip` and `@include` leave out.
+fn to_field_set(
+    selection_set: &SelectionSet<Value>,
+    variables: &Variables,
+) -> anyhow::Result<blueprint::SelectionSet> {
+    let mut selections = Vec::new();
+    for field in selection_set.iter() {
+        if !is_included(&field.directives, variables)? {
+            continue;
+        }
+
+        let mut arguments = IndexMap::new();
+        for argument in &field.arguments {
+            // An argument given an absent variable is left out, so that its
+            // default applies.
+            if let Value::Variable(name) = &argument.value {
+                if variables.get(name).is_none() {
+                    continue;
+                }
+            }
+            arguments.insert(argument.name.clone(), bind(&argument.value, variables)?);
+        }
+
+        let selection = Selection::Field(blueprint::Field {
+            alias: field.alias.clone(),
+            name: field.name.clone(),
+            arguments,
+            selections: to_field_set(&field.selections, variables)?,
+        });
+
+        // Fields selected through fragments keep their type conditions.
+        selections.push(match &field.type_condition {
+            Some(condition) => Selection::InlineFragment(blueprint::InlineFragment {
+                type_condition: Some(condition.as_str().to_string()),
+                selections: blueprint::SelectionSet(vec![selection]),
+            }),
+            None => selection,
+        });
+    }
+
+    Ok(blueprint::SelectionSet(selections))
+}
+
+// Whether the `@skip` and `@include` of a field let it be selected.
+fn is_included(directives: &[Directive<Value>], variables: &Variables) -> anyhow::Result<bool> {
+    for directive in directives {
+        let include = match directive.name.as_str() {
+            "include" => true,
+            "skip" => false,
+            _ => continue,
+        };
+        let condition = directive
+            .arguments
+            .iter()
+            .find(|argument| argument.name == "if")
+            .map(|argument| bind(&argument.value, variables))
+            .transpose()?;
+        if condition.is_some_and(|condition| condition != ConstValue::Boolean(include)) {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+// Substitutes the variables of the request into a value, absent ones as null.
+fn bind(value: &Value, variables: &Variables) -> anyhow::Result<ConstValue> {
+    value
+        .clone()
+        .into_const_with(|name| Ok(variables.get(&name).cloned().unwrap_or(ConstValue::Null)))
+}
+
 fn prepare_req<Value>(
     _blueprint: &Blueprint,
     _service: &Option<Graph>,
@@ -124,3 +228,60 @@ fn prepare_req<Value>(
     // TODO: prepare request
     todo!()
 }
+
+#[cfg(test)]
+mod tests {
+    use query_plan::OperationPlan;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_introspection_variables() {
+        let introspection =
+            Blueprint::parse(include_str!("../../blueprint/src/fixtures/router.graphql"))
+                .and_then(|blueprint| blueprint.introspection())
+                .to_result()
+                .unwrap();
+        let query = r#"
+            query ($name: String!, $brief: Boolean!) {
+                __type(name: $name) {
+                    name
+                    kind @skip(if: $brief)
+                    fields @include(if: $brief) { name }
+                }
+            }
+        "#;
+        let document = async_graphql_parser::parse_query(query).unwrap();
+        let operation = OperationPlan::try_new(&document, None).unwrap();
+        let QueryPlan::Introspect(selection_set) = operation.plan else {
+            panic!("expected an introspection plan")
+        };
+        let serde_json::Value::Object(variables) = json!({ "name": "Review", "brief": true })
+        else {
+            unreachable!()
+        };
+        let variables = Variables::coerce(introspection.index(), &operation.variables, &variables)
+            .to_result()
+            .unwrap();
+
+        let selection_set = to_field_set(&selection_set, &variables).unwrap();
+        let value = introspection.resolve(&selection_set).to_result().unwrap();
+
+        assert_eq!(
+            value,
+            json!({
+                "__type": {
+                    "name": "Review",
+                    "fields": [
+                        { "name": "id" },
+                        { "name": "body" },
+                        { "name": "pictures" },
+                        { "name": "product" },
+                        { "name": "author" },
+                    ],
+                },
+            })
+        );
+    }
+}
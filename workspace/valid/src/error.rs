@@ -93,6 +93,7 @@ mod tests {
 
     use crate::{Cause, Error};
 
+    #[allow(dead_code)]
     #[derive(Debug, PartialEq, serde::Deserialize)]
     struct Foo {
         a: i32,